@@ -27,6 +27,7 @@ GPU_ID=0
 MULTI_GPU=true
 GPU_BATCHES=40000
 API_PORT=3333
+API_TOKEN=""
 
 # User config can override settings (JSON format expected)
 if [[ ! -z $CUSTOM_USER_CONFIG ]]; then
@@ -42,6 +43,7 @@ if [[ ! -z $CUSTOM_USER_CONFIG ]]; then
     MULTI_GPU=$(echo "$CUSTOM_USER_CONFIG" | jq -r '.multi_gpu // true' 2>/dev/null)
     GPU_BATCHES=$(echo "$CUSTOM_USER_CONFIG" | jq -r '.gpu_batches // 40000' 2>/dev/null)
     API_PORT=$(echo "$CUSTOM_USER_CONFIG" | jq -r '.api_port // 3333' 2>/dev/null)
+    API_TOKEN=$(echo "$CUSTOM_USER_CONFIG" | jq -r '.api_token // empty' 2>/dev/null)
 fi
 
 # Create config file
@@ -56,7 +58,10 @@ GPU_ID=$GPU_ID
 MULTI_GPU=$MULTI_GPU
 GPU_BATCHES=$GPU_BATCHES
 API_PORT=$API_PORT
+API_TOKEN="$API_TOKEN"
 EOF
+# The API token is a secret
+chmod 600 $CUSTOM_CONFIG_FILENAME
 
 echo "Xenom miner config generated:"
 cat $CUSTOM_CONFIG_FILENAME
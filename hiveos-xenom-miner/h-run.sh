@@ -58,6 +58,7 @@ export GPU_ID
 export MULTI_GPU
 export GPU_BATCHES
 export API_PORT
+export API_TOKEN
 
 # Run the mining loop
 exec $MINER_DIR/mine-loop.sh 2>&1 | tee -a $LOG_FILE
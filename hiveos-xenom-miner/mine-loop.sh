@@ -10,6 +10,7 @@ GPU_ID="${GPU_ID:-0}"
 MULTI_GPU="${MULTI_GPU:-true}"
 MV_LEN="${MV_LEN:-16}"
 API_PORT="${API_PORT:-3333}"
+API_TOKEN="${API_TOKEN:-}"
 
 echo "🚀 Starting Xenom GPU Miner"
 echo "   Node URL: $NODE_URL"
@@ -28,6 +29,11 @@ if [ ! -z "$MINER_ADDRESS" ]; then
     MINER_CMD="$MINER_CMD --miner-address $MINER_ADDRESS"
 fi
 
+# Passed through the environment so the token does not show up in the process list
+if [ ! -z "$API_TOKEN" ]; then
+    export XENOM_API_TOKEN="$API_TOKEN"
+fi
+
 # Add GPU ID if not multi-GPU
 if [ "$MULTI_GPU" != "true" ]; then
     MINER_CMD="$MINER_CMD --gpu-id $GPU_ID"
@@ -1,6 +1,7 @@
+#[cfg(feature = "cuda")]
 use std::env;
+#[cfg(feature = "cuda")]
 use std::path::PathBuf;
-use std::process::Command;
 
 fn main() {
     #[cfg(feature = "cuda")]
@@ -25,9 +26,9 @@ fn main() {
         
         // Use simplified Blake3 implementation
         let output = std::process::Command::new("nvcc")
-            .args(&[
+            .args([
                 "--ptx",
-                "src/blake3_simple.cu",
+                cu_file,
                 "-o",
                 ptx_file.to_str().unwrap(),
                 &format!("-arch={}", arch),
@@ -0,0 +1,523 @@
+//! Placement of the CPU mining threads (`--cpu-*` options): the CPUs they are pinned to,
+//! their scheduling priority and the NUMA node their memory comes from. It is resolved
+//! once at startup and applied by every CPU search thread when it starts, in single-shot
+//! and loop modes alike.
+
+use clap::ValueEnum;
+use log::warn;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+/// Under the sysfs root
+const CPU_DIR: &str = "devices/system/cpu";
+const NODE_DIR: &str = "devices/system/node";
+
+/// Scheduling policy of the CPU mining threads
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Priority {
+    /// Regular time-sharing (SCHED_OTHER)
+    #[default]
+    Normal,
+    /// Fewer wake-up preemptions, for long CPU-bound work (SCHED_BATCH)
+    Batch,
+    /// Only runs when nothing else wants the CPU (SCHED_IDLE)
+    Idle,
+}
+
+/// Sorted CPU or NUMA node ids, written as ranges like `0-3,8,10-11`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdList(pub Vec<usize>);
+
+/// Parses `0-3,8,10-11` (the format of `taskset -c` and the sysfs `*_list` files)
+pub fn parse_list(s: &str) -> Result<IdList, String> {
+    let mut ids = Vec::new();
+    for part in s.split(',').map(str::trim) {
+        let parse = |n: &str| n.trim().parse::<usize>().map_err(|_| format!("invalid id `{}` in `{}`", n.trim(), s));
+        match part.split_once('-') {
+            Some((first, last)) => {
+                let (first, last) = (parse(first)?, parse(last)?);
+                if first > last {
+                    return Err(format!("range `{}` ends before it starts", part));
+                }
+                ids.extend(first..=last);
+            }
+            None => ids.push(parse(part)?),
+        }
+    }
+    ids.sort_unstable();
+    ids.dedup();
+    Ok(IdList(ids))
+}
+
+impl fmt::Display for IdList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut ids = self.0.iter().copied().peekable();
+        let mut first = true;
+        while let Some(start) = ids.next() {
+            let mut end = start;
+            while ids.peek() == Some(&(end + 1)) {
+                end = ids.next().unwrap_or(end);
+            }
+            let sep = if first { "" } else { "," };
+            first = false;
+            // Like the kernel's lists, any run of two or more is a range
+            if end == start {
+                write!(f, "{}{}", sep, start)?;
+            } else {
+                write!(f, "{}{}-{}", sep, start, end)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Placement options as given on the command line
+#[derive(Debug, Clone)]
+pub struct PlacementConfig {
+    /// Where sysfs is mounted (`--sysfs-root`)
+    pub sysfs: PathBuf,
+    /// CPUs to mine on (default: every CPU the process may use)
+    pub cpus: Option<IdList>,
+    /// Only CPUs of these NUMA nodes
+    pub numa_nodes: Option<IdList>,
+    /// One CPU per physical core
+    pub no_smt: bool,
+    /// Physical cores left free, lowest-numbered first
+    pub reserve: usize,
+    pub priority: Priority,
+    pub nice: Option<i32>,
+}
+
+impl PlacementConfig {
+    /// Whether the options restrict the CPUs, so the threads must be pinned
+    fn pins(&self) -> bool {
+        self.cpus.is_some() || self.numa_nodes.is_some() || self.no_smt || self.reserve > 0
+    }
+}
+
+/// A logical CPU and where it sits
+struct Cpu {
+    id: usize,
+    node: usize,
+    /// Hyperthread siblings sharing its physical core, itself included
+    siblings: Vec<usize>,
+}
+
+impl Cpu {
+    fn read(cpu_dir: &Path, id: usize) -> Self {
+        let dir = cpu_dir.join(format!("cpu{}", id));
+        let siblings = std::fs::read_to_string(dir.join("topology/thread_siblings_list"))
+            .ok()
+            .and_then(|list| parse_list(list.trim()).ok())
+            .map_or_else(|| vec![id], |list| list.0);
+        // The kernel links each CPU to its node as `cpuN/nodeM`
+        let node = std::fs::read_dir(&dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .find_map(|entry| entry.file_name().to_str()?.strip_prefix("node")?.parse().ok())
+            .unwrap_or(0);
+        Self { id, node, siblings }
+    }
+
+    /// Lowest sibling, identifying the physical core
+    fn core(&self) -> usize {
+        self.siblings.first().copied().unwrap_or(self.id)
+    }
+}
+
+/// Resolved placement of the CPU mining threads
+#[derive(Debug, Clone, Default)]
+pub struct Placement {
+    /// CPUs worker `i` is pinned to as `cpus[i % len]`: first one CPU of every core, then
+    /// their siblings, each pass grouped by NUMA node. Empty if threads are not pinned.
+    cpus: Vec<usize>,
+    /// Physical cores among `cpus`
+    cores: usize,
+    priority: Priority,
+    nice: Option<i32>,
+}
+
+impl Placement {
+    /// Selects the CPUs from the topology in sysfs. Fails on CPUs or nodes that do not
+    /// exist or are outside the process's affinity mask, or if no CPU is left.
+    pub fn resolve(config: &PlacementConfig) -> Result<Self, String> {
+        let mut placement = Self {
+            priority: config.priority,
+            nice: config.nice,
+            ..Self::default()
+        };
+        if (config.pins() || placement.changes_priority()) && !sys::SUPPORTED {
+            return Err("the --cpu-* placement options are only supported on Linux".to_string());
+        }
+        if !config.pins() {
+            return Ok(placement);
+        }
+
+        let allowed = sys::allowed_cpus().map_err(|e| format!("cannot read the CPU affinity mask: {}", e))?;
+        placement.select(config, allowed)?;
+        Ok(placement)
+    }
+
+    /// Selects the CPUs among `allowed` (the affinity mask) for `resolve`
+    fn select(&mut self, config: &PlacementConfig, allowed: Vec<usize>) -> Result<(), String> {
+        let ids = match &config.cpus {
+            Some(list) => {
+                if let Some(cpu) = list.0.iter().find(|cpu| !allowed.contains(cpu)) {
+                    return Err(format!(
+                        "CPU {} is not available to this process (allowed: {})",
+                        cpu,
+                        IdList(allowed.clone())
+                    ));
+                }
+                list.0.clone()
+            }
+            None => allowed,
+        };
+        let cpu_dir = config.sysfs.join(CPU_DIR);
+        let mut cpus: Vec<Cpu> = ids.into_iter().map(|id| Cpu::read(&cpu_dir, id)).collect();
+
+        if let Some(nodes) = &config.numa_nodes {
+            // Kernels without NUMA support have no node directory and a single node 0
+            let node_dir = config.sysfs.join(NODE_DIR);
+            let exists = |node: usize| match node_dir.exists() {
+                true => node_dir.join(format!("node{}", node)).exists(),
+                false => node == 0,
+            };
+            if let Some(node) = nodes.0.iter().find(|&&node| !exists(node)) {
+                return Err(format!("NUMA node {} does not exist", node));
+            }
+            cpus.retain(|cpu| nodes.0.contains(&cpu.node));
+        }
+        if config.reserve > 0 {
+            let mut cores: Vec<usize> = cpus.iter().map(Cpu::core).collect();
+            cores.sort_unstable();
+            cores.dedup();
+            cores.truncate(config.reserve);
+            cpus.retain(|cpu| !cores.contains(&cpu.core()));
+        }
+
+        // Position among the selected siblings of its core: 0 for the first hyperthread
+        let rank = |cpu: &Cpu, cpus: &[Cpu]| {
+            cpus.iter()
+                .filter(|other| other.id < cpu.id && cpu.siblings.contains(&other.id))
+                .count()
+        };
+        let mut ranked: Vec<(usize, usize, usize)> = cpus
+            .iter()
+            .map(|cpu| (rank(cpu, &cpus), cpu.node, cpu.id))
+            .filter(|&(rank, _, _)| rank == 0 || !config.no_smt)
+            .collect();
+        ranked.sort_unstable();
+        if ranked.is_empty() {
+            return Err("no CPUs are left for mining after --cpu-affinity, --cpu-numa-nodes and --cpu-reserve".to_string());
+        }
+
+        self.cores = ranked.iter().filter(|&&(rank, _, _)| rank == 0).count();
+        self.cpus = ranked.into_iter().map(|(_, _, id)| id).collect();
+        Ok(())
+    }
+
+    pub fn is_pinned(&self) -> bool {
+        !self.cpus.is_empty()
+    }
+
+    /// Whether the threads run at other than the default priority
+    pub fn changes_priority(&self) -> bool {
+        self.priority != Priority::Normal || self.nice.is_some()
+    }
+
+    /// Mining threads when `--threads` is 0: one per selected CPU, else one per logical CPU
+    pub fn threads(&self) -> usize {
+        if self.is_pinned() {
+            self.cpus.len()
+        } else {
+            num_cpus::get()
+        }
+    }
+
+    /// Logical and physical cores the mining threads can use
+    pub fn cores(&self) -> (usize, usize) {
+        if self.is_pinned() {
+            (self.cpus.len(), self.cores)
+        } else {
+            (num_cpus::get(), num_cpus::get_physical())
+        }
+    }
+
+    /// Selected CPUs, e.g. `2-7`
+    pub fn cpus(&self) -> IdList {
+        let mut cpus = self.cpus.clone();
+        cpus.sort_unstable();
+        IdList(cpus)
+    }
+
+    /// Applies the placement to the calling thread, the `worker`th mining thread. Pinned
+    /// threads also allocate from their local NUMA node, so buffers they create are
+    /// node-local. Failures are logged once and the thread mines unplaced.
+    pub fn apply(&self, worker: usize) {
+        if let Some(&cpu) = self.cpus.get(worker % self.cpus.len().max(1)) {
+            report(sys::pin(cpu).and_then(|()| sys::local_memory()), || format!("pin a mining thread to CPU {}", cpu));
+        }
+        if self.priority != Priority::Normal {
+            report(sys::set_policy(self.priority), || {
+                format!("set the {:?} scheduling policy", self.priority).to_lowercase()
+            });
+        }
+        if let Some(nice) = self.nice {
+            report(sys::set_nice(nice), || format!("set nice {}", nice));
+        }
+    }
+}
+
+impl fmt::Display for Placement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_pinned() {
+            write!(f, "CPUs {} ({} thread(s) on {} core(s))", self.cpus(), self.cpus.len(), self.cores)?;
+        } else {
+            write!(f, "all CPUs")?;
+        }
+        if self.priority != Priority::Normal {
+            write!(f, ", {} priority", format!("{:?}", self.priority).to_lowercase())?;
+        }
+        if let Some(nice) = self.nice {
+            write!(f, ", nice {}", nice)?;
+        }
+        Ok(())
+    }
+}
+
+/// Warns about the first failure only: every search round starts new threads
+fn report(result: std::io::Result<()>, action: impl FnOnce() -> String) {
+    static WARNED: AtomicBool = AtomicBool::new(false);
+    if let Err(e) = result {
+        if !WARNED.swap(true, Ordering::Relaxed) {
+            warn!("⚠️  Cannot {}: {} (further placement errors are not logged)", action(), e);
+        }
+    }
+}
+
+static PLACEMENT: OnceLock<Placement> = OnceLock::new();
+
+/// Sets the placement of all CPU mining threads; only the first call has an effect
+pub fn init(placement: Placement) {
+    let _ = PLACEMENT.set(placement);
+}
+
+/// The placement set by `init`, or none
+pub fn current() -> &'static Placement {
+    PLACEMENT.get_or_init(Placement::default)
+}
+
+#[cfg(target_os = "linux")]
+mod sys {
+    use super::Priority;
+    use std::io;
+
+    pub const SUPPORTED: bool = true;
+    /// `MPOL_LOCAL` from <linux/mempolicy.h>: allocate on the node of the running CPU
+    const MPOL_LOCAL: libc::c_int = 4;
+
+    fn check(ret: libc::c_long) -> io::Result<()> {
+        if ret < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// CPUs in the process's affinity mask (cpusets and `taskset` restrict it)
+    pub fn allowed_cpus() -> io::Result<Vec<usize>> {
+        // SAFETY: `set` is a plain bit mask that the kernel fills in
+        unsafe {
+            let mut set: libc::cpu_set_t = std::mem::zeroed();
+            check(libc::sched_getaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &mut set) as libc::c_long)?;
+            Ok((0..libc::CPU_SETSIZE as usize).filter(|&cpu| libc::CPU_ISSET(cpu, &set)).collect())
+        }
+    }
+
+    /// Pins the calling thread (pid 0 is the calling thread on Linux)
+    pub fn pin(cpu: usize) -> io::Result<()> {
+        // SAFETY: as above; CPU_SET ignores CPUs past CPU_SETSIZE
+        unsafe {
+            let mut set: libc::cpu_set_t = std::mem::zeroed();
+            libc::CPU_SET(cpu, &mut set);
+            check(libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) as libc::c_long)
+        }
+    }
+
+    /// Makes the calling thread allocate from the NUMA node it runs on
+    pub fn local_memory() -> io::Result<()> {
+        // SAFETY: MPOL_LOCAL takes no node mask
+        let ret = unsafe { libc::syscall(libc::SYS_set_mempolicy, MPOL_LOCAL, std::ptr::null::<libc::c_ulong>(), 0) };
+        match check(ret) {
+            // Kernels without NUMA support have a single node anyway
+            Err(e) if e.raw_os_error() == Some(libc::ENOSYS) => Ok(()),
+            result => result,
+        }
+    }
+
+    pub fn set_policy(priority: Priority) -> io::Result<()> {
+        let policy = match priority {
+            Priority::Normal => libc::SCHED_OTHER,
+            Priority::Batch => libc::SCHED_BATCH,
+            Priority::Idle => libc::SCHED_IDLE,
+        };
+        let param = libc::sched_param { sched_priority: 0 };
+        // SAFETY: `param` outlives the call
+        check(unsafe { libc::sched_setscheduler(0, policy, &param) } as libc::c_long)
+    }
+
+    /// Sets the nice value of the calling thread only (Linux applies it per thread id)
+    pub fn set_nice(nice: i32) -> io::Result<()> {
+        // SAFETY: plain syscalls without pointers
+        unsafe {
+            let tid = libc::syscall(libc::SYS_gettid) as libc::id_t;
+            check(libc::setpriority(libc::PRIO_PROCESS as _, tid, nice) as libc::c_long)
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod sys {
+    use super::Priority;
+    use std::io;
+
+    pub const SUPPORTED: bool = false;
+
+    fn unsupported() -> io::Error {
+        io::Error::new(io::ErrorKind::Unsupported, "not supported on this platform")
+    }
+
+    pub fn allowed_cpus() -> io::Result<Vec<usize>> {
+        Ok((0..num_cpus::get()).collect())
+    }
+
+    pub fn pin(_cpu: usize) -> io::Result<()> {
+        Err(unsupported())
+    }
+
+    pub fn local_memory() -> io::Result<()> {
+        Err(unsupported())
+    }
+
+    pub fn set_policy(_priority: Priority) -> io::Result<()> {
+        Err(unsupported())
+    }
+
+    pub fn set_nice(_nice: i32) -> io::Result<()> {
+        Err(unsupported())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    #[test]
+    fn id_lists_round_trip() {
+        for list in ["0-3,8,10-11", "0", "1-2", "0-7", "2,4,6", "5,7-9,11"] {
+            assert_eq!(parse_list(list).unwrap().to_string(), list);
+        }
+        assert_eq!(parse_list("0-3,8,10-11").unwrap().0, [0, 1, 2, 3, 8, 10, 11]);
+        // Sorted and deduplicated, whitespace tolerated
+        assert_eq!(parse_list(" 8, 1-2 ,2,3").unwrap().to_string(), "1-3,8");
+        assert_eq!(IdList(vec![4, 5]).to_string(), "4-5");
+        assert_eq!(IdList(Vec::new()).to_string(), "");
+
+        assert_eq!(parse_list("3-1").unwrap_err(), "range `3-1` ends before it starts");
+        assert_eq!(parse_list("0,x").unwrap_err(), "invalid id `x` in `0,x`");
+        assert!(parse_list("").is_err());
+        assert!(parse_list("1-").is_err());
+    }
+
+    /// Two NUMA nodes of two cores each, with two hyperthreads per core:
+    /// node 0 has CPUs 0-3, node 1 CPUs 4-7, and CPUs 2n and 2n+1 share a core
+    fn topology() -> TestDir {
+        let dir = TestDir::new("affinity");
+        for cpu in 0..8 {
+            let core = cpu / 2 * 2;
+            dir.write(
+                &format!("{}/cpu{}/topology/thread_siblings_list", CPU_DIR, cpu),
+                &format!("{}-{}\n", core, core + 1),
+            );
+            std::fs::create_dir_all(dir.path().join(format!("{}/cpu{}/node{}", CPU_DIR, cpu, cpu / 4))).unwrap();
+        }
+        for node in 0..2 {
+            std::fs::create_dir_all(dir.path().join(format!("{}/node{}", NODE_DIR, node))).unwrap();
+        }
+        dir
+    }
+
+    fn select(dir: &TestDir, configure: impl FnOnce(&mut PlacementConfig)) -> Result<Placement, String> {
+        let mut config = PlacementConfig {
+            sysfs: dir.path().to_path_buf(),
+            cpus: None,
+            numa_nodes: None,
+            no_smt: false,
+            reserve: 0,
+            priority: Priority::Normal,
+            nice: None,
+        };
+        configure(&mut config);
+        let mut placement = Placement::default();
+        placement.select(&config, (0..8).collect()).map(|()| placement)
+    }
+
+    #[test]
+    fn resolve_spreads_over_cores_then_siblings() {
+        let dir = topology();
+        let all = select(&dir, |_| ()).unwrap();
+        assert_eq!(all.cpus, [0, 2, 4, 6, 1, 3, 5, 7]);
+        assert_eq!(all.cores(), (8, 4));
+        assert_eq!(all.to_string(), "CPUs 0-7 (8 thread(s) on 4 core(s))");
+
+        let listed = select(&dir, |c| c.cpus = Some(parse_list("1-3,6").unwrap())).unwrap();
+        assert_eq!(listed.cpus, [1, 2, 6, 3]);
+        assert_eq!(listed.cores, 3);
+        let missing = select(&dir, |c| c.cpus = Some(parse_list("7-8").unwrap())).unwrap_err();
+        assert_eq!(missing, "CPU 8 is not available to this process (allowed: 0-7)");
+    }
+
+    #[test]
+    fn resolve_applies_no_smt_reserve_and_numa_nodes() {
+        let dir = topology();
+        let no_smt = select(&dir, |c| c.no_smt = true).unwrap();
+        assert_eq!(no_smt.cpus, [0, 2, 4, 6]);
+        assert_eq!(no_smt.cores(), (4, 4));
+
+        // The lowest-numbered core is left free
+        let reserve = select(&dir, |c| c.reserve = 1).unwrap();
+        assert_eq!(reserve.cpus, [2, 4, 6, 3, 5, 7]);
+        assert_eq!(reserve.cpus().to_string(), "2-7");
+
+        let node = select(&dir, |c| c.numa_nodes = Some(parse_list("1").unwrap())).unwrap();
+        assert_eq!(node.cpus, [4, 6, 5, 7]);
+        assert_eq!(node.cores, 2);
+
+        let combined = select(&dir, |c| {
+            c.numa_nodes = Some(parse_list("1").unwrap());
+            c.no_smt = true;
+            c.reserve = 1;
+        })
+        .unwrap();
+        assert_eq!(combined.cpus, [6]);
+
+        let unknown = select(&dir, |c| c.numa_nodes = Some(parse_list("0,2").unwrap())).unwrap_err();
+        assert_eq!(unknown, "NUMA node 2 does not exist");
+        assert!(select(&dir, |c| c.reserve = 4).unwrap_err().starts_with("no CPUs are left"));
+    }
+
+    #[test]
+    fn resolve_without_topology_files() {
+        // No sysfs at all: every CPU is its own core on node 0
+        let dir = TestDir::new("affinity-empty");
+        let placement = select(&dir, |c| c.no_smt = true).unwrap();
+        assert_eq!(placement.cpus, (0..8).collect::<Vec<_>>());
+        assert!(select(&dir, |c| c.numa_nodes = Some(parse_list("0").unwrap())).is_ok());
+        assert!(select(&dir, |c| c.numa_nodes = Some(parse_list("1").unwrap())).is_err());
+    }
+}
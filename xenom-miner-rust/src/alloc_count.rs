@@ -0,0 +1,67 @@
+//! Counting global allocator of the test build, for checking that the CPU search loops
+//! do not allocate. Only allocations on threads that called `track` are counted, so
+//! other tests running alongside do not disturb a measurement; measurements themselves
+//! are serialized by `measure`.
+
+use parking_lot::Mutex;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+struct Counting;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static MEASURING: Mutex<()> = Mutex::new(());
+
+thread_local! {
+    static TRACKED: Cell<bool> = const { Cell::new(false) };
+}
+
+// SAFETY: forwards to the system allocator; the counter neither allocates nor touches the memory
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count();
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        count();
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count();
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+fn count() {
+    if TRACKED.try_with(Cell::get).unwrap_or(false) {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Counts the allocations of the calling thread from now on, until `untrack`
+pub fn track() {
+    TRACKED.with(|tracked| tracked.set(true));
+}
+
+pub fn untrack() {
+    TRACKED.with(|tracked| tracked.set(false));
+}
+
+/// Runs `f` alone among measurements and returns its result with the allocations
+/// counted on tracked threads meanwhile
+pub fn measure<T>(f: impl FnOnce() -> T) -> (T, u64) {
+    let _guard = MEASURING.lock();
+    ALLOCATIONS.store(0, Ordering::Relaxed);
+    let result = f();
+    (result, ALLOCATIONS.load(Ordering::Relaxed))
+}
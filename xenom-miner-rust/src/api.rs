@@ -0,0 +1,617 @@
+use crate::control::{constant_time_eq, MinerControl};
+use crate::events::{EventBus, EventEnvelope};
+use crate::hive::HiveReport;
+use crate::stats::{HealthPolicy, MinerStats};
+use log::{error, info};
+use parking_lot::RwLock;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::Duration;
+
+type Response = tiny_http::Response<std::io::Cursor<Vec<u8>>>;
+
+/// Dashboard assets, embedded so the binary serves them with nothing else installed
+const DASHBOARD_HTML: &str = include_str!("dashboard/index.html");
+const DASHBOARD_JS: &str = include_str!("dashboard/dashboard.js");
+const DASHBOARD_CSS: &str = include_str!("dashboard/dashboard.css");
+
+/// Listener and access settings for the stats API server
+#[derive(Debug, Clone, Default)]
+pub struct ApiConfig {
+    /// Listen addresses: `host:port` or `unix:/path/to/socket`
+    pub binds: Vec<String>,
+    /// Bearer token required for every route except `/health*`, `/control` and the dashboard assets
+    pub token: Option<String>,
+    /// PEM certificate chain and private key; both must be set to enable TLS
+    pub tls_cert: Option<PathBuf>,
+    pub tls_key: Option<PathBuf>,
+    /// Origins allowed to read the API from a browser (`*` allows any)
+    pub cors_origins: Vec<String>,
+    /// Thresholds for `/health/live` and `/health/ready`
+    pub health: HealthPolicy,
+}
+
+impl ApiConfig {
+    fn cors_allows(&self, origin: &str) -> bool {
+        self.cors_origins.iter().any(|o| o == "*" || o.eq_ignore_ascii_case(origin))
+    }
+
+    fn load_tls(&self) -> Result<Option<tiny_http::SslConfig>, String> {
+        match (&self.tls_cert, &self.tls_key) {
+            (None, None) => Ok(None),
+            (Some(cert), Some(key)) => {
+                if !cfg!(feature = "tls") {
+                    return Err("TLS support not compiled. Build with --features tls".to_string());
+                }
+                let certificate = std::fs::read(cert)
+                    .map_err(|e| format!("failed to read TLS certificate {}: {}", cert.display(), e))?;
+                let private_key = std::fs::read(key)
+                    .map_err(|e| format!("failed to read TLS key {}: {}", key.display(), e))?;
+                Ok(Some(tiny_http::SslConfig {
+                    certificate,
+                    private_key,
+                }))
+            }
+            _ => Err("--api-tls-cert and --api-tls-key must be given together".to_string()),
+        }
+    }
+}
+
+/// Idle time after which an SSE comment is sent to keep proxies from closing the stream
+const EVENT_KEEPALIVE: Duration = Duration::from_secs(15);
+
+/// Largest request body read, far above any control request
+const MAX_BODY: usize = 64 * 1024;
+
+struct ApiContext {
+    stats: Arc<RwLock<MinerStats>>,
+    control: Arc<MinerControl>,
+    events: Arc<EventBus>,
+    /// Shared so token and CORS changes from a config reload apply to the next request
+    config: Arc<RwLock<ApiConfig>>,
+}
+
+impl ApiContext {
+    fn authorized(&self, request: &tiny_http::Request) -> bool {
+        match &self.config.read().token {
+            None => true,
+            Some(token) => header_value(request, "Authorization")
+                .and_then(|h| h.strip_prefix("Bearer "))
+                .is_some_and(|given| constant_time_eq(token.as_bytes(), given.trim().as_bytes())),
+        }
+    }
+}
+
+fn header(name: &str, value: &str) -> tiny_http::Header {
+    tiny_http::Header::from_bytes(name.as_bytes(), value.as_bytes()).unwrap()
+}
+
+pub fn json_response(status: u16, body: String) -> Response {
+    tiny_http::Response::from_string(body)
+        .with_status_code(status)
+        .with_header(header("Content-Type", "application/json"))
+}
+
+fn asset_response(content_type: &str, body: &'static str) -> Response {
+    tiny_http::Response::from_string(body)
+        .with_header(header("Content-Type", content_type))
+        .with_header(header("Cache-Control", "no-cache"))
+}
+
+pub fn header_value<'a>(request: &'a tiny_http::Request, name: &str) -> Option<&'a str> {
+    request
+        .headers()
+        .iter()
+        .find(|h| h.field.as_str().as_str().eq_ignore_ascii_case(name))
+        .map(|h| h.value.as_str())
+}
+
+/// Reads a request body of at most `MAX_BODY` bytes. Larger bodies give a 413 response.
+fn read_body(request: &mut tiny_http::Request) -> Result<String, (u16, String)> {
+    let too_large = || (413, format!(r#"{{"error":"request body larger than {} bytes"}}"#, MAX_BODY));
+    if request.body_length().is_some_and(|len| len > MAX_BODY) {
+        return Err(too_large());
+    }
+    let mut body = String::new();
+    request
+        .as_reader()
+        .take(MAX_BODY as u64 + 1)
+        .read_to_string(&mut body)
+        .map_err(|e| (400, serde_json::json!({ "error": format!("failed to read body: {}", e) }).to_string()))?;
+    if body.len() > MAX_BODY {
+        return Err(too_large());
+    }
+    Ok(body)
+}
+
+fn parse_body<T: serde::de::DeserializeOwned>(body: &str) -> Result<T, String> {
+    serde_json::from_str(body).map_err(|e| format!("invalid JSON body: {}", e))
+}
+
+/// Handles `/control/*`. Every route requires `Authorization: Bearer <control token>`.
+fn handle_control(request: &mut tiny_http::Request, path: &str, control: &MinerControl) -> (u16, String) {
+    if !control.enabled() {
+        return (403, r#"{"error":"control API disabled (start the miner with --control-token)"}"#.to_string());
+    }
+    if !control.authorize(header_value(request, "Authorization")) {
+        return (401, r#"{"error":"unauthorized"}"#.to_string());
+    }
+
+    let is_post = *request.method() == tiny_http::Method::Post;
+    let body = if is_post {
+        match read_body(request) {
+            Ok(body) => body,
+            Err(response) => return response,
+        }
+    } else {
+        String::new()
+    };
+    let result = match (path, is_post) {
+        ("/control", false) => {
+            let body = serde_json::json!({
+                "paused": control.is_paused(),
+                "settings": control.settings(),
+            });
+            return (200, body.to_string());
+        }
+        ("/control/pause", true) => {
+            control.pause();
+            Ok(())
+        }
+        ("/control/resume", true) => {
+            control.resume();
+            Ok(())
+        }
+        ("/control/refresh", true) => {
+            control.request_refresh();
+            Ok(())
+        }
+        ("/control/node", true) => parse_body(&body).and_then(|req| control.set_node(req)),
+        ("/control/address", true) => parse_body(&body).and_then(|req| control.set_address(req)),
+        ("/control/intensity", true) => parse_body(&body).and_then(|req| control.set_intensity(req)),
+        ("/control/throttle", true) => parse_body(&body).and_then(|req| control.set_throttle(req)),
+        (
+            "/control" | "/control/pause" | "/control/resume" | "/control/refresh" | "/control/node"
+            | "/control/address" | "/control/intensity" | "/control/throttle",
+            _,
+        ) => return (405, r#"{"error":"method not allowed"}"#.to_string()),
+        _ => return (404, r#"{"error":"not found"}"#.to_string()),
+    };
+
+    match result {
+        Ok(()) => {
+            info!("🎛️  Control request applied: {}", path);
+            (200, r#"{"status":"ok"}"#.to_string())
+        }
+        Err(e) => (400, serde_json::json!({ "error": e }).to_string()),
+    }
+}
+
+/// Streams events as Server-Sent Events until the client disconnects.
+/// `types` limits the stream to the given event types.
+fn stream_events(
+    request: tiny_http::Request,
+    rx: Receiver<Arc<EventEnvelope>>,
+    types: Option<Vec<String>>,
+    cors_origin: Option<String>,
+) {
+    // tiny_http buffers chunked bodies, so the response is written by hand and flushed per event
+    let mut writer = request.into_writer();
+    let mut head = String::from(
+        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: close\r\n",
+    );
+    if let Some(origin) = cors_origin {
+        head.push_str(&format!("Access-Control-Allow-Origin: {}\r\nVary: Origin\r\n", origin));
+    }
+    head.push_str("\r\nretry: 3000\n\n");
+    if writer.write_all(head.as_bytes()).and_then(|_| writer.flush()).is_err() {
+        return;
+    }
+
+    loop {
+        let chunk = match rx.recv_timeout(EVENT_KEEPALIVE) {
+            Ok(envelope) => {
+                let kind = envelope.event.kind();
+                if types.as_ref().is_some_and(|t| !t.iter().any(|k| k == kind)) {
+                    continue;
+                }
+                let data = serde_json::to_string(&*envelope).unwrap_or_else(|_| "{}".to_string());
+                format!("id: {}\nevent: {}\ndata: {}\n\n", envelope.id, kind, data)
+            }
+            Err(RecvTimeoutError::Timeout) => ": keepalive\n\n".to_string(),
+            Err(RecvTimeoutError::Disconnected) => return,
+        };
+        // A write error means the client went away; dropping `rx` unsubscribes it
+        if writer.write_all(chunk.as_bytes()).and_then(|_| writer.flush()).is_err() {
+            return;
+        }
+    }
+}
+
+/// Value of a query string parameter, percent-decoded
+fn query_param(url: &str, name: &str) -> Option<String> {
+    url.split_once('?')?
+        .1
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| percent_decode(key) == name)
+        .map(|(_, value)| percent_decode(value))
+}
+
+/// Decodes `%XX` escapes and `+` (space) of a form-encoded query component.
+/// Malformed escapes are kept as they are.
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| s.get(i + 1..i + 3))
+            .flatten()
+            .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], escaped) {
+            (_, Some(byte)) => {
+                out.push(byte);
+                i += 3;
+                continue;
+            }
+            (b'+', None) => out.push(b' '),
+            (byte, None) => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn handle_request(mut request: tiny_http::Request, ctx: &ApiContext) {
+    let url = request.url().to_string();
+    let path = url.split('?').next().unwrap_or_default();
+    let cors_origin = header_value(&request, "Origin")
+        .filter(|origin| ctx.config.read().cors_allows(origin))
+        .map(str::to_string);
+
+    if path == "/events" && *request.method() == tiny_http::Method::Get && ctx.authorized(&request) {
+        let types = query_param(&url, "types").map(|t| t.split(',').map(str::to_string).collect());
+        let rx = ctx.events.subscribe();
+        std::thread::spawn(move || stream_events(request, rx, types, cors_origin));
+        return;
+    }
+
+    let response = if *request.method() == tiny_http::Method::Options {
+        // CORS preflight
+        tiny_http::Response::from_string(String::new())
+            .with_status_code(204)
+            .with_header(header("Access-Control-Allow-Methods", "GET, POST, OPTIONS"))
+            .with_header(header("Access-Control-Allow-Headers", "Authorization, Content-Type"))
+    } else if matches!(path, "/" | "/dashboard" | "/dashboard/") {
+        // Static assets carry no data; the page asks for the token when /stats needs one
+        asset_response("text/html; charset=utf-8", DASHBOARD_HTML)
+    } else if path == "/dashboard/dashboard.js" {
+        asset_response("application/javascript; charset=utf-8", DASHBOARD_JS)
+    } else if path == "/dashboard/dashboard.css" {
+        asset_response("text/css; charset=utf-8", DASHBOARD_CSS)
+    } else if path == "/control" || path.starts_with("/control/") {
+        let (status, body) = handle_control(&mut request, path, &ctx.control);
+        json_response(status, body)
+    } else if !matches!(path, "/health" | "/health/live" | "/health/ready") && !ctx.authorized(&request) {
+        json_response(401, r#"{"error":"unauthorized"}"#.to_string())
+    } else {
+        match path {
+            "/stats" | "/api/stats" => {
+                let mut stats_data = ctx.stats.read().clone();
+                // Clients such as `hive-stats` see the live uptime, not the one of the last update
+                stats_data.uptime_secs = stats_data.now();
+                let json = serde_json::to_string_pretty(&stats_data).unwrap_or_else(|_| "{}".to_string());
+                json_response(200, json)
+            }
+            "/hive" => {
+                let report = HiveReport::from_stats(&ctx.stats.read());
+                json_response(200, serde_json::to_string(&report).unwrap_or_else(|_| "{}".to_string()))
+            }
+            "/health" => {
+                // Unauthenticated, so only device names and worker states
+                let stats = ctx.stats.read();
+                let status = stats.health();
+                let devices: Vec<_> = stats
+                    .devices
+                    .iter()
+                    .map(|d| serde_json::json!({"name": d.name, "health": d.health}))
+                    .collect();
+                let body = serde_json::json!({"status": status, "devices": devices});
+                json_response(if status == "unhealthy" { 503 } else { 200 }, body.to_string())
+            }
+            "/health/live" | "/health/ready" => {
+                let policy = ctx.config.read().health.clone();
+                let stats = ctx.stats.read();
+                let reasons = if path == "/health/live" {
+                    stats.liveness(&policy)
+                } else {
+                    stats.readiness(&policy)
+                };
+                if reasons.is_empty() {
+                    json_response(200, r#"{"status":"ok"}"#.to_string())
+                } else {
+                    json_response(503, serde_json::json!({"status": "fail", "reasons": reasons}).to_string())
+                }
+            }
+            _ => json_response(404, r#"{"error":"not found"}"#.to_string()),
+        }
+    };
+
+    let response = match cors_origin {
+        Some(origin) => response
+            .with_header(header("Access-Control-Allow-Origin", &origin))
+            .with_header(header("Vary", "Origin")),
+        None => response,
+    };
+    let _ = request.respond(response);
+}
+
+/// Binds one listen address. `unix:` addresses create a Unix domain socket,
+/// which is always served as plain HTTP since it never leaves the host.
+/// `systemd` (or `systemd:<FileDescriptorName>`) serves a socket passed by systemd socket activation.
+fn bind(
+    addr: &str,
+    ssl: Option<tiny_http::SslConfig>,
+) -> Result<tiny_http::Server, Box<dyn std::error::Error + Send + Sync>> {
+    if addr == "systemd" || addr.starts_with("systemd:") {
+        #[cfg(unix)]
+        {
+            let (_, listener) = crate::systemd::take_listener(addr.strip_prefix("systemd:"))?;
+            let ssl = match listener {
+                tiny_http::Listener::Tcp(_) => ssl,
+                _ => None,
+            };
+            return tiny_http::Server::from_listener(listener, ssl);
+        }
+        #[cfg(not(unix))]
+        return Err("socket activation is not supported on this platform".into());
+    }
+    if let Some(path) = addr.strip_prefix("unix:") {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::FileTypeExt;
+            // Remove a stale socket left behind by a previous run
+            if let Ok(meta) = std::fs::symlink_metadata(path) {
+                if meta.file_type().is_socket() {
+                    std::fs::remove_file(path)?;
+                }
+            }
+            return tiny_http::Server::http_unix(std::path::Path::new(path));
+        }
+        #[cfg(not(unix))]
+        return Err(format!("Unix sockets are not supported on this platform: {}", path).into());
+    }
+
+    tiny_http::Server::new(tiny_http::ServerConfig {
+        addr: tiny_http::ConfigListenAddr::from_socket_addrs(addr)?,
+        ssl,
+    })
+}
+
+pub fn start_api_server(
+    stats: Arc<RwLock<MinerStats>>,
+    control: Arc<MinerControl>,
+    events: Arc<EventBus>,
+    config: Arc<RwLock<ApiConfig>>,
+) {
+    let (binds, ssl) = {
+        let config = config.read();
+        (config.binds.clone(), config.load_tls())
+    };
+    let ssl = match ssl {
+        Ok(ssl) => ssl,
+        Err(e) => {
+            error!("❌ Failed to start stats API server: {}", e);
+            return;
+        }
+    };
+    let scheme = if ssl.is_some() { "https" } else { "http" };
+    let ctx = Arc::new(ApiContext {
+        stats,
+        control,
+        events,
+        config,
+    });
+
+    for addr in binds {
+        let server = match bind(&addr, ssl.clone()) {
+            Ok(s) => {
+                if let Some(path) = addr.strip_prefix("unix:") {
+                    info!("📊 Stats API server started on unix socket {}", path);
+                } else if addr.starts_with("systemd") {
+                    info!("📊 Stats API server started on socket-activated {}", s.server_addr());
+                } else {
+                    info!("📊 Stats API server started on {}://{}", scheme, addr);
+                }
+                s
+            }
+            Err(e) => {
+                error!("❌ Failed to start stats API server on {}: {}", addr, e);
+                continue;
+            }
+        };
+
+        let ctx = Arc::clone(&ctx);
+        std::thread::spawn(move || {
+            for request in server.incoming_requests() {
+                handle_request(request, &ctx);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::control::RuntimeSettings;
+    use crate::stats::StatsTracker;
+    use tiny_http::{Method, TestRequest};
+
+    fn control(token: Option<&str>) -> Arc<MinerControl> {
+        MinerControl::new(
+            RuntimeSettings {
+                node_urls: vec!["http://localhost:36669".to_string()],
+                active_node: 0,
+                miner_address: None,
+                threads: 1,
+                batches: 1,
+                population: 1,
+                throttle: Default::default(),
+            },
+            token.map(str::to_string),
+        )
+    }
+
+    fn context(config: ApiConfig) -> ApiContext {
+        ApiContext {
+            stats: StatsTracker::new(Vec::new()).get_stats(),
+            control: control(None),
+            events: EventBus::new(),
+            config: Arc::new(RwLock::new(config)),
+        }
+    }
+
+    fn bearer(token: &str) -> tiny_http::Header {
+        header("Authorization", &format!("Bearer {}", token))
+    }
+
+    fn post(path: &str, body: &'static str) -> tiny_http::Request {
+        TestRequest::new()
+            .with_method(Method::Post)
+            .with_path(path)
+            .with_header(bearer("control"))
+            .with_body(body)
+            .into()
+    }
+
+    #[test]
+    fn token_is_checked_when_configured() {
+        let open = context(ApiConfig::default());
+        assert!(open.authorized(&TestRequest::new().into()));
+
+        let ctx = context(ApiConfig {
+            token: Some("secret".to_string()),
+            ..Default::default()
+        });
+        assert!(!ctx.authorized(&TestRequest::new().into()));
+        assert!(!ctx.authorized(&TestRequest::new().with_header(bearer("wrong")).into()));
+        assert!(!ctx.authorized(&TestRequest::new().with_header(header("Authorization", "secret")).into()));
+        assert!(ctx.authorized(&TestRequest::new().with_header(bearer("secret")).into()));
+
+        // A reload replaces the token for the next request
+        ctx.config.write().token = None;
+        assert!(ctx.authorized(&TestRequest::new().into()));
+    }
+
+    #[test]
+    fn cors_allowlist() {
+        let config = ApiConfig {
+            cors_origins: vec!["https://dash.example".to_string()],
+            ..Default::default()
+        };
+        assert!(config.cors_allows("https://dash.example"));
+        assert!(config.cors_allows("HTTPS://DASH.EXAMPLE"));
+        assert!(!config.cors_allows("https://evil.example"));
+        assert!(!ApiConfig::default().cors_allows("https://dash.example"));
+
+        let any = ApiConfig {
+            cors_origins: vec!["*".to_string()],
+            ..Default::default()
+        };
+        assert!(any.cors_allows("https://evil.example"));
+    }
+
+    #[test]
+    fn control_routes_need_the_control_token() {
+        let disabled = control(None);
+        assert_eq!(handle_control(&mut post("/control/pause", ""), "/control/pause", &disabled).0, 403);
+
+        let control = control(Some("control"));
+        let mut request: tiny_http::Request =
+            TestRequest::new().with_method(Method::Post).with_header(bearer("stats")).into();
+        assert_eq!(handle_control(&mut request, "/control/pause", &control).0, 401);
+        assert!(!control.is_paused());
+
+        assert_eq!(handle_control(&mut post("/control/pause", ""), "/control/pause", &control).0, 200);
+        assert!(control.is_paused());
+        let mut get: tiny_http::Request = TestRequest::new().with_header(bearer("control")).into();
+        assert_eq!(handle_control(&mut get, "/control/pause", &control).0, 405);
+        assert_eq!(handle_control(&mut post("/control/nope", ""), "/control/nope", &control).0, 404);
+    }
+
+    #[test]
+    fn control_bodies_are_parsed_and_limited() {
+        let control = control(Some("control"));
+        let path = "/control/intensity";
+        assert_eq!(handle_control(&mut post(path, r#"{"batches": 8}"#), path, &control).0, 200);
+        assert_eq!(control.settings().batches, 8);
+        assert_eq!(handle_control(&mut post(path, "{"), path, &control).0, 400);
+        assert_eq!(handle_control(&mut post(path, r#"{"batches": 0}"#), path, &control).0, 400);
+
+        let huge: &'static str = format!(r#"{{"batches": 1{}}}"#, " ".repeat(MAX_BODY)).leak();
+        assert_eq!(handle_control(&mut post(path, huge), path, &control).0, 413);
+        assert_eq!(control.settings().batches, 8);
+    }
+
+    #[test]
+    fn query_params_are_decoded() {
+        let url = "/events?x=1&types=new_template%2Csolution_found&q=a+b%zz&t%79pe=%E2%9C%93";
+        assert_eq!(query_param(url, "types").as_deref(), Some("new_template,solution_found"));
+        assert_eq!(query_param(url, "q").as_deref(), Some("a b%zz"));
+        assert_eq!(query_param(url, "type").as_deref(), Some("✓"));
+        assert_eq!(query_param(url, "missing"), None);
+        assert_eq!(query_param("/events", "types"), None);
+        assert_eq!(percent_decode("%+1%2"), "% 1%2");
+    }
+
+    #[test]
+    fn event_stream_keeps_only_the_requested_types() {
+        use crate::events::MinerEvent;
+        use std::io::{BufRead, BufReader};
+
+        let ctx = context(ApiConfig::default());
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let addr = server.server_addr().to_ip().unwrap();
+        let events = Arc::clone(&ctx.events);
+        std::thread::spawn(move || handle_request(server.recv().unwrap(), &ctx));
+
+        let mut stream = std::net::TcpStream::connect(addr).unwrap();
+        stream.set_read_timeout(Some(std::time::Duration::from_secs(10))).unwrap();
+        stream
+            .write_all(b"GET /events?types=stale_abort%2Cdevice_error HTTP/1.1\r\nHost: test\r\n\r\n")
+            .unwrap();
+        let mut lines = BufReader::new(stream).lines().map(Result::unwrap);
+        // The subscription is made before the head is sent
+        assert_eq!(lines.next().unwrap(), "HTTP/1.1 200 OK");
+        assert!(lines.by_ref().any(|line| line == "retry: 3000"));
+
+        events.publish(MinerEvent::Hashrate {
+            total_mhs: 1.0,
+            per_device_mhs: vec![1.0],
+            total_hashes: 1,
+        });
+        events.publish(MinerEvent::DeviceError {
+            device: 0,
+            error: "lost".to_string(),
+        });
+        events.publish(MinerEvent::NodeFailover {
+            from: "http://a".to_string(),
+            to: "http://b".to_string(),
+            error: "timeout".to_string(),
+        });
+        events.publish(MinerEvent::StaleAbort {
+            height: 5,
+            reason: "template refresh requested".to_string(),
+        });
+
+        let received: Vec<String> = lines.filter(|line| !line.is_empty()).take(6).collect();
+        assert_eq!(received[..2], ["id: 2", "event: device_error"]);
+        assert!(received[2].starts_with("data: {\"id\":2,") && received[2].contains("\"error\":\"lost\""));
+        assert_eq!(received[3..5], ["id: 4", "event: stale_abort"]);
+        assert!(received[5].contains("\"height\":5"));
+    }
+}
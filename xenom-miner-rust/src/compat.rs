@@ -0,0 +1,325 @@
+use crate::api::{header_value, json_response, ApiConfig};
+use crate::control::constant_time_eq;
+use crate::hive::HIVE_ALGO;
+use crate::stats::MinerStats;
+use log::{error, info};
+use parking_lot::RwLock;
+use serde_json::{json, Value};
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Largest Claymore request accepted; real ones are well under 100 bytes
+const CLAYMORE_MAX_REQUEST: usize = 4096;
+
+/// Claymore connections served at once; further ones are closed right away
+const CLAYMORE_MAX_CONNECTIONS: usize = 16;
+
+/// Time a Claymore client gets to send its request or read the answer
+const CLAYMORE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Counts a Claymore connection until it is dropped
+struct ConnectionSlot(Arc<AtomicUsize>);
+
+impl ConnectionSlot {
+    fn acquire(open: &Arc<AtomicUsize>) -> Option<Self> {
+        open.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| (n < CLAYMORE_MAX_CONNECTIONS).then_some(n + 1))
+            .ok()
+            .map(|_| Self(Arc::clone(open)))
+    }
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+fn join(values: impl Iterator<Item = String>) -> String {
+    values.collect::<Vec<_>>().join(";")
+}
+
+fn active_node(stats: &MinerStats) -> Option<&crate::stats::NodeHealth> {
+    stats.nodes.iter().find(|n| n.active)
+}
+
+/// `result` array of Claymore's `miner_getstat1`. Hashrates are in kH/s;
+/// temperatures and fans are left empty since the miner does not read them.
+pub fn claymore_stats(stats: &MinerStats) -> Value {
+    let khs = |mhs: f64| ((mhs * 1000.0) as u64).to_string();
+    let pool = active_node(stats).map(|n| n.url.as_str()).unwrap_or("");
+    let pool = pool.split("://").last().unwrap_or(pool);
+
+    json!([
+        format!("{} - XNM", stats.version),
+        (stats.now() / 60).to_string(),
+        format!("{};{};{}", khs(stats.hashrate_mhs), stats.accepted_shares, stats.rejected_shares),
+        join(stats.per_gpu_hashrate_mhs.iter().map(|&h| khs(h))),
+        "0;0;0",
+        join(stats.per_gpu_hashrate_mhs.iter().map(|_| "off".to_string())),
+        "",
+        pool,
+        format!("{};0;0;0", stats.invalid_shares),
+    ])
+}
+
+/// Body of xmrig's `GET /1/summary`, restricted to what `MinerStats` tracks.
+/// Hashrates are in H/s; only the latest rate is known, so all xmrig windows carry it.
+pub fn xmrig_summary(stats: &MinerStats) -> Value {
+    let hs = |mhs: f64| mhs * 1_000_000.0;
+    let total = hs(stats.hashrate_mhs);
+    let node = active_node(stats);
+    let worker_id = std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .map(|h| h.trim().to_string())
+        .unwrap_or_else(|_| "xenom".to_string());
+
+    json!({
+        "id": worker_id,
+        "worker_id": worker_id,
+        "uptime": stats.now(),
+        "restricted": true,
+        "version": stats.version,
+        "kind": "miner",
+        "ua": format!("xenom-miner-rust/{}", stats.version),
+        "algo": HIVE_ALGO,
+        "paused": stats.paused,
+        "hashrate": {
+            "total": [total, total, total],
+            "highest": total,
+            "threads": stats.per_gpu_hashrate_mhs.iter().map(|&h| [hs(h), hs(h), hs(h)]).collect::<Vec<_>>(),
+        },
+        "results": {
+            "diff_current": 0,
+            "shares_good": stats.accepted_shares,
+            "shares_total": stats.accepted_shares + stats.rejected_shares + stats.invalid_shares,
+            "hashes_total": stats.total_hashes,
+            "error_log": [],
+        },
+        "connection": {
+            "pool": node.map(|n| n.url.clone()),
+            "uptime": stats.current_template.as_ref().map(|t| stats.now().saturating_sub(t.received_at)),
+            "ping": node.and_then(|n| n.latency_ms),
+            "failures": node.map(|n| n.consecutive_failures).unwrap_or(0),
+            "accepted": stats.accepted_shares,
+            "rejected": stats.rejected_shares,
+            "algo": HIVE_ALGO,
+        },
+    })
+}
+
+/// Reads one JSON request; Claymore clients do not always send a trailing newline
+fn read_claymore_request(stream: &mut TcpStream) -> Option<Value> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 512];
+    loop {
+        let n = stream.read(&mut chunk).ok()?;
+        if n == 0 {
+            return serde_json::from_slice(&buf).ok();
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        if let Ok(value) = serde_json::from_slice(&buf) {
+            return Some(value);
+        }
+        if buf.len() > CLAYMORE_MAX_REQUEST || buf.contains(&b'\n') {
+            return serde_json::from_slice(&buf).ok();
+        }
+    }
+}
+
+fn handle_claymore(mut stream: TcpStream, stats: &RwLock<MinerStats>, config: &RwLock<ApiConfig>) {
+    let _ = stream.set_read_timeout(Some(CLAYMORE_TIMEOUT));
+    let _ = stream.set_write_timeout(Some(CLAYMORE_TIMEOUT));
+    let Some(request) = read_claymore_request(&mut stream) else {
+        return;
+    };
+    let id = request.get("id").cloned().unwrap_or(json!(0));
+    let method = request["method"].as_str().unwrap_or("");
+    let authorized = match &config.read().token {
+        Some(psw) => request["psw"]
+            .as_str()
+            .is_some_and(|given| constant_time_eq(psw.as_bytes(), given.as_bytes())),
+        None => true,
+    };
+
+    let response = if !authorized {
+        json!({"id": id, "jsonrpc": "2.0", "result": null, "error": "invalid password"})
+    } else if method == "miner_getstat1" || method == "miner_getstat2" {
+        json!({"id": id, "jsonrpc": "2.0", "result": claymore_stats(&stats.read()), "error": null})
+    } else {
+        json!({"id": id, "jsonrpc": "2.0", "result": null, "error": "method not supported"})
+    };
+    let _ = stream.write_all(format!("{}\n", response).as_bytes());
+}
+
+/// Serves Claymore's `miner_getstat1` JSON-RPC over plain TCP, at most
+/// `CLAYMORE_MAX_CONNECTIONS` clients at a time.
+/// With an API token set, requests must carry it in `psw` as Claymore's `-mpsw` does.
+pub fn start_claymore_server(stats: Arc<RwLock<MinerStats>>, addr: &str, config: Arc<RwLock<ApiConfig>>) {
+    let listener = match TcpListener::bind(addr) {
+        Ok(l) => l,
+        Err(e) => {
+            error!("❌ Failed to start Claymore API on {}: {}", addr, e);
+            return;
+        }
+    };
+    info!("📊 Claymore-compatible API started on {}", addr);
+
+    let open = Arc::new(AtomicUsize::new(0));
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            // Dropping the stream closes a connection over the limit
+            let Some(slot) = ConnectionSlot::acquire(&open) else {
+                continue;
+            };
+            let (stats, config) = (Arc::clone(&stats), Arc::clone(&config));
+            std::thread::spawn(move || {
+                let _slot = slot;
+                handle_claymore(stream, &stats, &config);
+            });
+        }
+    });
+}
+
+/// Serves xmrig's `/1/summary` (and `/2/summary`) HTTP API.
+/// With an API token set, requests need `Authorization: Bearer <token>` like xmrig's access token.
+pub fn start_xmrig_server(stats: Arc<RwLock<MinerStats>>, addr: &str, config: Arc<RwLock<ApiConfig>>) {
+    let server = match tiny_http::Server::http(addr) {
+        Ok(s) => s,
+        Err(e) => {
+            error!("❌ Failed to start xmrig API on {}: {}", addr, e);
+            return;
+        }
+    };
+    info!("📊 xmrig-compatible API started on http://{}", addr);
+
+    std::thread::spawn(move || {
+        for request in server.incoming_requests() {
+            let authorized = match &config.read().token {
+                Some(token) => header_value(&request, "Authorization")
+                    .and_then(|h| h.strip_prefix("Bearer "))
+                    .is_some_and(|given| constant_time_eq(token.as_bytes(), given.trim().as_bytes())),
+                None => true,
+            };
+            let path = request.url().split('?').next().unwrap_or("");
+            let response = if !authorized {
+                json_response(401, r#"{"error":"unauthorized"}"#.to_string())
+            } else if matches!(path, "/1/summary" | "/2/summary" | "/api.json") {
+                json_response(200, xmrig_summary(&stats.read()).to_string())
+            } else {
+                json_response(404, r#"{"error":"not found"}"#.to_string())
+            };
+            let _ = request.respond(response);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::{NodeHealth, TemplateInfo};
+
+    fn stats() -> MinerStats {
+        let mut stats = MinerStats::default();
+        stats.version = "1.2.3".to_string();
+        stats.uptime_secs = 7260;
+        stats.hashrate_mhs = 3.5;
+        stats.per_gpu_hashrate_mhs = vec![2.25, 1.2505];
+        stats.total_hashes = 123_456_789;
+        (stats.accepted_shares, stats.rejected_shares, stats.invalid_shares) = (7, 2, 1);
+        stats.nodes = vec![
+            NodeHealth {
+                url: "http://backup:36669".to_string(),
+                active: false,
+                reachable: true,
+                latency_ms: Some(90),
+                last_success: None,
+                last_error: None,
+                consecutive_failures: 0,
+            },
+            NodeHealth {
+                url: "https://node.example:36669".to_string(),
+                active: true,
+                reachable: true,
+                latency_ms: Some(12),
+                last_success: Some(7200),
+                last_error: None,
+                consecutive_failures: 1,
+            },
+        ];
+        stats.current_template = Some(TemplateInfo {
+            height: 100,
+            difficulty_bits: "1d00ffff".to_string(),
+            target_hex: "00ff".to_string(),
+            received_at: 7200,
+        });
+        stats
+    }
+
+    #[test]
+    fn claymore_result_array() {
+        assert_eq!(
+            claymore_stats(&stats()),
+            json!([
+                "1.2.3 - XNM",
+                "121",
+                "3500;7;2",
+                "2250;1250",
+                "0;0;0",
+                "off;off",
+                "",
+                "node.example:36669",
+                "1;0;0;0",
+            ])
+        );
+
+        let idle = claymore_stats(&MinerStats::default());
+        assert_eq!(idle[1], "0");
+        assert_eq!(idle[2], "0;0;0");
+        assert_eq!((&idle[3], &idle[5], &idle[7]), (&json!(""), &json!(""), &json!("")));
+    }
+
+    #[test]
+    fn xmrig_summary_fields() {
+        let summary = xmrig_summary(&stats());
+        assert_eq!(summary["uptime"], 7260);
+        assert_eq!(summary["version"], "1.2.3");
+        assert_eq!(summary["algo"], HIVE_ALGO);
+        assert_eq!(summary["paused"], false);
+        assert_eq!(summary["hashrate"]["total"], json!([3_500_000.0, 3_500_000.0, 3_500_000.0]));
+        assert_eq!(summary["hashrate"]["highest"], 3_500_000.0);
+        assert_eq!(summary["hashrate"]["threads"].as_array().unwrap().len(), 2);
+        assert_eq!(summary["hashrate"]["threads"][0], json!([2_250_000.0, 2_250_000.0, 2_250_000.0]));
+        assert_eq!(summary["results"]["shares_good"], 7);
+        assert_eq!(summary["results"]["shares_total"], 10);
+        assert_eq!(summary["results"]["hashes_total"], 123_456_789);
+        assert_eq!(
+            summary["connection"],
+            json!({
+                "pool": "https://node.example:36669",
+                "uptime": 60,
+                "ping": 12,
+                "failures": 1,
+                "accepted": 7,
+                "rejected": 2,
+                "algo": HIVE_ALGO,
+            })
+        );
+
+        let idle = xmrig_summary(&MinerStats::default());
+        assert_eq!(idle["connection"]["pool"], Value::Null);
+        assert_eq!(idle["connection"]["uptime"], Value::Null);
+        assert_eq!(idle["connection"]["failures"], 0);
+    }
+
+    #[test]
+    fn claymore_connections_are_limited() {
+        let open = Arc::new(AtomicUsize::new(0));
+        let slots: Vec<_> = (0..CLAYMORE_MAX_CONNECTIONS).map(|_| ConnectionSlot::acquire(&open).unwrap()).collect();
+        assert!(ConnectionSlot::acquire(&open).is_none());
+        drop(slots);
+        assert_eq!(open.load(Ordering::SeqCst), 0);
+        assert!(ConnectionSlot::acquire(&open).is_some());
+    }
+}
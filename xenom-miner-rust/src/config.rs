@@ -0,0 +1,378 @@
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Command};
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::SystemTime;
+use toml::Value;
+
+/// Prefix of environment variables overriding options (`XENOM_NODE_URL`, ...)
+pub const ENV_PREFIX: &str = "XENOM_";
+
+/// Serializes tests that resolve a config, since they read and set `XENOM_*` variables
+#[cfg(test)]
+pub static ENV_LOCK: parking_lot::Mutex<()> = parking_lot::Mutex::new(());
+
+/// Options that only make sense on the command line or select the config itself
+const NOT_CONFIGURABLE: &[&str] = &["help", "version", "config", "profile"];
+
+/// Command line after merging the config file, profiles and environment.
+/// Precedence, highest first: CLI flags, `XENOM_*` variables, profiles (later
+/// ones win), the file's top level, built-in defaults.
+#[derive(Debug, Default)]
+pub struct Resolved {
+    /// Arguments to parse: generated `--option=value` pairs followed by the original CLI
+    pub argv: Vec<OsString>,
+    pub file: Option<PathBuf>,
+    pub profiles: Vec<String>,
+    /// Where each option's value came from
+    pub origins: BTreeMap<String, String>,
+    pub errors: Vec<String>,
+    /// The original command line, parsed on its own
+    pub cli: ArgMatches,
+}
+
+fn env_var(id: &str) -> String {
+    format!("{}{}", ENV_PREFIX, id.to_uppercase())
+}
+
+/// Flattens a scalar or array TOML value into option values
+fn to_strings(value: &Value) -> Result<Vec<String>, String> {
+    match value {
+        Value::String(s) => Ok(vec![s.clone()]),
+        Value::Integer(i) => Ok(vec![i.to_string()]),
+        Value::Float(f) => Ok(vec![f.to_string()]),
+        Value::Boolean(b) => Ok(vec![b.to_string()]),
+        Value::Array(items) => items.iter().map(to_strings).collect::<Result<Vec<_>, _>>().map(|v| v.concat()),
+        Value::Table(_) | Value::Datetime(_) => Err("expected a string, number, boolean or array".to_string()),
+    }
+}
+
+fn parse_bool(s: &str) -> Option<bool> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "true" | "1" | "yes" | "on" => Some(true),
+        "false" | "0" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+struct Layers<'a> {
+    command: &'a Command,
+    values: BTreeMap<String, (Vec<String>, String)>,
+    errors: Vec<String>,
+}
+
+impl Layers<'_> {
+    /// Records `key = value` from `origin`, replacing any lower-precedence value
+    fn set(&mut self, key: &str, values: Vec<String>, origin: &str) {
+        let id = key.replace('-', "_");
+        if NOT_CONFIGURABLE.contains(&id.as_str()) || self.command.get_arguments().all(|a| a.get_id() != id.as_str()) {
+            self.errors.push(format!("unknown option `{}` in {}", key, origin));
+            return;
+        }
+        self.values.insert(id, (values, origin.to_string()));
+    }
+
+    fn set_table(&mut self, table: &toml::Table, origin: &str) {
+        for (key, value) in table {
+            match to_strings(value) {
+                Ok(values) => self.set(key, values, origin),
+                Err(e) => self.errors.push(format!("`{}` in {}: {}", key, origin, e)),
+            }
+        }
+    }
+}
+
+fn read_file(path: &Path) -> Result<toml::Table, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    text.parse::<toml::Table>().map_err(|e| format!("invalid TOML in {}: {}", path.display(), e))
+}
+
+/// Merges `--config`, `--profile` and `XENOM_*` variables into a command line for `command`
+pub fn resolve(command: Command, argv: Vec<OsString>) -> Resolved {
+    let cli = command.clone().get_matches_from(argv.clone());
+    let from_cli = |id: &str| cli.value_source(id) == Some(ValueSource::CommandLine);
+    let cli_strings = |id: &str| -> Option<Vec<String>> {
+        from_cli(id).then(|| cli.get_raw(id).into_iter().flatten().map(|s| s.to_string_lossy().into_owned()).collect())
+    };
+
+    let mut resolved = Resolved::default();
+    let mut layers = Layers {
+        command: &command,
+        values: BTreeMap::new(),
+        errors: Vec::new(),
+    };
+
+    resolved.file = cli_strings("config")
+        .or_else(|| std::env::var(env_var("config")).ok().map(|s| vec![s]))
+        .and_then(|v| v.into_iter().next())
+        .map(PathBuf::from);
+    let mut table = match &resolved.file {
+        Some(path) => read_file(path).unwrap_or_else(|e| {
+            layers.errors.push(e);
+            toml::Table::new()
+        }),
+        None => toml::Table::new(),
+    };
+    let mut profile_tables = match table.remove("profile") {
+        Some(Value::Table(t)) => t,
+        Some(_) => {
+            layers.errors.push("`profile` must be a table of named profiles".to_string());
+            toml::Table::new()
+        }
+        None => toml::Table::new(),
+    };
+    // Profiles applied by default, unless chosen on the command line or environment
+    let default_profiles = table.remove("profiles").map(|v| to_strings(&v).unwrap_or_default()).unwrap_or_default();
+
+    resolved.profiles = cli_strings("profile")
+        .or_else(|| {
+            std::env::var(env_var("profile"))
+                .ok()
+                .map(|s| s.split(',').map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).collect())
+        })
+        .unwrap_or(default_profiles);
+
+    let file_origin = resolved.file.as_ref().map(|p| p.display().to_string()).unwrap_or_default();
+    layers.set_table(&table, &file_origin);
+    for name in &resolved.profiles {
+        match profile_tables.remove(name) {
+            Some(Value::Table(t)) => layers.set_table(&t, &format!("profile {}", name)),
+            Some(_) => layers.errors.push(format!("profile `{}` must be a table", name)),
+            None => layers.errors.push(format!("profile `{}` not found in {}", name, file_origin)),
+        }
+    }
+
+    for arg in command.get_arguments() {
+        let id = arg.get_id().as_str();
+        if NOT_CONFIGURABLE.contains(&id) {
+            continue;
+        }
+        if let Ok(value) = std::env::var(env_var(id)) {
+            let values = if arg.get_num_args().is_some_and(|n| n.max_values() > 1)
+                || matches!(arg.get_action(), clap::ArgAction::Append)
+            {
+                value.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect()
+            } else {
+                vec![value]
+            };
+            layers.set(id, values, &format!("env {}", env_var(id)));
+        }
+    }
+
+    // Generated arguments go first so that the original command line (and any subcommand) follows
+    resolved.argv.push(argv.first().cloned().unwrap_or_default());
+    for arg in command.get_arguments() {
+        let id = arg.get_id().as_str();
+        if from_cli(id) {
+            resolved.origins.insert(id.to_string(), "cli".to_string());
+            continue;
+        }
+        let (Some((values, origin)), Some(long)) = (layers.values.get(id), arg.get_long()) else {
+            continue;
+        };
+        if arg.get_action().takes_values() {
+            for value in values {
+                resolved.argv.push(format!("--{}={}", long, value).into());
+            }
+        } else {
+            match values.first().and_then(|v| parse_bool(v)) {
+                Some(true) => resolved.argv.push(format!("--{}", long).into()),
+                Some(false) => {}
+                None => {
+                    layers.errors.push(format!("`{}` from {} must be true or false", id, origin));
+                    continue;
+                }
+            }
+        }
+        resolved.origins.insert(id.to_string(), origin.clone());
+    }
+    resolved.argv.extend(argv.into_iter().skip(1));
+    resolved.errors = layers.errors;
+    resolved.cli = cli;
+    resolved
+}
+
+/// Raw values of every option, keyed by id
+pub fn values(command: &Command, matches: &ArgMatches) -> BTreeMap<String, Vec<String>> {
+    command
+        .get_arguments()
+        .filter(|a| !matches!(a.get_id().as_str(), "help" | "version"))
+        .map(|arg| {
+            let id = arg.get_id().as_str();
+            let raw = matches
+                .get_raw(id)
+                .into_iter()
+                .flatten()
+                .map(|s| s.to_string_lossy().into_owned())
+                .collect();
+            (id.to_string(), raw)
+        })
+        .collect()
+}
+
+/// Effective value of every option with its source, secrets masked
+pub fn describe(command: &Command, matches: &ArgMatches, resolved: &Resolved) -> Vec<(String, String, String)> {
+    let values = values(command, matches);
+    command
+        .get_arguments()
+        .filter(|a| !matches!(a.get_id().as_str(), "help" | "version"))
+        .map(|arg| {
+            let id = arg.get_id().as_str();
+            let raw = &values[id];
+            let value = if raw.is_empty() {
+                if arg.get_action().takes_values() { "(unset)".to_string() } else { "false".to_string() }
+            } else if id.contains("token") {
+                "********".to_string()
+            } else {
+                raw.join(", ")
+            };
+            let origin = resolved
+                .origins
+                .get(id)
+                .cloned()
+                .unwrap_or_else(|| "default".to_string());
+            (id.to_string(), value, origin)
+        })
+        .collect()
+}
+
+/// Detects when the config file should be re-read: on SIGHUP (Unix) or when its
+/// modification time changes
+pub struct Watcher {
+    file: Option<PathBuf>,
+    modified: Option<SystemTime>,
+    hangup: Arc<AtomicBool>,
+}
+
+fn modified(file: &Option<PathBuf>) -> Option<SystemTime> {
+    file.as_ref().and_then(|f| std::fs::metadata(f).and_then(|m| m.modified()).ok())
+}
+
+impl Watcher {
+    pub fn new(file: Option<PathBuf>) -> Self {
+        let hangup = Arc::new(AtomicBool::new(false));
+        #[cfg(unix)]
+        if let Err(e) = signal_hook::flag::register(signal_hook::consts::SIGHUP, Arc::clone(&hangup)) {
+            log::warn!("⚠️  Cannot handle SIGHUP: {}", e);
+        }
+        Self {
+            modified: modified(&file),
+            file,
+            hangup,
+        }
+    }
+
+    pub fn changed(&mut self) -> bool {
+        let hangup = self.hangup.swap(false, Ordering::SeqCst);
+        let modified = modified(&self.file);
+        let touched = modified != self.modified;
+        self.modified = modified;
+        hangup || touched
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+    use clap::{CommandFactory, FromArgMatches};
+
+    const CONFIG: &str = r#"
+node_url = "http://file:36669"
+threads = 1
+batches = 10
+population = 100
+duty_cycle = 0.9
+
+[profile.fast]
+threads = 4
+batches = 20
+population = 200
+duty_cycle = 0.8
+
+[profile.later]
+batches = 30
+"#;
+
+    fn argv(args: &[&str]) -> Vec<OsString> {
+        std::iter::once("xenom-miner-rust").chain(args.iter().copied()).map(OsString::from).collect()
+    }
+
+    fn origin<'a>(resolved: &'a Resolved, id: &str) -> Option<&'a str> {
+        resolved.origins.get(id).map(String::as_str)
+    }
+
+    #[test]
+    fn cli_beats_env_beats_profiles_beats_file() {
+        let _env = ENV_LOCK.lock();
+        let dir = TestDir::new("config-precedence");
+        let file = dir.write("miner.toml", CONFIG);
+        let file_arg = file.to_str().unwrap();
+        std::env::set_var("XENOM_POPULATION", "64");
+        std::env::set_var("XENOM_DUTY_CYCLE", "0.7");
+        std::env::set_var("XENOM_FALLBACK_NODE", "http://a:1, http://b:2");
+        let resolved = resolve(
+            crate::Args::command(),
+            argv(&["--config", file_arg, "--profile", "fast", "--profile", "later", "--duty-cycle", "0.5"]),
+        );
+        for var in ["XENOM_POPULATION", "XENOM_DUTY_CYCLE", "XENOM_FALLBACK_NODE"] {
+            std::env::remove_var(var);
+        }
+
+        assert!(resolved.errors.is_empty(), "{:?}", resolved.errors);
+        assert_eq!(resolved.profiles, ["fast", "later"]);
+        assert_eq!(origin(&resolved, "node_url"), Some(file_arg));
+        assert_eq!(origin(&resolved, "threads"), Some("profile fast"));
+        assert_eq!(origin(&resolved, "batches"), Some("profile later"));
+        assert_eq!(origin(&resolved, "population"), Some("env XENOM_POPULATION"));
+        assert_eq!(origin(&resolved, "fallback_node"), Some("env XENOM_FALLBACK_NODE"));
+        assert_eq!(origin(&resolved, "duty_cycle"), Some("cli"));
+        assert_eq!(origin(&resolved, "mv_len"), None);
+
+        let args = crate::Args::from_arg_matches(&crate::Args::command().get_matches_from(&resolved.argv)).unwrap();
+        assert_eq!(args.node_url, "http://file:36669");
+        assert_eq!((args.threads, args.batches, args.population), (4, 30, 64));
+        assert_eq!(args.fallback_node, ["http://a:1", "http://b:2"]);
+        assert_eq!(args.duty_cycle, 0.5);
+        assert_eq!(args.mv_len, 16);
+    }
+
+    #[test]
+    fn default_profiles_come_from_the_file() {
+        let _env = ENV_LOCK.lock();
+        let dir = TestDir::new("config-default-profiles");
+        let file = dir.write("miner.toml", &format!("profiles = [\"later\"]\n{}", CONFIG));
+        let resolved = resolve(crate::Args::command(), argv(&["--config", file.to_str().unwrap()]));
+        assert!(resolved.errors.is_empty(), "{:?}", resolved.errors);
+        assert_eq!(origin(&resolved, "threads"), Some(file.to_str().unwrap()));
+        assert_eq!(origin(&resolved, "batches"), Some("profile later"));
+    }
+
+    #[test]
+    fn unknown_keys_and_profiles_are_rejected() {
+        let _env = ENV_LOCK.lock();
+        let dir = TestDir::new("config-unknown");
+        let file = dir.write(
+            "miner.toml",
+            "bogus = 1\nconfig = \"other.toml\"\ngpu = \"maybe\"\nthreads = { a = 1 }\n[profile.fast]\nturbo = true\n",
+        );
+        let resolved = resolve(
+            crate::Args::command(),
+            argv(&["--config", file.to_str().unwrap(), "--profile", "fast", "--profile", "missing"]),
+        );
+        let errors = resolved.errors.join("\n");
+        assert!(errors.contains("unknown option `bogus`"), "{}", errors);
+        assert!(errors.contains("unknown option `config`"), "{}", errors);
+        assert!(errors.contains("unknown option `turbo` in profile fast"), "{}", errors);
+        assert!(errors.contains("profile `missing` not found"), "{}", errors);
+        assert!(errors.contains("`gpu`") && errors.contains("true or false"), "{}", errors);
+        assert!(errors.contains("`threads`"), "{}", errors);
+        assert_eq!(origin(&resolved, "gpu"), None);
+
+        let missing = resolve(crate::Args::command(), argv(&["--config", "/nonexistent/miner.toml"]));
+        assert!(missing.errors[0].contains("cannot read"), "{:?}", missing.errors);
+    }
+}
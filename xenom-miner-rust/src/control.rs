@@ -0,0 +1,590 @@
+use crate::throttle::ThrottleSettings;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Exit code when a second signal (or an expired deadline) cuts shutdown short
+pub const FORCED_EXIT_CODE: i32 = 130;
+
+/// Longest a solution submission may take. A submission in flight when the shutdown
+/// deadline runs out is given the rest of it rather than cut off mid-request.
+pub const SUBMIT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Upper bounds of `/control/intensity`. `batches * population` stays far below
+/// `usize::MAX` and a GPU population still fits in device memory.
+pub const MAX_THREADS: usize = 4096;
+pub const MAX_BATCHES: usize = 1 << 20;
+pub const MAX_POPULATION: usize = 1 << 24;
+
+/// Settings that can be changed at runtime through the control API.
+/// `mine_loop` takes a snapshot at every template boundary.
+#[derive(Debug, Clone, Serialize)]
+pub struct RuntimeSettings {
+    pub node_urls: Vec<String>,
+    pub active_node: usize,
+    pub miner_address: Option<String>,
+    pub threads: usize,
+    pub batches: usize,
+    pub population: usize,
+    pub throttle: ThrottleSettings,
+}
+
+impl RuntimeSettings {
+    pub fn active_node_url(&self) -> &str {
+        &self.node_urls[self.active_node]
+    }
+}
+
+/// Body of `POST /control/node`
+#[derive(Debug, Deserialize)]
+pub struct NodeRequest {
+    pub url: String,
+    /// Make the node active immediately (default: only add it to the failover list)
+    #[serde(default)]
+    pub switch: bool,
+}
+
+/// Body of `POST /control/address`
+#[derive(Debug, Deserialize)]
+pub struct AddressRequest {
+    pub address: Option<String>,
+}
+
+/// Body of `POST /control/intensity`. Missing fields keep their current value.
+#[derive(Debug, Deserialize)]
+pub struct IntensityRequest {
+    pub threads: Option<usize>,
+    pub batches: Option<usize>,
+    pub population: Option<usize>,
+}
+
+/// Body of `POST /control/throttle`. Missing fields keep their current value.
+#[derive(Debug, Deserialize)]
+pub struct ThrottleRequest {
+    pub max_hashrate: Option<f64>,
+    pub device_max_hashrate: Option<f64>,
+    pub duty_cycle: Option<f64>,
+}
+
+/// Shared handle between the control API and the mining loop
+pub struct MinerControl {
+    token: RwLock<Option<String>>,
+    paused: AtomicBool,
+    refresh: AtomicBool,
+    revision: AtomicU64,
+    settings: RwLock<RuntimeSettings>,
+    shutdown: Arc<AtomicBool>,
+    /// `--shutdown-timeout`, once signal handling is installed
+    shutdown_timeout: Mutex<Option<Duration>>,
+    /// When the shutdown timeout runs out, fixed when the shutdown is first seen
+    shutdown_deadline: Mutex<Option<Instant>>,
+    /// When the timeout of the submission in flight runs out
+    submit_deadline: Mutex<Option<Instant>>,
+}
+
+/// A solution submission in flight; holds off the forced exit until dropped or timed out
+pub struct Submission<'a>(&'a MinerControl);
+
+impl Drop for Submission<'_> {
+    fn drop(&mut self) {
+        *self.0.submit_deadline.lock() = None;
+    }
+}
+
+impl MinerControl {
+    pub fn new(settings: RuntimeSettings, token: Option<String>) -> Arc<Self> {
+        Arc::new(Self {
+            token: RwLock::new(token),
+            paused: AtomicBool::new(false),
+            refresh: AtomicBool::new(false),
+            revision: AtomicU64::new(0),
+            settings: RwLock::new(settings),
+            shutdown: Arc::new(AtomicBool::new(false)),
+            shutdown_timeout: Mutex::new(None),
+            shutdown_deadline: Mutex::new(None),
+            submit_deadline: Mutex::new(None),
+        })
+    }
+
+    /// Requests a graceful stop on SIGINT/SIGTERM. A second signal exits at once, and so
+    /// does the process if the loop has not finished within `deadline` of the first one
+    /// (and any submission in flight then has run into its own timeout).
+    pub fn handle_signals(self: &Arc<Self>, deadline: Duration) -> std::io::Result<()> {
+        use signal_hook::consts::{SIGINT, SIGTERM};
+        for signal in [SIGINT, SIGTERM] {
+            // Registered first, so it only fires when the flag was already set by an earlier signal
+            signal_hook::flag::register_conditional_shutdown(signal, FORCED_EXIT_CODE, Arc::clone(&self.shutdown))?;
+            signal_hook::flag::register(signal, Arc::clone(&self.shutdown))?;
+        }
+        *self.shutdown_timeout.lock() = Some(deadline);
+
+        let control = Arc::clone(self);
+        std::thread::spawn(move || {
+            while !control.is_shutting_down() {
+                std::thread::sleep(Duration::from_millis(100));
+            }
+            log::warn!("🛑 Shutdown requested; finishing current work (deadline {}s, signal again to force)", deadline.as_secs());
+            while let Some(left) = control.exit_at().map(|at| at.saturating_duration_since(Instant::now())) {
+                if left.is_zero() {
+                    break;
+                }
+                std::thread::sleep(left.min(Duration::from_millis(100)));
+            }
+            log::error!("❌ Shutdown deadline exceeded, exiting");
+            log::logger().flush();
+            std::process::exit(FORCED_EXIT_CODE);
+        });
+        Ok(())
+    }
+
+    /// End of the shutdown timeout, once a shutdown was requested with signal handling installed
+    fn shutdown_deadline(&self) -> Option<Instant> {
+        if !self.is_shutting_down() {
+            return None;
+        }
+        let timeout = (*self.shutdown_timeout.lock())?;
+        Some(*self.shutdown_deadline.lock().get_or_insert_with(|| Instant::now() + timeout))
+    }
+
+    /// When a shutdown that did not finish in time forces the exit
+    fn exit_at(&self) -> Option<Instant> {
+        let deadline = self.shutdown_deadline()?;
+        Some(self.submit_deadline.lock().map_or(deadline, |submit| submit.max(deadline)))
+    }
+
+    /// Timeout for the next solution submission: `SUBMIT_TIMEOUT`, or what is left of the
+    /// shutdown deadline once a shutdown was requested
+    pub fn submit_timeout(&self) -> Duration {
+        self.shutdown_deadline()
+            .map_or(SUBMIT_TIMEOUT, |deadline| deadline.saturating_duration_since(Instant::now()).min(SUBMIT_TIMEOUT))
+    }
+
+    /// Marks a submission with `timeout` as in flight until the guard is dropped
+    pub fn submitting(&self, timeout: Duration) -> Submission<'_> {
+        *self.submit_deadline.lock() = Some(Instant::now() + timeout);
+        Submission(self)
+    }
+
+    /// No new work should be started once this is set
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutdown.load(Ordering::Relaxed)
+    }
+
+    /// Sleeps up to `duration`, waking early on shutdown
+    pub fn sleep(&self, duration: Duration) {
+        let until = Instant::now() + duration;
+        while !self.is_shutting_down() {
+            let left = until.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                break;
+            }
+            std::thread::sleep(left.min(Duration::from_millis(100)));
+        }
+    }
+
+    /// Control endpoints are only served when a token has been configured
+    pub fn enabled(&self) -> bool {
+        self.token.read().is_some()
+    }
+
+    pub fn set_token(&self, token: Option<String>) {
+        *self.token.write() = token;
+    }
+
+    /// Checks an `Authorization` header value (`Bearer <token>`)
+    pub fn authorize(&self, header: Option<&str>) -> bool {
+        match (&*self.token.read(), header.and_then(|h| h.strip_prefix("Bearer "))) {
+            (Some(token), Some(given)) => constant_time_eq(token.as_bytes(), given.trim().as_bytes()),
+            _ => false,
+        }
+    }
+
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+    }
+
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// Blocks the calling worker while mining is paused. Work in progress is kept.
+    pub fn wait_while_paused(&self, stop: &AtomicBool) {
+        while self.is_paused() && !stop.load(Ordering::Relaxed) && !self.is_shutting_down() {
+            std::thread::sleep(Duration::from_millis(100));
+        }
+    }
+
+    /// Asks the loop to drop the current template and fetch a fresh one
+    pub fn request_refresh(&self) {
+        self.refresh.store(true, Ordering::SeqCst);
+    }
+
+    pub fn refresh_requested(&self) -> bool {
+        self.refresh.load(Ordering::Relaxed)
+    }
+
+    pub fn take_refresh(&self) -> bool {
+        self.refresh.swap(false, Ordering::SeqCst)
+    }
+
+    /// Increases every time the settings change, so the loop can cheaply detect updates
+    pub fn revision(&self) -> u64 {
+        self.revision.load(Ordering::SeqCst)
+    }
+
+    pub fn settings(&self) -> RuntimeSettings {
+        self.settings.read().clone()
+    }
+
+    /// Current limits, cheap enough to read on every hash report
+    pub fn throttle(&self) -> ThrottleSettings {
+        self.settings.read().throttle
+    }
+
+    fn update(&self, f: impl FnOnce(&mut RuntimeSettings)) {
+        f(&mut self.settings.write());
+        self.revision.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub fn set_node(&self, req: NodeRequest) -> Result<(), String> {
+        let url = req.url.trim().trim_end_matches('/').to_string();
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(format!("invalid node url: {}", req.url));
+        }
+        self.update(|s| {
+            let idx = match s.node_urls.iter().position(|u| *u == url) {
+                Some(idx) => idx,
+                None => {
+                    s.node_urls.push(url);
+                    s.node_urls.len() - 1
+                }
+            };
+            if req.switch {
+                s.active_node = idx;
+            }
+        });
+        Ok(())
+    }
+
+    /// Moves to the next node in the list after a failed request
+    pub fn fail_over(&self) -> Option<String> {
+        if self.settings.read().node_urls.len() < 2 {
+            return None;
+        }
+        let mut next = None;
+        self.update(|s| {
+            s.active_node = (s.active_node + 1) % s.node_urls.len();
+            next = Some(s.node_urls[s.active_node].clone());
+        });
+        next
+    }
+
+    pub fn set_address(&self, req: AddressRequest) -> Result<(), String> {
+        let address = req.address.map(|a| a.trim().to_string()).filter(|a| !a.is_empty());
+        if let Some(addr) = &address {
+            if !addr.chars().all(|c| c.is_ascii_alphanumeric()) {
+                return Err(format!("invalid miner address: {}", addr));
+            }
+        }
+        self.update(|s| s.miner_address = address);
+        Ok(())
+    }
+
+    /// Replaces the node list, address, intensity and limits from a reloaded config.
+    /// The active node is kept if it is still listed.
+    pub fn reload(
+        &self,
+        node_urls: Vec<String>,
+        miner_address: Option<String>,
+        threads: usize,
+        batches: usize,
+        population: usize,
+        throttle: ThrottleSettings,
+    ) {
+        self.update(|s| {
+            let active = s.active_node_url().to_string();
+            s.active_node = node_urls.iter().position(|u| *u == active).unwrap_or(0);
+            s.node_urls = node_urls;
+            s.miner_address = miner_address;
+            s.threads = threads;
+            s.batches = batches;
+            s.population = population;
+            s.throttle = throttle;
+        });
+    }
+
+    pub fn set_intensity(&self, req: IntensityRequest) -> Result<(), String> {
+        if req.threads == Some(0) || req.batches == Some(0) || req.population == Some(0) {
+            return Err("threads, batches and population must be greater than 0".to_string());
+        }
+        for (name, value, max) in [
+            ("threads", req.threads, MAX_THREADS),
+            ("batches", req.batches, MAX_BATCHES),
+            ("population", req.population, MAX_POPULATION),
+        ] {
+            if value.is_some_and(|v| v > max) {
+                return Err(format!("{} must be at most {}", name, max));
+            }
+        }
+        self.update(|s| {
+            if let Some(t) = req.threads {
+                s.threads = t;
+            }
+            if let Some(b) = req.batches {
+                s.batches = b;
+            }
+            if let Some(p) = req.population {
+                s.population = p;
+            }
+        });
+        Ok(())
+    }
+
+    pub fn set_throttle(&self, req: ThrottleRequest) -> Result<(), String> {
+        let current = self.throttle();
+        let throttle = ThrottleSettings {
+            max_hashrate: req.max_hashrate.unwrap_or(current.max_hashrate),
+            device_max_hashrate: req.device_max_hashrate.unwrap_or(current.device_max_hashrate),
+            duty_cycle: req.duty_cycle.unwrap_or(current.duty_cycle),
+        };
+        throttle.validate()?;
+        self.update(|s| s.throttle = throttle);
+        Ok(())
+    }
+}
+
+/// Compares secrets without leaking the position of the first mismatch
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn control(token: Option<&str>) -> Arc<MinerControl> {
+        MinerControl::new(
+            RuntimeSettings {
+                node_urls: vec!["http://a:36669".to_string()],
+                active_node: 0,
+                miner_address: None,
+                threads: 4,
+                batches: 100,
+                population: 512,
+                throttle: ThrottleSettings::default(),
+            },
+            token.map(str::to_string),
+        )
+    }
+
+    #[test]
+    fn authorize_requires_the_bearer_token() {
+        let control = control(Some("secret"));
+        assert!(control.enabled());
+        assert!(control.authorize(Some("Bearer secret")));
+        assert!(!control.authorize(Some("Bearer secreT")));
+        assert!(!control.authorize(Some("secret")));
+        assert!(!control.authorize(None));
+
+        let disabled = self::control(None);
+        assert!(!disabled.enabled());
+        assert!(!disabled.authorize(Some("Bearer ")));
+    }
+
+    #[test]
+    fn intensity_is_bounded() {
+        let control = control(None);
+        let request = |threads, batches, population| IntensityRequest {
+            threads,
+            batches,
+            population,
+        };
+        assert!(control.set_intensity(request(None, Some(0), None)).is_err());
+        assert!(control.set_intensity(request(Some(MAX_THREADS + 1), None, None)).is_err());
+        assert!(control.set_intensity(request(None, Some(usize::MAX), None)).is_err());
+        assert!(control.set_intensity(request(None, None, Some(MAX_POPULATION + 1))).is_err());
+        assert_eq!(control.revision(), 0, "rejected requests change nothing");
+
+        control.set_intensity(request(None, Some(MAX_BATCHES), Some(MAX_POPULATION))).unwrap();
+        let settings = control.settings();
+        assert_eq!((settings.threads, settings.batches, settings.population), (4, MAX_BATCHES, MAX_POPULATION));
+        assert_eq!(control.revision(), 1);
+    }
+
+    #[test]
+    fn nodes_are_added_switched_and_failed_over() {
+        let control = control(None);
+        assert!(control.fail_over().is_none(), "nothing to fail over to");
+        assert!(control
+            .set_node(NodeRequest {
+                url: "ftp://b".to_string(),
+                switch: true,
+            })
+            .is_err());
+
+        control
+            .set_node(NodeRequest {
+                url: " http://b:36669/ ".to_string(),
+                switch: false,
+            })
+            .unwrap();
+        assert_eq!(control.settings().active_node_url(), "http://a:36669");
+        control
+            .set_node(NodeRequest {
+                url: "http://b:36669".to_string(),
+                switch: true,
+            })
+            .unwrap();
+        let settings = control.settings();
+        assert_eq!(settings.node_urls.len(), 2, "known nodes are not added twice");
+        assert_eq!(settings.active_node_url(), "http://b:36669");
+
+        assert_eq!(control.fail_over().as_deref(), Some("http://a:36669"));
+    }
+
+    #[test]
+    fn address_and_throttle_are_validated() {
+        let control = control(None);
+        assert!(control.set_address(AddressRequest { address: Some("3M x".to_string()) }).is_err());
+        control.set_address(AddressRequest { address: Some(" 3Mabc ".to_string()) }).unwrap();
+        assert_eq!(control.settings().miner_address.as_deref(), Some("3Mabc"));
+        control.set_address(AddressRequest { address: Some(String::new()) }).unwrap();
+        assert_eq!(control.settings().miner_address, None);
+
+        let throttle = |duty_cycle| ThrottleRequest {
+            max_hashrate: None,
+            device_max_hashrate: Some(1e6),
+            duty_cycle,
+        };
+        assert!(control.set_throttle(throttle(Some(1.5))).is_err());
+        control.set_throttle(throttle(Some(0.5))).unwrap();
+        control.set_throttle(throttle(None)).unwrap();
+        assert_eq!(control.throttle().duty_cycle, 0.5);
+        assert_eq!(control.throttle().device_max_hashrate, 1e6);
+    }
+
+    #[test]
+    fn pause_and_refresh() {
+        let control = control(None);
+        control.pause();
+        assert!(control.is_paused());
+        // Returns at once when the worker's stop flag is set
+        control.wait_while_paused(&AtomicBool::new(true));
+        control.resume();
+        assert!(!control.is_paused());
+
+        control.request_refresh();
+        assert!(control.refresh_requested());
+        assert!(control.take_refresh());
+        assert!(!control.refresh_requested());
+    }
+
+    #[test]
+    fn shutdown_wakes_sleepers_and_bounds_submissions() {
+        let control = control(None);
+        let start = Instant::now();
+        control.sleep(Duration::from_millis(150));
+        assert!(start.elapsed() >= Duration::from_millis(150));
+        assert_eq!(control.submit_timeout(), SUBMIT_TIMEOUT);
+        assert_eq!(control.exit_at(), None);
+
+        *control.shutdown_timeout.lock() = Some(Duration::from_secs(2));
+        let sleeper = {
+            let control = Arc::clone(&control);
+            std::thread::spawn(move || {
+                let start = Instant::now();
+                control.sleep(Duration::from_secs(30));
+                start.elapsed()
+            })
+        };
+        std::thread::sleep(Duration::from_millis(50));
+        // What the signal handler does on the first signal
+        control.shutdown.store(true, Ordering::SeqCst);
+        assert!(control.is_shutting_down());
+        assert!(sleeper.join().unwrap() < Duration::from_secs(5));
+        control.sleep(Duration::from_secs(30));
+
+        // The deadline is fixed when the shutdown is first seen; submissions only get what is left of it
+        let deadline = control.exit_at().unwrap();
+        assert_eq!(control.shutdown_deadline(), Some(deadline));
+        assert!(control.submit_timeout() <= Duration::from_secs(2));
+        assert!(control.submit_timeout() > Duration::from_secs(1));
+
+        // A submission started before the signal, with the full timeout, holds off the exit
+        let submission = control.submitting(SUBMIT_TIMEOUT);
+        let held = control.exit_at().unwrap();
+        assert!(held > deadline + Duration::from_secs(5));
+        drop(submission);
+        assert_eq!(control.exit_at(), Some(deadline));
+    }
+
+    /// Runs `signals_in_a_child` in a child process, which signal handling may end
+    #[cfg(unix)]
+    fn run_child(mode: &str) -> (Option<i32>, String, Duration) {
+        let start = Instant::now();
+        let output = std::process::Command::new(std::env::current_exe().unwrap())
+            .args(["control::tests::signals_in_a_child", "--exact", "--ignored", "--nocapture"])
+            .env("XENOM_SIGNAL_TEST", mode)
+            .output()
+            .unwrap();
+        (output.status.code(), String::from_utf8_lossy(&output.stdout).into_owned(), start.elapsed())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    #[ignore = "run by signals_stop_gracefully_then_force_the_exit in a child process"]
+    fn signals_in_a_child() {
+        use signal_hook::consts::SIGTERM;
+        use signal_hook::low_level::raise;
+        let Ok(mode) = std::env::var("XENOM_SIGNAL_TEST") else {
+            return;
+        };
+        let control = control(None);
+        let deadline = if mode == "second-signal" { 60 } else { 1 };
+        control.handle_signals(Duration::from_secs(deadline)).unwrap();
+        assert!(!control.is_shutting_down());
+
+        raise(SIGTERM).unwrap();
+        assert!(control.is_shutting_down());
+        let start = Instant::now();
+        control.sleep(Duration::from_secs(30));
+        assert!(start.elapsed() < Duration::from_secs(1));
+        println!("graceful");
+
+        match mode.as_str() {
+            "second-signal" => {
+                raise(SIGTERM).unwrap();
+            }
+            _ => {
+                let _submission = control.submitting(Duration::from_secs(3));
+                std::thread::sleep(Duration::from_secs(2));
+                println!("submission survived the deadline");
+                std::thread::sleep(Duration::from_secs(30));
+            }
+        }
+        println!("still running");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn signals_stop_gracefully_then_force_the_exit() {
+        let (code, stdout, elapsed) = run_child("second-signal");
+        assert_eq!(code, Some(FORCED_EXIT_CODE), "{}", stdout);
+        assert!(stdout.contains("graceful"), "{}", stdout);
+        assert!(!stdout.contains("still running"), "{}", stdout);
+        assert!(elapsed < Duration::from_secs(30));
+
+        // The deadline (1s) waits for the submission in flight (3s), then exits
+        let (code, stdout, elapsed) = run_child("deadline");
+        assert_eq!(code, Some(FORCED_EXIT_CODE), "{}", stdout);
+        assert!(stdout.contains("submission survived the deadline"), "{}", stdout);
+        assert!(!stdout.contains("still running"), "{}", stdout);
+        assert!(elapsed < Duration::from_secs(20));
+    }
+}
@@ -0,0 +1,333 @@
+use crate::affinity;
+use crate::control::MinerControl;
+use crate::throttle::Pacer;
+use crate::watchdog::Heartbeat;
+use std::sync::Arc;
+use log::info;
+use num_bigint::BigUint;
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+/// Mutation vector and its BLAKE3 digest
+pub type Solution = (Vec<u8>, [u8; 32]);
+
+/// Leading mutation vector bytes holding the nonce (little-endian), as in the CUDA
+/// `blake3_brute_force` kernel. The next 8 bytes hold the extranonce, which the kernel
+/// leaves at zero; any bytes after that are zero.
+pub const NONCE_BYTES: usize = 8;
+
+/// Writes `nonce` and `extranonce` into a mutation vector. Bytes past its end are dropped.
+pub fn write_nonce(mv: &mut [u8], nonce: u64, extranonce: u64) {
+    let (nonce, extranonce) = (nonce.to_le_bytes(), extranonce.to_le_bytes());
+    for (i, byte) in mv.iter_mut().enumerate() {
+        *byte = match i {
+            0..NONCE_BYTES => nonce[i],
+            NONCE_BYTES..16 => extranonce[i - NONCE_BYTES],
+            _ => 0,
+        };
+    }
+}
+
+/// Seed stream id of the default extranonce (see `seed::derive`)
+pub const EXTRANONCE_STREAM: u64 = u64::MAX;
+
+/// Number of distinct nonces a mutation vector of `mv_len` bytes can hold
+pub fn nonce_space(mv_len: usize) -> u64 {
+    if mv_len >= NONCE_BYTES {
+        u64::MAX
+    } else {
+        1u64 << (8 * mv_len)
+    }
+}
+
+/// `count` consecutive nonces from `start` under one extranonce
+#[derive(Debug, Clone, Copy)]
+pub struct NonceRange {
+    pub extranonce: u64,
+    pub start: u64,
+    pub count: u64,
+}
+
+/// Result of `search_range`
+#[derive(Debug)]
+pub struct RangeOutcome {
+    /// Solution with its nonce
+    pub solution: Option<(Solution, u64)>,
+    pub hashes: u64,
+    /// Every nonce of the range below this one was hashed; a resumed search starts here
+    pub resume: u64,
+}
+
+/// Enumerates `range` on `threads` threads striding like the CUDA kernel: thread `t`
+/// hashes `start + t`, `start + t + threads`, ... so every hash is a distinct point of
+/// the search space. Each thread applies the CPU placement (`affinity`). Every 1024 hashes each thread passes its new hash count to
+/// `progress` and stops if it returns false. All threads stop once `found` is set.
+pub fn search_range(
+    header_prefix: &[u8],
+    mv_len: usize,
+    target: &[u8; 32],
+    range: NonceRange,
+    threads: usize,
+    found: &AtomicBool,
+    progress: &(dyn Fn(u64) -> bool + Sync),
+) -> RangeOutcome {
+    let threads = threads.max(1) as u64;
+    let end = range.start.saturating_add(range.count).min(nonce_space(mv_len));
+    let width = mv_len.min(NONCE_BYTES);
+    let offset = header_prefix.len();
+    // Next nonce of each thread
+    let next: Vec<AtomicU64> = (0..threads).map(|t| AtomicU64::new(range.start.saturating_add(t))).collect();
+    let hashes = AtomicU64::new(0);
+    let solution = Mutex::new(None);
+
+    std::thread::scope(|s| {
+        for t in 0..threads as usize {
+            let (next, hashes, solution) = (&next[t], &hashes, &solution);
+            s.spawn(move || {
+                // Before the first allocation, so a pinned thread's buffer is node-local
+                affinity::current().apply(t);
+                let mut candidate = header_prefix.to_vec();
+                candidate.resize(offset + mv_len, 0);
+                write_nonce(&mut candidate[offset..], 0, range.extranonce);
+                let mut nonce = next.load(Ordering::Relaxed);
+                let (mut done, mut reported) = (0u64, 0u64);
+                while nonce < end {
+                    if done.is_multiple_of(1024) {
+                        next.store(nonce, Ordering::Relaxed);
+                        let go = progress(done - reported);
+                        reported = done;
+                        if !go || found.load(Ordering::Relaxed) {
+                            break;
+                        }
+                    }
+                    candidate[offset..offset + width].copy_from_slice(&nonce.to_le_bytes()[..width]);
+                    let digest = blake3::hash(&candidate);
+                    done += 1;
+                    if digest.as_bytes()[..] <= target[..] {
+                        if !found.swap(true, Ordering::SeqCst) {
+                            *solution.lock() = Some(((candidate[offset..].to_vec(), *digest.as_bytes()), nonce));
+                        }
+                        nonce = nonce.saturating_add(threads);
+                        break;
+                    }
+                    nonce = nonce.saturating_add(threads);
+                }
+                next.store(nonce.min(end), Ordering::Relaxed);
+                progress(done - reported);
+                hashes.fetch_add(done, Ordering::Relaxed);
+            });
+        }
+    });
+
+    RangeOutcome {
+        solution: solution.into_inner(),
+        hashes: hashes.into_inner(),
+        resume: next.iter().map(|n| n.load(Ordering::Relaxed)).min().unwrap_or(end).min(end),
+    }
+}
+
+/// CPU backend for `--mine-loop`, mirroring the `GpuMiner` interface
+pub struct CpuMiner {
+    threads: usize,
+    mv_len: usize,
+    /// Reports hashes to the worker watchdog
+    heartbeat: Option<Arc<Heartbeat>>,
+    /// Enforces the hashrate and duty-cycle limits
+    pacer: Option<Arc<Pacer>>,
+    extranonce: u64,
+    /// Header of the last template with the nonce to resume it from
+    cursor: Mutex<Option<(Vec<u8>, u64)>>,
+}
+
+impl CpuMiner {
+    pub fn new(threads: usize, mv_len: usize) -> Self {
+        let threads = if threads == 0 { affinity::current().threads() } else { threads };
+        info!("🧮 Initializing CPU miner with {} thread(s)", threads);
+        Self {
+            threads,
+            mv_len,
+            heartbeat: None,
+            pacer: None,
+            extranonce: 0,
+            cursor: Mutex::new(None),
+        }
+    }
+
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    pub fn set_heartbeat(&mut self, heartbeat: Arc<Heartbeat>) {
+        self.heartbeat = Some(heartbeat);
+    }
+
+    pub fn set_pacer(&mut self, pacer: Arc<Pacer>) {
+        self.pacer = Some(pacer);
+    }
+
+    pub fn set_extranonce(&mut self, extranonce: u64) {
+        self.extranonce = extranonce;
+    }
+
+    /// Counter-based brute-force over up to `max_hashes` nonces, split across worker threads.
+    /// A round on the same template continues from where the previous one stopped.
+    /// Workers stop as soon as `found` is set, a template refresh or shutdown is requested,
+    /// hold their position while mining is paused and sleep while ahead of the limits.
+    /// A thermal back-off of the pacer can run the round on fewer threads.
+    /// A short mutation vector can run out of nonces on one template: the round that
+    /// hashes the last one requests a new template, and later rounds on the same template
+    /// return at once, holding the heartbeat since there is nothing left to search.
+    /// Returns the solution (if any) and the number of hashes computed.
+    pub fn mine_bruteforce(
+        &self,
+        header_prefix: &[u8],
+        target: &BigUint,
+        max_hashes: u64,
+        found: &AtomicBool,
+        control: &MinerControl,
+    ) -> (Option<Solution>, u64) {
+        let start = match &*self.cursor.lock() {
+            Some((header, next)) if header.as_slice() == header_prefix => *next,
+            _ => 0,
+        };
+        if start >= nonce_space(self.mv_len) {
+            if let Some(heartbeat) = &self.heartbeat {
+                heartbeat.hold();
+            }
+            return (None, 0);
+        }
+        let range = NonceRange {
+            extranonce: self.extranonce,
+            start,
+            count: max_hashes,
+        };
+        let progress = |hashes: u64| {
+            if let Some(heartbeat) = &self.heartbeat {
+                heartbeat.beat(hashes);
+            }
+            if let Some(pacer) = &self.pacer {
+                pacer.pace(hashes, self.heartbeat.as_deref(), &|| found.load(Ordering::Relaxed));
+            }
+            if control.refresh_requested() || control.is_shutting_down() {
+                return false;
+            }
+            control.wait_while_paused(found);
+            true
+        };
+        let outcome = search_range(
+            header_prefix,
+            self.mv_len,
+            &target_to_bytes(target),
+            range,
+            self.pacer.as_ref().map_or(self.threads, |pacer| pacer.threads(self.threads)),
+            found,
+            &progress,
+        );
+        *self.cursor.lock() = Some((header_prefix.to_vec(), outcome.resume));
+        if outcome.solution.is_none() && outcome.resume >= nonce_space(self.mv_len) {
+            let space = nonce_space(self.mv_len);
+            info!("🔚 All {} nonces of this template were hashed, requesting a new template", space);
+            control.request_refresh();
+        }
+        (outcome.solution.map(|(solution, _)| solution), outcome.hashes)
+    }
+}
+
+/// Converts a target into 32 big-endian bytes, saturating at 2^256 - 1 so that
+/// digests can be compared bytewise
+pub fn target_to_bytes(target: &BigUint) -> [u8; 32] {
+    let bytes = target.to_bytes_be();
+    let mut out = [0u8; 32];
+    if bytes.len() > 32 {
+        out = [0xff; 32];
+    } else {
+        out[32 - bytes.len()..].copy_from_slice(&bytes);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alloc_count;
+
+    /// Hashes `count` nonces against an unreachable target, counting allocations from
+    /// each thread's first progress report on
+    fn search_allocations(count: u64, threads: usize) -> (RangeOutcome, u64) {
+        let header = [0xab; 80];
+        let range = NonceRange {
+            extranonce: 7,
+            start: 0,
+            count,
+        };
+        let found = AtomicBool::new(false);
+        let progress = |_| {
+            alloc_count::track();
+            true
+        };
+        alloc_count::measure(|| search_range(&header, 16, &[0; 32], range, threads, &found, &progress))
+    }
+
+    #[test]
+    fn search_range_does_not_allocate() {
+        for threads in [1, 3] {
+            for count in [10_000, 100_000] {
+                let (outcome, allocations) = search_allocations(count, threads);
+                assert_eq!(outcome.hashes, count);
+                assert_eq!(allocations, 0, "{} hashes on {} thread(s)", count, threads);
+            }
+        }
+    }
+
+    #[test]
+    fn search_range_covers_the_range_once() {
+        let header = [0x11; 40];
+        let found = AtomicBool::new(false);
+        let range = NonceRange {
+            extranonce: 0,
+            start: 5,
+            count: 1000,
+        };
+        let outcome = search_range(&header, 16, &[0; 32], range, 4, &found, &|_| true);
+        assert_eq!(outcome.hashes, 1000);
+        assert_eq!(outcome.resume, 1005);
+        assert!(outcome.solution.is_none());
+    }
+
+    #[test]
+    fn exhausted_nonce_space_requests_a_template_without_stalling() {
+        let settings = crate::control::RuntimeSettings {
+            node_urls: vec!["http://a:36669".to_string()],
+            active_node: 0,
+            miner_address: None,
+            threads: 2,
+            batches: 1,
+            population: 1,
+            throttle: Default::default(),
+        };
+        let control = MinerControl::new(settings, None);
+        let heartbeat = Arc::new(Heartbeat::default());
+        let mut miner = CpuMiner::new(2, 1);
+        miner.set_heartbeat(Arc::clone(&heartbeat));
+        let (header, found) = ([0x22; 40], AtomicBool::new(false));
+        let unreachable = BigUint::from(0u32);
+
+        // One byte of mutation vector: 256 nonces
+        let (solution, hashes) = miner.mine_bruteforce(&header, &unreachable, 1000, &found, &control);
+        assert!(solution.is_none());
+        assert_eq!(hashes, 256);
+        assert!(control.take_refresh());
+
+        // The node served the same template again: nothing to hash, but not a stall either
+        heartbeat.start();
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        let (_, hashes) = miner.mine_bruteforce(&header, &unreachable, 1000, &found, &control);
+        assert_eq!(hashes, 0);
+        assert!(!control.refresh_requested());
+        assert!(heartbeat.stalled_for().unwrap() < std::time::Duration::from_millis(100));
+
+        // A new template starts over
+        let (_, hashes) = miner.mine_bruteforce(&[0x33; 40], &unreachable, 100, &found, &control);
+        assert_eq!(hashes, 100);
+    }
+}
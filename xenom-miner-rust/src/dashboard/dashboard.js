@@ -0,0 +1,139 @@
+// Xenom miner dashboard. Polls /stats and keeps a short hashrate history in memory.
+(function () {
+  "use strict";
+
+  var POLL_MS = 2000;
+  var HISTORY = 150;
+  var COLORS = ["#61afef", "#98c379", "#e5c07b", "#c678dd", "#56b6c2", "#e06c75", "#d19a66", "#abb2bf"];
+  var samples = [];
+  var token = localStorage.getItem("xenomApiToken") || "";
+
+  function $(id) { return document.getElementById(id); }
+
+  // Escapes text for element content and quoted attribute values
+  function esc(value) {
+    return String(value === null || value === undefined ? "-" : value)
+      .replace(/&/g, "&amp;").replace(/</g, "&lt;").replace(/>/g, "&gt;")
+      .replace(/"/g, "&quot;").replace(/'/g, "&#39;");
+  }
+
+  function duration(secs) {
+    var d = Math.floor(secs / 86400), h = Math.floor(secs % 86400 / 3600), m = Math.floor(secs % 3600 / 60);
+    return (d ? d + "d " : "") + (d || h ? h + "h " : "") + m + "m " + (secs % 60) + "s";
+  }
+
+  function ago(stats, t) {
+    return t === null || t === undefined ? "-" : duration(Math.max(0, stats.uptime_secs - t)) + " ago";
+  }
+
+  function table(el, head, rows) {
+    el.innerHTML = "<tr>" + head.map(function (h) { return "<th>" + h + "</th>"; }).join("") + "</tr>" +
+      (rows.length ? rows.join("") : "<tr><td colspan=\"" + head.length + "\" class=\"muted\">none</td></tr>");
+  }
+
+  function drawChart() {
+    var canvas = $("chart");
+    var ctx = canvas.getContext("2d");
+    var w = canvas.width = canvas.clientWidth * devicePixelRatio;
+    var h = canvas.height = 220 * devicePixelRatio;
+    ctx.clearRect(0, 0, w, h);
+    if (!samples.length) return;
+
+    var devices = samples[samples.length - 1].length;
+    var max = 0;
+    samples.forEach(function (s) { s.forEach(function (v) { max = Math.max(max, v); }); });
+    max = max > 0 ? max * 1.1 : 1;
+
+    ctx.strokeStyle = "#252c37";
+    ctx.fillStyle = "#7b8594";
+    ctx.font = 11 * devicePixelRatio + "px sans-serif";
+    for (var g = 0; g <= 4; g++) {
+      var y = h - (h - 10) * g / 4;
+      ctx.beginPath(); ctx.moveTo(0, y); ctx.lineTo(w, y); ctx.stroke();
+      ctx.fillText((max * g / 4).toFixed(2), 4, y - 2);
+    }
+
+    for (var d = 0; d < devices; d++) {
+      ctx.strokeStyle = COLORS[d % COLORS.length];
+      ctx.lineWidth = 2 * devicePixelRatio;
+      ctx.beginPath();
+      samples.forEach(function (s, i) {
+        var x = w * i / (HISTORY - 1);
+        var y = h - (h - 10) * (s[d] || 0) / max;
+        if (i === 0) ctx.moveTo(x, y); else ctx.lineTo(x, y);
+      });
+      ctx.stroke();
+    }
+
+    $("legend").innerHTML = samples[samples.length - 1].map(function (v, d) {
+      return "<span><i style=\"background:" + COLORS[d % COLORS.length] + "\"></i>device " + d +
+        ": " + v.toFixed(2) + " MH/s</span>";
+    }).join("");
+  }
+
+  function render(stats) {
+    var status = $("status");
+    if (stats.paused) { status.textContent = "paused"; status.className = "badge warn"; }
+    else if (stats.mining) { status.textContent = "mining"; status.className = "badge ok"; }
+    else { status.textContent = "idle"; status.className = "badge"; }
+
+    $("version").textContent = "v" + stats.version;
+    $("hashrate").textContent = stats.hashrate_mhs.toFixed(2) + " MH/s";
+    $("height").textContent = stats.current_height;
+    $("shares").innerHTML = "<span class=\"ok\">" + stats.accepted_shares + "</span> / <span class=\"bad\">" +
+      stats.rejected_shares + "</span>";
+    $("uptime").textContent = duration(stats.uptime_secs);
+
+    samples.push(stats.per_gpu_hashrate_mhs.slice());
+    if (samples.length > HISTORY) samples.shift();
+    drawChart();
+
+    var t = stats.current_template;
+    table($("template"), ["Field", "Value"], t ? [
+      "<tr><td>Height</td><td>" + esc(t.height) + "</td></tr>",
+      "<tr><td>Difficulty bits</td><td class=\"mono\">0x" + esc(t.difficulty_bits) + "</td></tr>",
+      "<tr><td>Target</td><td class=\"mono\">" + esc(t.target_hex) + "</td></tr>",
+      "<tr><td>Received</td><td>" + ago(stats, t.received_at) + "</td></tr>"
+    ] : []);
+
+    table($("nodes"), ["Node", "Status", "Latency", "Last success", "Failures"], stats.nodes.map(function (n) {
+      return "<tr><td class=\"mono\">" + esc(n.url) + (n.active ? " ★" : "") + "</td>" +
+        "<td class=\"" + (n.reachable ? "ok\">up" : "bad\" title=\"" + esc(n.last_error) + "\">down") + "</td>" +
+        "<td>" + (n.latency_ms === null ? "-" : n.latency_ms + " ms") + "</td>" +
+        "<td>" + ago(stats, n.last_success) + "</td><td>" + n.consecutive_failures + "</td></tr>";
+    }));
+
+    table($("blocks"), ["Height", "Device", "Result", "Hash", "When"], stats.recent_blocks.slice().reverse().map(function (b) {
+      return "<tr><td>" + b.height + "</td><td>" + b.device + "</td>" +
+        "<td class=\"" + (b.accepted ? "ok\">accepted" : "bad\">rejected") + "</td>" +
+        "<td class=\"mono\" title=\"" + esc(b.message) + "\">" + esc(b.hash.slice(0, 16)) + "…</td>" +
+        "<td>" + ago(stats, b.submitted_at) + "</td></tr>";
+    }));
+
+    table($("rejects"), ["Reason", "Count"], Object.keys(stats.reject_reasons).map(function (reason) {
+      return "<tr><td>" + esc(reason) + "</td><td>" + stats.reject_reasons[reason] + "</td></tr>";
+    }));
+  }
+
+  function poll() {
+    var headers = token ? { "Authorization": "Bearer " + token } : {};
+    fetch("/stats", { headers: headers })
+      .then(function (res) {
+        if (res.status === 401) {
+          token = prompt("API token") || "";
+          localStorage.setItem("xenomApiToken", token);
+          throw new Error("unauthorized");
+        }
+        return res.json();
+      })
+      .then(render)
+      .catch(function () {
+        $("status").textContent = "offline";
+        $("status").className = "badge bad";
+      })
+      .then(function () { setTimeout(poll, POLL_MS); });
+  }
+
+  window.addEventListener("resize", drawChart);
+  poll();
+})();
@@ -0,0 +1,601 @@
+//! Evaluation harness for search strategies (`evaluate` subcommand).
+//! Runs many independent trials per strategy at easy targets, records hashes and
+//! wall time to solution, and compares strategies with rank-based tests.
+
+use crate::ga::{Evaluation, Fitness, GaConfig, GeneticSearch, TargetFitness};
+use log::info;
+use num_bigint::BigUint;
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Strategy {
+    /// A fresh random mutation vector per hash (`--brute`)
+    Brute,
+    /// A random start, then counting up
+    Sequential,
+    /// The CPU GA with the `--ga-*` options
+    Ga,
+}
+
+#[derive(Debug, Clone)]
+pub struct EvalConfig {
+    pub trials: usize,
+    /// Targets as leading zero bits: the target is `2^(256 - bits) - 1`
+    pub target_bits: Vec<u32>,
+    pub strategies: Vec<Strategy>,
+    /// Hashes after which a trial gives up (0 = 32x the expected hashes)
+    pub max_hashes: u64,
+    /// Bytes of random header prefix per trial
+    pub header_len: usize,
+    pub mv_len: usize,
+    pub population: usize,
+    pub ga: GaConfig,
+    pub csv: Option<PathBuf>,
+    pub json: Option<PathBuf>,
+}
+
+/// One search
+#[derive(Debug, Clone, Serialize)]
+pub struct Trial {
+    pub strategy: Strategy,
+    pub target_bits: u32,
+    pub trial: usize,
+    pub solved: bool,
+    /// Hashes computed, up to the cap for unsolved trials
+    pub hashes: u64,
+    pub time_ms: f64,
+}
+
+/// Distribution of one strategy's trials at one target
+#[derive(Debug, Clone, Serialize)]
+pub struct Summary {
+    pub strategy: Strategy,
+    pub target_bits: u32,
+    pub trials: usize,
+    pub solved: usize,
+    /// `2^bits`, the mean for any search that never repeats a guess
+    pub expected_hashes: f64,
+    pub mean_hashes: f64,
+    pub median_hashes: f64,
+    pub p90_hashes: f64,
+    pub p99_hashes: f64,
+    pub mean_time_ms: f64,
+    pub median_time_ms: f64,
+}
+
+/// Two strategies at one target, compared on hashes to solution.
+/// Unsolved trials are censored at the cap: the rank tests compare hashes capped there
+/// (every unsolved trial ties at the cap), and the log-rank test treats them as censored.
+#[derive(Debug, Clone, Serialize)]
+pub struct Comparison {
+    pub target_bits: u32,
+    pub a: Strategy,
+    pub b: Strategy,
+    /// Unsolved (censored) trials of `a` and `b`
+    pub a_censored: usize,
+    pub b_censored: usize,
+    /// Median hashes of `a` over those of `b`
+    pub median_ratio: f64,
+    /// Log-rank chi-square (1 degree of freedom) and its p-value
+    pub log_rank_chi2: f64,
+    pub log_rank_p: f64,
+    /// Mann-Whitney U for `a` (two-sided, normal approximation)
+    pub mann_whitney_u: f64,
+    pub mann_whitney_z: f64,
+    pub mann_whitney_p: f64,
+    /// Two-sample Kolmogorov-Smirnov statistic (asymptotic p-value)
+    pub ks_d: f64,
+    pub ks_p: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub trials: usize,
+    pub mv_len: usize,
+    pub population: usize,
+    pub summaries: Vec<Summary>,
+    pub comparisons: Vec<Comparison>,
+}
+
+/// Counts evaluations of the wrapped fitness
+struct Counting<'a> {
+    inner: TargetFitness<'a>,
+    hashes: AtomicU64,
+}
+
+impl Fitness for Counting<'_> {
+    fn evaluate(&self, genome: &[u8]) -> Evaluation {
+        self.hashes.fetch_add(1, Ordering::Relaxed);
+        self.inner.evaluate(genome)
+    }
+}
+
+fn target_for(bits: u32) -> BigUint {
+    (BigUint::from(1u8) << (256 - bits as usize)) - 1u8
+}
+
+/// Hashes after which a trial at `bits` gives up
+fn cap_for(config: &EvalConfig, bits: u32) -> u64 {
+    match config.max_hashes {
+        0 => 32u64 << bits,
+        n => n,
+    }
+}
+
+/// Hashes to solution capped at `cap`, and whether the solution came within it.
+/// A GA trial can overshoot the cap by part of a generation; that counts as censored too.
+fn censored(trial: &Trial, cap: u64) -> (f64, bool) {
+    (trial.hashes.min(cap) as f64, trial.solved && trial.hashes <= cap)
+}
+
+/// Runs every strategy at every target and writes the reports. Returns the report for printing.
+pub fn run(config: &EvalConfig, rng: &mut dyn RngCore) -> Result<Report, String> {
+    if config.trials == 0 {
+        return Err("trials must be greater than 0".to_string());
+    }
+    if let Some(bits) = config.target_bits.iter().find(|&&b| b == 0 || b > 48) {
+        return Err(format!("target bits {} is outside 1-48", bits));
+    }
+
+    let mut trials = Vec::new();
+    for &bits in &config.target_bits {
+        let cap = cap_for(config, bits);
+        // Strategies run one after another so their wall times are not skewed by each other
+        for &strategy in &config.strategies {
+            info!("🧪 {:?} at {} bits: {} trials", strategy, bits, config.trials);
+            let seeds: Vec<u64> = (0..config.trials).map(|_| rng.next_u64()).collect();
+            let start = Instant::now();
+            let mut batch: Vec<Trial> = seeds
+                .into_par_iter()
+                .enumerate()
+                .map(|(trial, seed)| run_trial(config, strategy, bits, cap, trial, seed))
+                .collect();
+            let solved = batch.iter().filter(|t| t.solved).count();
+            info!(
+                "   {}/{} solved in {:.1}s",
+                solved,
+                config.trials,
+                start.elapsed().as_secs_f64()
+            );
+            trials.append(&mut batch);
+        }
+    }
+
+    let report = build_report(config, &trials);
+    if let Some(path) = &config.csv {
+        write_csv(path, &trials).map_err(|e| format!("cannot write {}: {}", path.display(), e))?;
+        info!("📄 Trials written to {}", path.display());
+    }
+    if let Some(path) = &config.json {
+        let json = serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?;
+        std::fs::write(path, json).map_err(|e| format!("cannot write {}: {}", path.display(), e))?;
+        info!("📄 Report written to {}", path.display());
+    }
+    Ok(report)
+}
+
+fn run_trial(config: &EvalConfig, strategy: Strategy, bits: u32, cap: u64, trial: usize, seed: u64) -> Trial {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut header = vec![0u8; config.header_len];
+    rng.fill_bytes(&mut header);
+    let target = target_for(bits);
+    let fitness = Counting {
+        inner: TargetFitness::new(&header, &target),
+        hashes: AtomicU64::new(0),
+    };
+
+    let start = Instant::now();
+    let solved = match strategy {
+        Strategy::Brute => {
+            let mut mv = vec![0u8; config.mv_len];
+            (0..cap).any(|_| {
+                rng.fill_bytes(&mut mv);
+                fitness.evaluate(&mv).solved
+            })
+        }
+        Strategy::Sequential => {
+            let mut mv = vec![0u8; config.mv_len];
+            rng.fill_bytes(&mut mv);
+            (0..cap).any(|_| {
+                // Little-endian counter over the whole vector
+                for byte in mv.iter_mut() {
+                    *byte = byte.wrapping_add(1);
+                    if *byte != 0 {
+                        break;
+                    }
+                }
+                fitness.evaluate(&mv).solved
+            })
+        }
+        Strategy::Ga => {
+            let generations = cap.div_ceil(config.population.max(2) as u64) as usize;
+            GeneticSearch::new(&config.ga)
+                .quiet()
+                .run(&fitness, config.population, config.mv_len, generations, &mut rng)
+                .is_some()
+        }
+    };
+    Trial {
+        strategy,
+        target_bits: bits,
+        trial,
+        solved,
+        hashes: fitness.hashes.load(Ordering::Relaxed),
+        time_ms: start.elapsed().as_secs_f64() * 1000.0,
+    }
+}
+
+fn write_csv(path: &PathBuf, trials: &[Trial]) -> std::io::Result<()> {
+    let mut out = std::io::BufWriter::new(std::fs::File::create(path)?);
+    writeln!(out, "strategy,target_bits,trial,solved,hashes,time_ms")?;
+    for t in trials {
+        writeln!(
+            out,
+            "{},{},{},{},{},{:.3}",
+            format!("{:?}", t.strategy).to_lowercase(),
+            t.target_bits,
+            t.trial,
+            t.solved,
+            t.hashes,
+            t.time_ms
+        )?;
+    }
+    out.flush()
+}
+
+fn build_report(config: &EvalConfig, trials: &[Trial]) -> Report {
+    let mut summaries = Vec::new();
+    let mut comparisons = Vec::new();
+    for &bits in &config.target_bits {
+        let cap = cap_for(config, bits);
+        let hashes = |strategy: Strategy| -> Vec<f64> {
+            trials
+                .iter()
+                .filter(|t| t.strategy == strategy && t.target_bits == bits)
+                .map(|t| t.hashes as f64)
+                .collect()
+        };
+        let survival = |strategy: Strategy| -> Vec<(f64, bool)> {
+            trials
+                .iter()
+                .filter(|t| t.strategy == strategy && t.target_bits == bits)
+                .map(|t| censored(t, cap))
+                .collect()
+        };
+        for &strategy in &config.strategies {
+            let of: Vec<&Trial> = trials.iter().filter(|t| t.strategy == strategy && t.target_bits == bits).collect();
+            let mut h = hashes(strategy);
+            let mut times: Vec<f64> = of.iter().map(|t| t.time_ms).collect();
+            h.sort_by(f64::total_cmp);
+            times.sort_by(f64::total_cmp);
+            summaries.push(Summary {
+                strategy,
+                target_bits: bits,
+                trials: of.len(),
+                solved: of.iter().filter(|t| t.solved).count(),
+                expected_hashes: 2f64.powi(bits as i32),
+                mean_hashes: mean(&h),
+                median_hashes: quantile(&h, 0.5),
+                p90_hashes: quantile(&h, 0.9),
+                p99_hashes: quantile(&h, 0.99),
+                mean_time_ms: mean(&times),
+                median_time_ms: quantile(&times, 0.5),
+            });
+        }
+        for (i, &a) in config.strategies.iter().enumerate() {
+            for &b in &config.strategies[i + 1..] {
+                let (sa, sb) = (survival(a), survival(b));
+                let (x, y): (Vec<f64>, Vec<f64>) = (sa.iter().map(|s| s.0).collect(), sb.iter().map(|s| s.0).collect());
+                let (u, z, p) = mann_whitney(&x, &y);
+                let (d, ks_p) = kolmogorov_smirnov(&x, &y);
+                let (chi2, lr_p) = log_rank(&sa, &sb);
+                let (mut sx, mut sy) = (x.clone(), y.clone());
+                sx.sort_by(f64::total_cmp);
+                sy.sort_by(f64::total_cmp);
+                comparisons.push(Comparison {
+                    target_bits: bits,
+                    a,
+                    b,
+                    a_censored: sa.iter().filter(|s| !s.1).count(),
+                    b_censored: sb.iter().filter(|s| !s.1).count(),
+                    median_ratio: quantile(&sx, 0.5) / quantile(&sy, 0.5).max(1.0),
+                    log_rank_chi2: chi2,
+                    log_rank_p: lr_p,
+                    mann_whitney_u: u,
+                    mann_whitney_z: z,
+                    mann_whitney_p: p,
+                    ks_d: d,
+                    ks_p,
+                });
+            }
+        }
+    }
+    Report {
+        trials: config.trials,
+        mv_len: config.mv_len,
+        population: config.population,
+        summaries,
+        comparisons,
+    }
+}
+
+impl Report {
+    /// Human-readable tables for stdout
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        out.push_str(&format!(
+            "{:<10} {:>4} {:>9} {:>12} {:>12} {:>12} {:>12} {:>12} {:>10}\n",
+            "strategy", "bits", "solved", "expected", "mean", "median", "p90", "p99", "median ms"
+        ));
+        for s in &self.summaries {
+            out.push_str(&format!(
+                "{:<10} {:>4} {:>9} {:>12.0} {:>12.0} {:>12.0} {:>12.0} {:>12.0} {:>10.2}\n",
+                format!("{:?}", s.strategy).to_lowercase(),
+                s.target_bits,
+                format!("{}/{}", s.solved, s.trials),
+                s.expected_hashes,
+                s.mean_hashes,
+                s.median_hashes,
+                s.p90_hashes,
+                s.p99_hashes,
+                s.median_time_ms
+            ));
+        }
+        if !self.comparisons.is_empty() {
+            out.push_str(&format!(
+                "\n{:<22} {:>4} {:>11} {:>12} {:>10} {:>9} {:>10} {:>8} {:>10}\n",
+                "comparison", "bits", "censored", "median a/b", "LR p", "MW z", "MW p", "KS D", "KS p"
+            ));
+            for c in &self.comparisons {
+                out.push_str(&format!(
+                    "{:<22} {:>4} {:>11} {:>12.3} {:>10.4} {:>9.3} {:>10.4} {:>8.4} {:>10.4}\n",
+                    format!("{:?} vs {:?}", c.a, c.b).to_lowercase(),
+                    c.target_bits,
+                    format!("{}/{}", c.a_censored, c.b_censored),
+                    c.median_ratio,
+                    c.log_rank_p,
+                    c.mann_whitney_z,
+                    c.mann_whitney_p,
+                    c.ks_d,
+                    c.ks_p
+                ));
+            }
+        }
+        out
+    }
+}
+
+fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// Nearest-rank quantile of sorted values
+fn quantile(sorted: &[f64], q: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (q * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Complementary error function (Numerical Recipes `erfcc`, relative error below 1.2e-7)
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let poly = -z * z - 1.26551223
+        + t * (1.00002368
+            + t * (0.37409196
+                + t * (0.09678418
+                    + t * (-0.18628806
+                        + t * (0.27886807 + t * (-1.13520398 + t * (1.48851587 + t * (-0.82215223 + t * 0.17087277))))))));
+    let r = t * poly.exp();
+    if x >= 0.0 {
+        r
+    } else {
+        2.0 - r
+    }
+}
+
+/// Mann-Whitney U of `x` with tie-corrected z and two-sided p
+fn mann_whitney(x: &[f64], y: &[f64]) -> (f64, f64, f64) {
+    let (n1, n2) = (x.len() as f64, y.len() as f64);
+    if x.is_empty() || y.is_empty() {
+        return (0.0, 0.0, 1.0);
+    }
+    let mut all: Vec<(f64, bool)> = x.iter().map(|&v| (v, true)).chain(y.iter().map(|&v| (v, false))).collect();
+    all.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let n = all.len();
+    let (mut rank_sum, mut ties) = (0.0, 0.0);
+    let mut i = 0;
+    while i < n {
+        let mut j = i;
+        while j + 1 < n && all[j + 1].0 == all[i].0 {
+            j += 1;
+        }
+        // Ranks i+1..=j+1 share their average
+        let rank = (i + j + 2) as f64 / 2.0;
+        rank_sum += all[i..=j].iter().filter(|(_, from_x)| *from_x).count() as f64 * rank;
+        let t = (j - i + 1) as f64;
+        ties += t * t * t - t;
+        i = j + 1;
+    }
+
+    let u = rank_sum - n1 * (n1 + 1.0) / 2.0;
+    let n = n1 + n2;
+    let variance = n1 * n2 / 12.0 * ((n + 1.0) - ties / (n * (n - 1.0)));
+    if variance <= 0.0 {
+        return (u, 0.0, 1.0);
+    }
+    let z = (u - n1 * n2 / 2.0) / variance.sqrt();
+    (u, z, erfc(z.abs() / std::f64::consts::SQRT_2))
+}
+
+/// Log-rank test of two samples of `(hashes, solved)`, where unsolved samples are
+/// censored: at risk up to their hashes, never an event. Returns chi-square and p.
+fn log_rank(x: &[(f64, bool)], y: &[(f64, bool)]) -> (f64, f64) {
+    let mut times: Vec<f64> = x.iter().chain(y).filter(|s| s.1).map(|s| s.0).collect();
+    times.sort_by(f64::total_cmp);
+    times.dedup();
+    let at_risk = |sample: &[(f64, bool)], t: f64| sample.iter().filter(|s| s.0 >= t).count() as f64;
+    let events = |sample: &[(f64, bool)], t: f64| sample.iter().filter(|s| s.1 && s.0 == t).count() as f64;
+
+    let (mut observed, mut expected, mut variance) = (0.0, 0.0, 0.0);
+    for t in times {
+        let (n1, n2) = (at_risk(x, t), at_risk(y, t));
+        let (d1, d) = (events(x, t), events(x, t) + events(y, t));
+        let n = n1 + n2;
+        observed += d1;
+        expected += d * n1 / n;
+        if n > 1.0 {
+            variance += d * (n1 / n) * (n2 / n) * (n - d) / (n - 1.0);
+        }
+    }
+    if variance <= 0.0 {
+        return (0.0, 1.0);
+    }
+    let chi2 = (observed - expected).powi(2) / variance;
+    // Chi-square with 1 degree of freedom: P(X > chi2) = erfc(sqrt(chi2 / 2))
+    (chi2, erfc((chi2 / 2.0).sqrt()))
+}
+
+/// Two-sample Kolmogorov-Smirnov D with its asymptotic p-value
+fn kolmogorov_smirnov(x: &[f64], y: &[f64]) -> (f64, f64) {
+    if x.is_empty() || y.is_empty() {
+        return (0.0, 1.0);
+    }
+    let (mut x, mut y) = (x.to_vec(), y.to_vec());
+    x.sort_by(f64::total_cmp);
+    y.sort_by(f64::total_cmp);
+    let (n1, n2) = (x.len() as f64, y.len() as f64);
+
+    let (mut i, mut j, mut d) = (0, 0, 0.0f64);
+    while i < x.len() && j < y.len() {
+        let v = x[i].min(y[j]);
+        while i < x.len() && x[i] <= v {
+            i += 1;
+        }
+        while j < y.len() && y[j] <= v {
+            j += 1;
+        }
+        d = d.max((i as f64 / n1 - j as f64 / n2).abs());
+    }
+
+    let en = (n1 * n2 / (n1 + n2)).sqrt();
+    let lambda = (en + 0.12 + 0.11 / en) * d;
+    (d, ks_probability(lambda))
+}
+
+/// Kolmogorov distribution tail `Q(lambda)`
+fn ks_probability(lambda: f64) -> f64 {
+    if lambda < 0.2 {
+        return 1.0;
+    }
+    let mut sum = 0.0;
+    let mut sign = 1.0;
+    for j in 1..=100 {
+        let term = sign * (-2.0 * (j * j) as f64 * lambda * lambda).exp();
+        sum += term;
+        if term.abs() < 1e-12 {
+            break;
+        }
+        sign = -sign;
+    }
+    (2.0 * sum).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(actual: f64, expected: f64, tolerance: f64) {
+        assert!((actual - expected).abs() <= tolerance * expected.abs().max(1e-300), "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn erfc_matches_reference_values() {
+        // Reference values from the C library's erfc (Python `math.erfc`)
+        for (x, expected) in [
+            (0.0, 1.0),
+            (0.5, 0.4795001221869535),
+            (1.0, 0.15729920705028513),
+            (2.0, 0.004677734981047266),
+            (3.0, 2.209049699858544e-5),
+            (-1.0, 1.8427007929497148),
+        ] {
+            close(erfc(x), expected, 1.2e-7);
+        }
+    }
+
+    #[test]
+    fn mann_whitney_matches_reference_values() {
+        // Separated samples: U = 0, z = -12.5 / sqrt(25 * 11 / 12)
+        let (u, z, p) = mann_whitney(&[1.0, 2.0, 3.0, 4.0, 5.0], &[6.0, 7.0, 8.0, 9.0, 10.0]);
+        assert_eq!(u, 0.0);
+        close(z, -2.6111648393354674, 1e-12);
+        close(p, 0.009023438818080334, 1e-6);
+        // With ties: U = 2.5, tie-corrected variance 16 / 12 * (9 - 30 / 56)
+        let (u, z, p) = mann_whitney(&[1.0, 2.0, 2.0, 3.0], &[2.0, 3.0, 4.0, 5.0]);
+        assert_eq!(u, 2.5);
+        close(z, -1.6371865339158649, 1e-12);
+        close(p, 0.1015914998616528, 1e-6);
+        assert_eq!(mann_whitney(&[], &[1.0]), (0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn kolmogorov_smirnov_matches_reference_values() {
+        // Asymptotic p-values of Numerical Recipes' `kstwo`
+        let (d, p) = kolmogorov_smirnov(&[1.0, 2.0, 3.0, 4.0, 5.0], &[6.0, 7.0, 8.0, 9.0, 10.0]);
+        assert_eq!(d, 1.0);
+        close(p, 0.0037813540593701006, 1e-9);
+        let (d, p) = kolmogorov_smirnov(&[1.0, 3.0, 5.0, 7.0], &[2.0, 4.0, 6.0, 8.0]);
+        assert_eq!(d, 0.25);
+        close(p, 0.9968756885202118, 1e-9);
+        assert_eq!(kolmogorov_smirnov(&[2.0, 1.0], &[1.0, 2.0]), (0.0, 1.0));
+    }
+
+    #[test]
+    fn log_rank_handles_censored_trials() {
+        // Every event of `a` comes before any of `b`; one `b` trial is censored
+        let a = [(1.0, true), (2.0, true), (3.0, true)];
+        let b = [(4.0, true), (5.0, true), (6.0, false)];
+        let (chi2, p) = log_rank(&a, &b);
+        close(chi2, 5.051660516605167, 1e-12);
+        close(p, 0.024602349953641786, 1e-6);
+        // Tied event times and censoring in both samples
+        let a = [(1.0, true), (3.0, true), (3.0, true), (6.0, false)];
+        let b = [(2.0, true), (3.0, true), (6.0, false), (6.0, false)];
+        let (chi2, p) = log_rank(&a, &b);
+        close(chi2, 0.34557235421166277, 1e-12);
+        close(p, 0.5566302781602341, 1e-6);
+        // Nothing solved: no evidence either way
+        assert_eq!(log_rank(&[(8.0, false)], &[(8.0, false)]), (0.0, 1.0));
+    }
+
+    #[test]
+    fn unsolved_trials_tie_at_the_cap() {
+        let trial = |solved, hashes| Trial {
+            strategy: Strategy::Ga,
+            target_bits: 4,
+            trial: 0,
+            solved,
+            hashes,
+            time_ms: 0.0,
+        };
+        assert_eq!(censored(&trial(true, 90), 100), (90.0, true));
+        // A GA generation that ran past the cap, solved or not, is censored at the cap
+        assert_eq!(censored(&trial(false, 128), 100), (100.0, false));
+        assert_eq!(censored(&trial(true, 110), 100), (100.0, false));
+    }
+}
@@ -0,0 +1,253 @@
+use crate::stats::MinerStats;
+use parking_lot::{Mutex, RwLock};
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Events queued per subscriber before new ones are dropped for that subscriber
+const SUBSCRIBER_QUEUE_LEN: usize = 256;
+
+/// Typed events pushed to `/events` subscribers
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MinerEvent {
+    NewTemplate {
+        height: u64,
+        difficulty_bits: String,
+        target_hex: String,
+        node: String,
+    },
+    SolutionFound {
+        height: u64,
+        device: usize,
+        mutation_vector: String,
+        hash: String,
+    },
+    SubmitResult {
+        height: u64,
+        device: usize,
+        accepted: bool,
+        message: String,
+    },
+    NodeFailover {
+        from: String,
+        to: String,
+        error: String,
+    },
+    DeviceError {
+        device: usize,
+        error: String,
+    },
+    /// A round ended early because a template refresh was requested (`POST /control/refresh`
+    /// or an exhausted nonce space). The node is not polled during a round, so a template
+    /// that went stale on the node only shows as the next `NewTemplate`.
+    StaleAbort {
+        height: u64,
+        reason: String,
+    },
+    Hashrate {
+        total_mhs: f64,
+        per_device_mhs: Vec<f64>,
+        total_hashes: u64,
+    },
+}
+
+impl MinerEvent {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            MinerEvent::NewTemplate { .. } => "new_template",
+            MinerEvent::SolutionFound { .. } => "solution_found",
+            MinerEvent::SubmitResult { .. } => "submit_result",
+            MinerEvent::NodeFailover { .. } => "node_failover",
+            MinerEvent::DeviceError { .. } => "device_error",
+            MinerEvent::StaleAbort { .. } => "stale_abort",
+            MinerEvent::Hashrate { .. } => "hashrate",
+        }
+    }
+}
+
+/// An event as delivered to subscribers
+#[derive(Debug, Clone, Serialize)]
+pub struct EventEnvelope {
+    pub id: u64,
+    pub timestamp_ms: u64,
+    #[serde(flatten)]
+    pub event: MinerEvent,
+}
+
+/// Fan-out of miner events to any number of stream subscribers
+#[derive(Default)]
+pub struct EventBus {
+    next_id: AtomicU64,
+    subscribers: Mutex<Vec<SyncSender<Arc<EventEnvelope>>>>,
+}
+
+impl EventBus {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// Delivers an event to every subscriber without blocking the caller.
+    /// Slow subscribers miss events; disconnected ones are dropped.
+    pub fn publish(&self, event: MinerEvent) {
+        let mut subscribers = self.subscribers.lock();
+        if subscribers.is_empty() {
+            return;
+        }
+        let envelope = Arc::new(EventEnvelope {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed) + 1,
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0),
+            event,
+        });
+        subscribers.retain(|tx| !matches!(tx.try_send(Arc::clone(&envelope)), Err(TrySendError::Disconnected(_))));
+    }
+
+    pub fn subscribe(&self) -> Receiver<Arc<EventEnvelope>> {
+        let (tx, rx) = sync_channel(SUBSCRIBER_QUEUE_LEN);
+        self.subscribers.lock().push(tx);
+        rx
+    }
+
+    /// Publishes a `hashrate` sample from the stats every `interval`
+    pub fn start_hashrate_sampler(self: &Arc<Self>, stats: Arc<RwLock<MinerStats>>, interval: Duration) {
+        let bus = Arc::clone(self);
+        std::thread::spawn(move || loop {
+            std::thread::sleep(interval);
+            let event = {
+                let stats = stats.read();
+                MinerEvent::Hashrate {
+                    total_mhs: stats.hashrate_mhs,
+                    per_device_mhs: stats.per_gpu_hashrate_mhs.clone(),
+                    total_hashes: stats.total_hashes,
+                }
+            };
+            bus.publish(event);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stale(height: u64) -> MinerEvent {
+        MinerEvent::StaleAbort {
+            height,
+            reason: "template refresh requested".to_string(),
+        }
+    }
+
+    fn height(envelope: &EventEnvelope) -> u64 {
+        match envelope.event {
+            MinerEvent::StaleAbort { height, .. } => height,
+            _ => panic!("unexpected event {:?}", envelope.event),
+        }
+    }
+
+    #[test]
+    fn events_serialize_with_their_kind() {
+        let events = [
+            MinerEvent::NewTemplate {
+                height: 1,
+                difficulty_bits: "1d00ffff".to_string(),
+                target_hex: "00ff".to_string(),
+                node: "http://a:36669".to_string(),
+            },
+            MinerEvent::SolutionFound {
+                height: 1,
+                device: 0,
+                mutation_vector: "ab".to_string(),
+                hash: "00".to_string(),
+            },
+            MinerEvent::SubmitResult {
+                height: 1,
+                device: 0,
+                accepted: true,
+                message: "ok".to_string(),
+            },
+            MinerEvent::NodeFailover {
+                from: "http://a:36669".to_string(),
+                to: "http://b:36669".to_string(),
+                error: "timeout".to_string(),
+            },
+            MinerEvent::DeviceError {
+                device: 1,
+                error: "panic".to_string(),
+            },
+            stale(1),
+            MinerEvent::Hashrate {
+                total_mhs: 1.5,
+                per_device_mhs: vec![1.0, 0.5],
+                total_hashes: 100,
+            },
+        ];
+        for event in events {
+            let value = serde_json::to_value(&event).unwrap();
+            assert_eq!(value["type"], event.kind());
+        }
+
+        let envelope = EventEnvelope {
+            id: 7,
+            timestamp_ms: 1_760_000_000_000,
+            event: MinerEvent::SubmitResult {
+                height: 12345,
+                device: 2,
+                accepted: false,
+                message: "stale".to_string(),
+            },
+        };
+        assert_eq!(
+            serde_json::to_string(&envelope).unwrap(),
+            r#"{"id":7,"timestamp_ms":1760000000000,"type":"submit_result","height":12345,"device":2,"accepted":false,"message":"stale"}"#
+        );
+    }
+
+    #[test]
+    fn publish_reaches_every_subscriber_in_order() {
+        let bus = EventBus::new();
+        // Nobody listens: the event is dropped without using an id
+        bus.publish(stale(0));
+        let (a, b) = (bus.subscribe(), bus.subscribe());
+        bus.publish(stale(1));
+        bus.publish(stale(2));
+        for rx in [&a, &b] {
+            let received: Vec<(u64, u64)> = rx.try_iter().map(|e| (e.id, height(&e))).collect();
+            assert_eq!(received, [(1, 1), (2, 2)]);
+        }
+    }
+
+    #[test]
+    fn disconnected_subscribers_are_dropped() {
+        let bus = EventBus::new();
+        let kept = bus.subscribe();
+        drop(bus.subscribe());
+        assert_eq!(bus.subscribers.lock().len(), 2);
+        bus.publish(stale(1));
+        assert_eq!(bus.subscribers.lock().len(), 1);
+        assert_eq!(kept.try_iter().count(), 1);
+    }
+
+    #[test]
+    fn slow_subscribers_miss_events_without_blocking() {
+        let bus = EventBus::new();
+        let (slow, fast) = (bus.subscribe(), bus.subscribe());
+        let mut fast_received = 0;
+        for i in 0..SUBSCRIBER_QUEUE_LEN as u64 + 10 {
+            bus.publish(stale(i));
+            fast_received += fast.try_iter().count();
+        }
+        assert_eq!(fast_received, SUBSCRIBER_QUEUE_LEN + 10);
+
+        // The full queue keeps the oldest events; the subscriber stays and gets new ones once drained
+        let missed: Vec<u64> = slow.try_iter().map(|e| height(&e)).collect();
+        assert_eq!(missed, (0..SUBSCRIBER_QUEUE_LEN as u64).collect::<Vec<_>>());
+        assert_eq!(bus.subscribers.lock().len(), 2);
+        bus.publish(stale(999));
+        assert_eq!(slow.try_iter().map(|e| height(&e)).collect::<Vec<_>>(), [999]);
+    }
+}
@@ -0,0 +1,1218 @@
+//! Genetic search over mutation vectors, shared by the CPU GA paths.
+//! Selection, crossover, mutation and fitness are traits so that strategies can be
+//! swapped from the command line or replaced in code.
+//! The population can be split into islands that evolve on their own rayon workers
+//! and trade their best individuals now and then. With adaptation on, each population
+//! tunes its mutation rate and selection pressure to its diversity.
+
+use crate::cpu_miner::target_to_bytes;
+use crate::telemetry::TelemetrySink;
+use log::{info, log, Level};
+use num_bigint::BigUint;
+use parking_lot::Mutex;
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Mutation vector and its BLAKE3 digest
+pub type Solution = (Vec<u8>, [u8; 32]);
+
+/// Score of one candidate
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Evaluation {
+    /// Higher is better; 1.0 for a solution
+    pub fitness: f64,
+    pub hash: [u8; 32],
+    pub solved: bool,
+}
+
+/// Scores candidates. Called from several threads at once.
+pub trait Fitness: Sync {
+    fn evaluate(&self, genome: &[u8]) -> Evaluation;
+}
+
+/// Picks the index of a parent from the population's fitness values.
+/// `pressure` scales how strongly fitter parents are favoured (1.0 = as configured).
+pub trait Selection: Send + Sync {
+    fn select(&self, fitness: &[f64], pressure: f64, rng: &mut dyn RngCore) -> usize;
+}
+
+/// Combines two parents into `child`, which has the parents' length
+pub trait Crossover: Send + Sync {
+    fn crossover(&self, a: &[u8], b: &[u8], child: &mut [u8], rng: &mut dyn RngCore);
+}
+
+/// Alters a child in place. `scale` multiplies the configured rate.
+pub trait Mutation: Send + Sync {
+    fn mutate(&self, genome: &mut [u8], scale: f64, rng: &mut dyn RngCore);
+    /// Configured rate, as reported in telemetry
+    fn rate(&self) -> f64;
+}
+
+/// State of one population after a generation is evaluated
+#[derive(Debug, Clone, Serialize)]
+pub struct GenerationRecord {
+    pub generation: usize,
+    /// `None` for a single population
+    pub island: Option<usize>,
+    pub best_fitness: f64,
+    pub mean_fitness: f64,
+    pub median_fitness: f64,
+    /// Mean Hamming distance between paired individuals, as a fraction of bits
+    pub diversity: f64,
+    pub fitness_cv: f64,
+    /// Mutation rate in effect (configured rate times the adaptive scale)
+    pub mutation_rate: f64,
+    pub selection_pressure: f64,
+    /// Most leading zero bits of any hash in the generation
+    pub best_zero_bits: u32,
+    pub mean_zero_bits: f64,
+    /// Hashes computed by this population so far
+    pub hashes: u64,
+}
+
+/// Receives a record per generation and population. Called from several threads at once.
+pub trait Telemetry: Send + Sync {
+    fn record(&self, record: &GenerationRecord);
+}
+
+/// BLAKE3 of `header_prefix || genome` against a target. Fitness is 1.0 at or below the
+/// target and otherwise falls with the log of the distance to it. Evaluation works on
+/// fixed-size arrays and never allocates.
+pub struct TargetFitness<'a> {
+    header_prefix: &'a [u8],
+    /// Big-endian, as produced by [`target_to_bytes`]
+    target: [u8; 32],
+}
+
+impl<'a> TargetFitness<'a> {
+    pub fn new(header_prefix: &'a [u8], target: &BigUint) -> Self {
+        Self {
+            header_prefix,
+            target: target_to_bytes(target),
+        }
+    }
+}
+
+impl Fitness for TargetFitness<'_> {
+    fn evaluate(&self, genome: &[u8]) -> Evaluation {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(self.header_prefix);
+        hasher.update(genome);
+        let hash = *hasher.finalize().as_bytes();
+        // Big-endian arrays of equal length order like the numbers they hold
+        if hash <= self.target {
+            return Evaluation {
+                fitness: 1.0,
+                hash,
+                solved: true,
+            };
+        }
+        let bits = (distance_bits(&hash, &self.target) as f64).max(1.0);
+        Evaluation {
+            fitness: 1.0 / (1.0 + bits.ln()),
+            hash,
+            solved: false,
+        }
+    }
+}
+
+/// Bit length of `hash - target` for big-endian 256-bit values with `hash > target`
+fn distance_bits(hash: &[u8; 32], target: &[u8; 32]) -> u32 {
+    let limb = |bytes: &[u8; 32], i: usize| u64::from_be_bytes(bytes[i * 8..i * 8 + 8].try_into().unwrap());
+    let mut diff = [0u64; 4];
+    let mut borrow = false;
+    for i in (0..4).rev() {
+        let (d, b1) = limb(hash, i).overflowing_sub(limb(target, i));
+        let (d, b2) = d.overflowing_sub(borrow as u64);
+        diff[i] = d;
+        borrow = b1 || b2;
+    }
+    diff.iter()
+        .position(|&d| d != 0)
+        .map_or(0, |i| (4 - i as u32) * 64 - diff[i].leading_zeros())
+}
+
+/// Best of `size` parents drawn at random (`size` 2 is binary tournament).
+/// Pressure scales the tournament size.
+pub struct Tournament {
+    pub size: usize,
+}
+
+impl Selection for Tournament {
+    fn select(&self, fitness: &[f64], pressure: f64, rng: &mut dyn RngCore) -> usize {
+        let size = (self.size as f64 * pressure).round().max(1.0) as usize;
+        (0..size)
+            .map(|_| rng.gen_range(0..fitness.len()))
+            .max_by(|&a, &b| fitness[a].total_cmp(&fitness[b]))
+            .unwrap_or(0)
+    }
+}
+
+/// Fitness-proportionate (roulette wheel) selection. Pressure is an exponent on fitness.
+pub struct Roulette;
+
+impl Selection for Roulette {
+    fn select(&self, fitness: &[f64], pressure: f64, rng: &mut dyn RngCore) -> usize {
+        let weight = |f: f64| f.max(0.0).powf(pressure);
+        let total: f64 = fitness.iter().map(|&f| weight(f)).sum();
+        if total <= 0.0 {
+            return rng.gen_range(0..fitness.len());
+        }
+        let mut ball = rng.gen_range(0.0..total);
+        for (idx, &f) in fitness.iter().enumerate() {
+            ball -= weight(f);
+            if ball < 0.0 {
+                return idx;
+            }
+        }
+        fitness.len() - 1
+    }
+}
+
+/// Head of one parent and tail of the other
+pub struct OnePoint;
+
+impl Crossover for OnePoint {
+    fn crossover(&self, a: &[u8], b: &[u8], child: &mut [u8], rng: &mut dyn RngCore) {
+        let point = rng.gen_range(0..=child.len());
+        child[..point].copy_from_slice(&a[..point]);
+        child[point..].copy_from_slice(&b[point..]);
+    }
+}
+
+/// A middle segment from the second parent, the rest from the first
+pub struct TwoPoint;
+
+impl Crossover for TwoPoint {
+    fn crossover(&self, a: &[u8], b: &[u8], child: &mut [u8], rng: &mut dyn RngCore) {
+        let (x, y) = (rng.gen_range(0..=child.len()), rng.gen_range(0..=child.len()));
+        let (start, end) = (x.min(y), x.max(y));
+        child.copy_from_slice(a);
+        child[start..end].copy_from_slice(&b[start..end]);
+    }
+}
+
+/// Each byte from either parent with equal probability
+pub struct Uniform;
+
+impl Crossover for Uniform {
+    fn crossover(&self, a: &[u8], b: &[u8], child: &mut [u8], rng: &mut dyn RngCore) {
+        for (i, byte) in child.iter_mut().enumerate() {
+            *byte = if rng.gen_bool(0.5) { a[i] } else { b[i] };
+        }
+    }
+}
+
+/// Replaces each byte with a random one with probability `rate`
+pub struct ByteMutation {
+    pub rate: f64,
+}
+
+impl Mutation for ByteMutation {
+    fn mutate(&self, genome: &mut [u8], scale: f64, rng: &mut dyn RngCore) {
+        let rate = (self.rate * scale).clamp(0.0, 1.0);
+        for byte in genome {
+            if rng.gen_bool(rate) {
+                *byte = rng.gen();
+            }
+        }
+    }
+
+    fn rate(&self) -> f64 {
+        self.rate
+    }
+}
+
+/// Flips each bit with probability `rate`
+pub struct BitFlip {
+    pub rate: f64,
+}
+
+impl Mutation for BitFlip {
+    fn mutate(&self, genome: &mut [u8], scale: f64, rng: &mut dyn RngCore) {
+        let rate = (self.rate * scale).clamp(0.0, 1.0);
+        for byte in genome {
+            for bit in 0..8 {
+                if rng.gen_bool(rate) {
+                    *byte ^= 1 << bit;
+                }
+            }
+        }
+    }
+
+    fn rate(&self) -> f64 {
+        self.rate
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SelectionKind {
+    Tournament,
+    Roulette,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum CrossoverKind {
+    OnePoint,
+    TwoPoint,
+    Uniform,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum MutationKind {
+    Byte,
+    BitFlip,
+}
+
+/// Which islands receive an island's migrants
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Topology {
+    /// The next island, wrapping around
+    Ring,
+    /// One other island picked at random each time
+    Random,
+    /// Every other island
+    Full,
+}
+
+/// Island model: the population is split evenly between islands
+#[derive(Debug, Clone)]
+pub struct IslandConfig {
+    /// 0 for one island per rayon thread, 1 for a single population.
+    /// Candidates depend on the island count, so a replay needs the same count.
+    pub islands: usize,
+    /// Generations between migrations (0 = islands never exchange individuals)
+    pub migration_interval: usize,
+    /// Best individuals sent per migration
+    pub migrants: usize,
+    pub topology: Topology,
+}
+
+impl Default for IslandConfig {
+    fn default() -> Self {
+        Self {
+            islands: 1,
+            migration_interval: 50,
+            migrants: 2,
+            topology: Topology::Ring,
+        }
+    }
+}
+
+impl IslandConfig {
+    /// Islands a population of `population` is split into
+    fn count(&self, population: usize) -> usize {
+        match self.islands {
+            0 => rayon::current_num_threads(),
+            n => n,
+        }
+        .clamp(1, population.max(2) / 2)
+    }
+}
+
+/// Built-in operators chosen on the command line
+#[derive(Debug, Clone)]
+pub struct GaConfig {
+    pub selection: SelectionKind,
+    pub tournament_size: usize,
+    pub crossover: CrossoverKind,
+    pub mutation: MutationKind,
+    /// Per byte for `Byte`, per bit for `BitFlip`
+    pub mutation_rate: f64,
+    /// Best individuals copied unchanged into the next generation
+    pub elitism: usize,
+    pub islands: IslandConfig,
+    /// Adapt mutation rate and selection pressure to population diversity
+    pub adaptive: bool,
+    /// Generations without a better best fitness before the population is reseeded
+    /// around its elites (0 = never)
+    pub stagnation: usize,
+    /// Log every adaptation step at info level instead of debug (`--ga-adaptive-trace`)
+    pub adaptive_trace: bool,
+    /// Per-generation records (`--ga-telemetry`)
+    pub telemetry: Option<Arc<TelemetrySink>>,
+}
+
+/// Mean pairwise Hamming distance (as a fraction of bits) below which a population
+/// counts as converged; random vectors sit at 0.5
+const DIVERSITY_LOW: f64 = 0.15;
+/// Diversity above which selection is tightened and mutation eased off
+const DIVERSITY_HIGH: f64 = 0.35;
+/// Fitness coefficient of variation below which a population counts as converged.
+/// Log-distance fitness is flat (a random population sits near 1e-3), so this only
+/// catches populations of near-copies.
+const FITNESS_CV_LOW: f64 = 1e-5;
+const MUTATION_SCALE: (f64, f64) = (0.25, 32.0);
+const PRESSURE: (f64, f64) = (0.5, 4.0);
+
+/// Diversity of one population
+#[derive(Debug, Clone, Copy)]
+pub struct Diversity {
+    /// Mean Hamming distance between paired individuals, as a fraction of bits
+    pub hamming: f64,
+    /// Standard deviation of fitness over its mean
+    pub fitness_cv: f64,
+}
+
+impl Diversity {
+    /// Pairs each individual of `genomes` (back to back, `mv_len` bytes each) with the
+    /// one half a population away
+    pub fn measure(genomes: &[u8], mv_len: usize, values: &[f64]) -> Self {
+        let n = values.len();
+        let bits = (genomes.len() * 8).max(1) as f64;
+        let genome = |i: usize| &genomes[i * mv_len..(i + 1) * mv_len];
+        let distance: u32 = (0..n)
+            .map(|i| {
+                let (a, b) = (genome(i), genome((i + n / 2) % n));
+                a.iter().zip(b).map(|(x, y)| (x ^ y).count_ones()).sum::<u32>()
+            })
+            .sum();
+        let mean = values.iter().sum::<f64>() / n as f64;
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n as f64;
+        Self {
+            hamming: distance as f64 / bits,
+            fitness_cv: if mean > 0.0 { variance.sqrt() / mean } else { 0.0 },
+        }
+    }
+}
+
+/// Adaptive state of one population
+#[derive(Debug, Clone)]
+struct Adaptation {
+    /// Multiplier on the configured mutation rate
+    mutation: f64,
+    /// Selection pressure passed to the selection operator
+    pressure: f64,
+    best: f64,
+    /// Generations since `best` last improved
+    stalled: usize,
+}
+
+impl Default for Adaptation {
+    fn default() -> Self {
+        Self {
+            mutation: 1.0,
+            pressure: 1.0,
+            best: 0.0,
+            stalled: 0,
+        }
+    }
+}
+
+/// One population stored flat: genomes of `mv_len` bytes back to back, plus the buffer
+/// the next generation is bred into. Everything is sized once, so evaluating and
+/// breeding a generation allocates nothing.
+struct Population {
+    mv_len: usize,
+    genomes: Vec<u8>,
+    next: Vec<u8>,
+    scores: Vec<Evaluation>,
+    values: Vec<f64>,
+    /// Index buffer for ranking; from the best fitness to the worst after `rank`
+    order: Vec<usize>,
+}
+
+impl Population {
+    fn random(size: usize, mv_len: usize, rng: &mut dyn RngCore) -> Self {
+        let mut genomes = vec![0u8; size * mv_len];
+        randomize(&mut genomes, mv_len, rng);
+        Self {
+            mv_len,
+            next: vec![0u8; genomes.len()],
+            genomes,
+            scores: vec![Evaluation::default(); size],
+            values: vec![0.0; size],
+            order: (0..size).collect(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    fn genome(&self, i: usize) -> &[u8] {
+        &self.genomes[i * self.mv_len..(i + 1) * self.mv_len]
+    }
+
+    /// Scores every genome, over rayon's threads when `parallel`. Returns the first solution.
+    fn evaluate(&mut self, fitness: &dyn Fitness, parallel: bool) -> Option<usize> {
+        if parallel {
+            self.scores
+                .par_iter_mut()
+                .zip(self.genomes.par_chunks_exact(self.mv_len))
+                .for_each(|(score, genome)| *score = fitness.evaluate(genome));
+        } else {
+            for (score, genome) in self.scores.iter_mut().zip(self.genomes.chunks_exact(self.mv_len)) {
+                *score = fitness.evaluate(genome);
+            }
+        }
+        for (value, score) in self.values.iter_mut().zip(&self.scores) {
+            *value = score.fitness;
+        }
+        self.scores.iter().position(|s| s.solved)
+    }
+
+    fn solution(&self, i: usize) -> Solution {
+        (self.genome(i).to_vec(), self.scores[i].hash)
+    }
+
+    /// Sorts `order` from the best fitness to the worst
+    fn rank(&mut self) {
+        let values = &self.values;
+        self.order.iter_mut().enumerate().for_each(|(i, slot)| *slot = i);
+        self.order.sort_unstable_by(|&a, &b| values[b].total_cmp(&values[a]));
+    }
+
+    /// Overwrites the worst individuals with `genomes` (back to back) and their scores
+    fn replace_worst(&mut self, genomes: &[u8], scores: &[Evaluation]) {
+        let fitness = &self.values;
+        self.order.iter_mut().enumerate().for_each(|(i, slot)| *slot = i);
+        self.order.sort_unstable_by(|&a, &b| fitness[a].total_cmp(&fitness[b]));
+        let m = self.mv_len;
+        for (k, &slot) in self.order.iter().enumerate().take(scores.len()) {
+            self.genomes[slot * m..(slot + 1) * m].copy_from_slice(&genomes[k * m..(k + 1) * m]);
+            self.scores[slot] = scores[k];
+            self.values[slot] = scores[k].fitness;
+        }
+    }
+
+    /// Copies the first `count` ranked individuals to the front of the next generation
+    fn keep_elites(&mut self, count: usize) {
+        let m = self.mv_len;
+        for (slot, &i) in self.order.iter().take(count).enumerate() {
+            self.next[slot * m..(slot + 1) * m].copy_from_slice(&self.genomes[i * m..(i + 1) * m]);
+        }
+    }
+
+    /// Makes the bred generation current
+    fn advance(&mut self) {
+        std::mem::swap(&mut self.genomes, &mut self.next);
+    }
+}
+
+/// Migrants waiting for an island: genomes back to back with their scores. The buffers
+/// are sized up front; migrants arriving at a full inbox are dropped.
+struct Inbox {
+    capacity: usize,
+    genomes: Vec<u8>,
+    scores: Vec<Evaluation>,
+}
+
+impl Inbox {
+    fn new(capacity: usize, mv_len: usize) -> Self {
+        Self {
+            capacity,
+            genomes: Vec::with_capacity(capacity * mv_len),
+            scores: Vec::with_capacity(capacity),
+        }
+    }
+
+    fn push(&mut self, genome: &[u8], score: Evaluation) {
+        if self.scores.len() < self.capacity {
+            self.genomes.extend_from_slice(genome);
+            self.scores.push(score);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.genomes.clear();
+        self.scores.clear();
+    }
+}
+
+type Mailbox = Mutex<Inbox>;
+
+/// Generational GA built from pluggable operators
+pub struct GeneticSearch {
+    selection: Box<dyn Selection>,
+    crossover: Box<dyn Crossover>,
+    mutation: Box<dyn Mutation>,
+    elitism: usize,
+    islands: IslandConfig,
+    adaptive: bool,
+    stagnation: usize,
+    /// Level of the adaptation trace
+    trace: Level,
+    telemetry: Option<Arc<dyn Telemetry>>,
+    /// Log progress while running
+    progress: bool,
+}
+
+impl GeneticSearch {
+    pub fn new(config: &GaConfig) -> Self {
+        let selection: Box<dyn Selection> = match config.selection {
+            SelectionKind::Tournament => Box::new(Tournament {
+                size: config.tournament_size,
+            }),
+            SelectionKind::Roulette => Box::new(Roulette),
+        };
+        let crossover: Box<dyn Crossover> = match config.crossover {
+            CrossoverKind::OnePoint => Box::new(OnePoint),
+            CrossoverKind::TwoPoint => Box::new(TwoPoint),
+            CrossoverKind::Uniform => Box::new(Uniform),
+        };
+        let rate = config.mutation_rate.clamp(0.0, 1.0);
+        let mutation: Box<dyn Mutation> = match config.mutation {
+            MutationKind::Byte => Box::new(ByteMutation { rate }),
+            MutationKind::BitFlip => Box::new(BitFlip { rate }),
+        };
+        Self::with_operators(selection, crossover, mutation, config.elitism)
+            .with_islands(config.islands.clone())
+            .with_adaptation(config.adaptive, config.stagnation)
+            .with_adaptive_trace(config.adaptive_trace)
+            .with_telemetry(config.telemetry.clone().map(|sink| sink as Arc<dyn Telemetry>))
+    }
+
+    pub fn with_operators(
+        selection: Box<dyn Selection>,
+        crossover: Box<dyn Crossover>,
+        mutation: Box<dyn Mutation>,
+        elitism: usize,
+    ) -> Self {
+        Self {
+            selection,
+            crossover,
+            mutation,
+            elitism,
+            islands: IslandConfig::default(),
+            adaptive: false,
+            stagnation: 0,
+            trace: Level::Debug,
+            telemetry: None,
+            progress: true,
+        }
+    }
+
+    pub fn with_islands(mut self, islands: IslandConfig) -> Self {
+        self.islands = islands;
+        self
+    }
+
+    pub fn with_adaptation(mut self, adaptive: bool, stagnation: usize) -> Self {
+        self.adaptive = adaptive;
+        self.stagnation = stagnation;
+        self
+    }
+
+    /// Logs every adaptation step at info level rather than debug
+    pub fn with_adaptive_trace(mut self, info: bool) -> Self {
+        self.trace = if info { Level::Info } else { Level::Debug };
+        self
+    }
+
+    pub fn with_telemetry(mut self, telemetry: Option<Arc<dyn Telemetry>>) -> Self {
+        self.telemetry = telemetry;
+        self
+    }
+
+    /// No progress logging, for callers that run many searches
+    pub fn quiet(mut self) -> Self {
+        self.progress = false;
+        self
+    }
+
+    /// Evolves a random population of `population` vectors of `mv_len` bytes for up to
+    /// `generations` generations. Returns the first solution with the generation it was found in;
+    /// between islands, the earliest generation and then the lowest island wins.
+    /// Apart from telemetry and progress logging, the generation loop does not allocate.
+    pub fn run(
+        &self,
+        fitness: &dyn Fitness,
+        population: usize,
+        mv_len: usize,
+        generations: usize,
+        rng: &mut (dyn RngCore + Send),
+    ) -> Option<(Solution, usize)> {
+        let population = population.max(2);
+        let mv_len = mv_len.max(1);
+        let islands = self.islands.count(population);
+        if islands == 1 {
+            // Run from a pool thread: parallel evaluation then splits work on that worker's
+            // own deque, where a caller outside the pool would go through rayon's global
+            // injector and allocate every few dozen generations
+            rayon::scope(|_| self.run_single(fitness, population, mv_len, generations, rng))
+        } else {
+            self.run_islands(fitness, population / islands, islands, mv_len, generations, rng)
+        }
+    }
+
+    /// One population, evaluated in parallel
+    fn run_single(
+        &self,
+        fitness: &dyn Fitness,
+        population: usize,
+        mv_len: usize,
+        generations: usize,
+        rng: &mut dyn RngCore,
+    ) -> Option<(Solution, usize)> {
+        let mut pop = Population::random(population, mv_len, rng);
+        let mut state = Adaptation::default();
+        for gen in 0..generations {
+            let solved = pop.evaluate(fitness, true);
+            let diversity = self.measure(&pop);
+            self.record(gen, None, &pop, diversity, &state, ((gen + 1) * population) as u64);
+            if let Some(idx) = solved {
+                return Some((pop.solution(idx), gen));
+            }
+
+            let best = pop.values.iter().cloned().fold(0.0, f64::max);
+            self.adapt(&mut state, gen, None, &pop.values, diversity);
+            if self.progress && gen % 100 == 0 {
+                self.log_progress(gen, best, diversity, &state);
+            }
+            self.next_generation(&mut state, gen, None, &mut pop, rng);
+        }
+        None
+    }
+
+    /// `islands` populations of `size`, each evolved serially on its own rayon worker.
+    /// Migrants go through per-island mailboxes, so islands never wait for each other.
+    fn run_islands(
+        &self,
+        fitness: &dyn Fitness,
+        size: usize,
+        islands: usize,
+        mv_len: usize,
+        generations: usize,
+        rng: &mut dyn RngCore,
+    ) -> Option<(Solution, usize)> {
+        if self.progress {
+            info!(
+                "GA: {} islands of {}, {} migrants every {} generations ({:?})",
+                islands, size, self.islands.migrants, self.islands.migration_interval, self.islands.topology
+            );
+        }
+        // Islands are set up before they start, so their generation loops allocate nothing
+        let populations: Vec<(StdRng, Population)> = (0..islands)
+            .map(|_| {
+                let mut rng = StdRng::seed_from_u64(rng.next_u64());
+                let pop = Population::random(size, mv_len, &mut rng);
+                (rng, pop)
+            })
+            .collect();
+        // Room for one migration from every other island
+        let capacity = (self.islands.migrants * (islands - 1)).min(size);
+        let mailboxes: Vec<Mailbox> = (0..islands).map(|_| Mutex::new(Inbox::new(capacity, mv_len))).collect();
+        // Earliest generation any island solved in: islands stop after it, but finish it, so
+        // the winner does not depend on which thread got there first
+        let solved_at = AtomicUsize::new(usize::MAX);
+        // Bits of the best fitness seen on any island; non-negative f64s order like their bits
+        let best = AtomicU64::new(0);
+
+        populations.into_par_iter().enumerate().filter_map(|(island, (mut rng, mut pop))| {
+            let mut state = Adaptation::default();
+            for gen in 0..generations {
+                if gen > solved_at.load(Ordering::Relaxed) {
+                    return None;
+                }
+                let solved = pop.evaluate(fitness, false);
+                let diversity = self.measure(&pop);
+                let hashes = ((gen + 1) * size) as u64;
+                self.record(gen, Some(island), &pop, diversity, &state, hashes);
+                if let Some(idx) = solved {
+                    solved_at.fetch_min(gen, Ordering::Relaxed);
+                    if self.progress {
+                        info!("GA: island {} found a solution", island);
+                    }
+                    return Some((pop.solution(idx), gen, island));
+                }
+
+                {
+                    let mut inbox = mailboxes[island].lock();
+                    if !inbox.scores.is_empty() {
+                        // Migrants replace the worst individuals
+                        pop.replace_worst(&inbox.genomes, &inbox.scores);
+                        inbox.clear();
+                    }
+                }
+
+                let local_best = pop.values.iter().cloned().fold(0.0, f64::max);
+                best.fetch_max(local_best.to_bits(), Ordering::Relaxed);
+                self.adapt(&mut state, gen, Some(island), &pop.values, diversity);
+                if self.progress && island == 0 && gen % 100 == 0 {
+                    // Global best, with island 0's adaptive state
+                    self.log_progress(gen, f64::from_bits(best.load(Ordering::Relaxed)), diversity, &state);
+                }
+
+                let interval = self.islands.migration_interval;
+                if interval > 0 && gen > 0 && gen % interval == 0 {
+                    self.migrate(island, &mut pop, &mailboxes, &mut rng);
+                }
+                self.next_generation(&mut state, gen, Some(island), &mut pop, &mut rng);
+            }
+            None
+        })
+        .min_by_key(|&(_, gen, island)| (gen, island))
+        .map(|(solution, gen, _)| (solution, gen))
+    }
+
+    /// Copies the island's best individuals into its neighbours' inboxes under the topology
+    fn migrate(&self, island: usize, pop: &mut Population, mailboxes: &[Mailbox], rng: &mut dyn RngCore) {
+        if self.islands.migrants == 0 {
+            return;
+        }
+        let islands = mailboxes.len();
+        pop.rank();
+        // Targets are the islands `first..first + count` places along
+        let (first, count) = match self.islands.topology {
+            Topology::Ring => (1, 1),
+            Topology::Random => (rng.gen_range(1..islands), 1),
+            Topology::Full => (1, islands - 1),
+        };
+        for offset in first..first + count {
+            let mut inbox = mailboxes[(island + offset) % islands].lock();
+            for &i in pop.order.iter().take(self.islands.migrants) {
+                inbox.push(pop.genome(i), pop.scores[i]);
+            }
+        }
+    }
+
+    /// Diversity, when adaptation or telemetry needs it
+    fn measure(&self, pop: &Population) -> Option<Diversity> {
+        (self.adaptive || self.telemetry.is_some()).then(|| Diversity::measure(&pop.genomes, pop.mv_len, &pop.values))
+    }
+
+    fn record(
+        &self,
+        gen: usize,
+        island: Option<usize>,
+        pop: &Population,
+        diversity: Option<Diversity>,
+        state: &Adaptation,
+        hashes: u64,
+    ) {
+        let (Some(telemetry), Some(diversity)) = (&self.telemetry, diversity) else {
+            return;
+        };
+        let (scores, values) = (&pop.scores, &pop.values);
+        let mut sorted = values.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let zero_bits: Vec<u32> = scores.iter().map(|s| leading_zero_bits(&s.hash)).collect();
+        telemetry.record(&GenerationRecord {
+            generation: gen,
+            island,
+            best_fitness: sorted[sorted.len() - 1],
+            mean_fitness: sorted.iter().sum::<f64>() / sorted.len() as f64,
+            median_fitness: sorted[sorted.len() / 2],
+            diversity: diversity.hamming,
+            fitness_cv: diversity.fitness_cv,
+            mutation_rate: (self.mutation.rate() * state.mutation).min(1.0),
+            selection_pressure: state.pressure,
+            best_zero_bits: zero_bits.iter().copied().max().unwrap_or(0),
+            mean_zero_bits: zero_bits.iter().sum::<u32>() as f64 / zero_bits.len() as f64,
+            hashes,
+        });
+    }
+
+    /// Tracks stagnation and, when adaptive, steers mutation and selection by diversity:
+    /// a converged population mutates more and selects less, a scattered one the reverse
+    fn adapt(&self, state: &mut Adaptation, gen: usize, island: Option<usize>, values: &[f64], diversity: Option<Diversity>) {
+        let best = values.iter().cloned().fold(0.0, f64::max);
+        if best > state.best {
+            state.best = best;
+            state.stalled = 0;
+        } else {
+            state.stalled += 1;
+        }
+        let Some(diversity) = diversity.filter(|_| self.adaptive) else {
+            return;
+        };
+        let (mutation, pressure) = (state.mutation, state.pressure);
+        if diversity.hamming < DIVERSITY_LOW || diversity.fitness_cv < FITNESS_CV_LOW {
+            state.mutation *= 1.2;
+            state.pressure *= 0.9;
+        } else if diversity.hamming > DIVERSITY_HIGH {
+            state.mutation *= 0.9;
+            state.pressure *= 1.1;
+        }
+        state.mutation = state.mutation.clamp(MUTATION_SCALE.0, MUTATION_SCALE.1);
+        state.pressure = state.pressure.clamp(PRESSURE.0, PRESSURE.1);
+        if self.progress && (state.mutation != mutation || state.pressure != pressure) {
+            log!(
+                self.trace,
+                "GA adapt gen={} island={} diversity={:.3} fitness_cv={:.5} mutation_x={:.2} pressure={:.2}",
+                gen,
+                island.map_or("-".to_string(), |i| i.to_string()),
+                diversity.hamming,
+                diversity.fitness_cv,
+                state.mutation,
+                state.pressure
+            );
+        }
+    }
+
+    fn log_progress(&self, gen: usize, best: f64, diversity: Option<Diversity>, state: &Adaptation) {
+        match diversity.filter(|_| self.adaptive) {
+            Some(d) => info!(
+                "GA gen={} best_fitness={:.6} diversity={:.3} fitness_cv={:.5} mutation_x={:.2} pressure={:.2}",
+                gen, best, d.hamming, d.fitness_cv, state.mutation, state.pressure
+            ),
+            None => info!("GA gen={} best_fitness={:.6}", gen, best),
+        }
+    }
+
+    /// Breeds the next generation, or reseeds the population around its elites once the
+    /// best fitness has stalled for `stagnation` generations
+    fn next_generation(
+        &self,
+        state: &mut Adaptation,
+        gen: usize,
+        island: Option<usize>,
+        pop: &mut Population,
+        rng: &mut dyn RngCore,
+    ) {
+        pop.rank();
+        if self.stagnation == 0 || state.stalled < self.stagnation {
+            self.breed(pop, state, rng);
+        } else {
+            let keep = self.elitism.clamp(1, pop.len() - 1);
+            if self.progress {
+                info!(
+                    "GA gen={}{}: no improvement in {} generations, restarting around {} elite(s)",
+                    gen,
+                    island.map_or(String::new(), |i| format!(" island={}", i)),
+                    state.stalled,
+                    keep
+                );
+            }
+            pop.keep_elites(keep);
+            randomize(&mut pop.next[keep * pop.mv_len..], pop.mv_len, rng);
+            *state = Adaptation {
+                best: state.best,
+                ..Adaptation::default()
+            };
+        }
+        pop.advance();
+    }
+
+    /// Fills the next generation of a ranked population: elites first, then children of
+    /// selected parents bred in place
+    fn breed(&self, pop: &mut Population, state: &Adaptation, rng: &mut dyn RngCore) {
+        // At least one child per generation, or the search would stand still
+        let elites = self.elitism.min(pop.len() - 1);
+        pop.keep_elites(elites);
+        let m = pop.mv_len;
+        for child in pop.next.chunks_exact_mut(m).skip(elites) {
+            let a = self.selection.select(&pop.values, state.pressure, rng);
+            let b = self.selection.select(&pop.values, state.pressure, rng);
+            self.crossover
+                .crossover(&pop.genomes[a * m..(a + 1) * m], &pop.genomes[b * m..(b + 1) * m], child, rng);
+            self.mutation.mutate(child, state.mutation, rng);
+        }
+    }
+}
+
+fn leading_zero_bits(hash: &[u8; 32]) -> u32 {
+    let mut bits = 0;
+    for byte in hash {
+        bits += byte.leading_zeros();
+        if *byte != 0 {
+            break;
+        }
+    }
+    bits
+}
+
+/// Fills each `mv_len`-byte genome of a flat buffer with random bytes
+fn randomize(genomes: &mut [u8], mv_len: usize, rng: &mut dyn RngCore) {
+    for genome in genomes.chunks_exact_mut(mv_len) {
+        rng.fill_bytes(genome);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alloc_count;
+
+    fn config(islands: usize, elitism: usize, adaptive: bool, stagnation: usize) -> GaConfig {
+        GaConfig {
+            selection: SelectionKind::Tournament,
+            tournament_size: 2,
+            crossover: CrossoverKind::TwoPoint,
+            mutation: MutationKind::Byte,
+            mutation_rate: 0.05,
+            elitism,
+            islands: IslandConfig {
+                islands,
+                migration_interval: 10,
+                ..IslandConfig::default()
+            },
+            adaptive,
+            stagnation,
+            adaptive_trace: false,
+            telemetry: None,
+        }
+    }
+
+    #[test]
+    fn crossover_keeps_length_and_takes_each_byte_from_a_parent() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let (a, b) = ([0x00u8; 16], [0xffu8; 16]);
+        let operators: [&dyn Crossover; 3] = [&OnePoint, &TwoPoint, &Uniform];
+        for crossover in operators {
+            for _ in 0..100 {
+                let mut child = [0x55u8; 16];
+                crossover.crossover(&a, &b, &mut child, &mut rng);
+                assert!(child.iter().all(|&byte| byte == 0x00 || byte == 0xff), "{:?}", child);
+            }
+        }
+        // One point: a head from the first parent, a tail from the second
+        for _ in 0..100 {
+            let mut child = [0x55u8; 16];
+            OnePoint.crossover(&a, &b, &mut child, &mut rng);
+            let point = child.iter().position(|&byte| byte == 0xff).unwrap_or(16);
+            assert!(child[point..].iter().all(|&byte| byte == 0xff));
+        }
+    }
+
+    #[test]
+    fn mutation_follows_its_rate() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let original: Vec<u8> = (0..64).collect();
+        let operators: [&dyn Mutation; 2] = [&ByteMutation { rate: 0.0 }, &BitFlip { rate: 0.0 }];
+        for mutation in operators {
+            for scale in [1.0, 32.0] {
+                let mut genome = original.clone();
+                mutation.mutate(&mut genome, scale, &mut rng);
+                assert_eq!(genome, original);
+            }
+        }
+        let mut genome = original.clone();
+        BitFlip { rate: 1.0 }.mutate(&mut genome, 1.0, &mut rng);
+        assert!(genome.iter().zip(&original).all(|(x, y)| *x == !*y));
+        // The scale is clamped to a probability
+        let mut genome = original.clone();
+        BitFlip { rate: 0.5 }.mutate(&mut genome, 4.0, &mut rng);
+        assert!(genome.iter().zip(&original).all(|(x, y)| *x == !*y));
+    }
+
+    #[test]
+    fn selection_favours_fitter_parents() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let fitness = [0.1, 0.2, 0.9, 0.3];
+        let picks = |selection: &dyn Selection, pressure: f64, rng: &mut StdRng| {
+            let mut counts = [0usize; 4];
+            for _ in 0..1000 {
+                counts[selection.select(&fitness, pressure, rng)] += 1;
+            }
+            counts
+        };
+        let tournament = Tournament { size: 2 };
+        let loose = picks(&tournament, 0.5, &mut rng);
+        let tight = picks(&tournament, 4.0, &mut rng);
+        assert!(loose.iter().all(|&n| n > 0), "{:?}", loose);
+        assert!(tight[2] > loose[2] && tight[2] > 900, "{:?} {:?}", loose, tight);
+
+        let roulette = picks(&Roulette, 1.0, &mut rng);
+        assert!(roulette[2] > roulette[3] && roulette[3] > roulette[0], "{:?}", roulette);
+        // Only one parent has any weight
+        let single = [0.0, 0.0, 0.5, 0.0];
+        assert!((0..100).all(|_| Roulette.select(&single, 2.0, &mut rng) == 2));
+        // No weight at all: uniform
+        assert!((0..100).all(|_| Roulette.select(&[0.0; 4], 1.0, &mut rng) < 4));
+    }
+
+    #[test]
+    fn target_fitness_rises_towards_the_target() {
+        let header = [0x77u8; 32];
+        let easy = TargetFitness::new(&header, &(BigUint::from(1u32) << 256));
+        let solved = easy.evaluate(&[1, 2, 3, 4]);
+        assert!(solved.solved);
+        assert_eq!(solved.fitness, 1.0);
+
+        let hard = TargetFitness::new(&header, &BigUint::from(0u32));
+        let miss = hard.evaluate(&[1, 2, 3, 4]);
+        assert!(!miss.solved);
+        assert!(miss.fitness > 0.0 && miss.fitness < 1.0);
+        assert_eq!(miss.hash, solved.hash);
+        // A target just below the hash scores far better than a target of zero
+        let close = BigUint::from_bytes_be(&miss.hash) - 1u32;
+        let near = TargetFitness::new(&header, &close).evaluate(&[1, 2, 3, 4]);
+        assert!(!near.solved);
+        assert!(near.fitness > miss.fitness);
+        assert_eq!(distance_bits(&[0xff; 32], &[0xff; 32]), 0);
+        assert_eq!(distance_bits(&[0xff; 32], &[0; 32]), 256);
+    }
+
+    #[test]
+    fn migrants_reach_their_neighbours_and_replace_the_worst() {
+        let header = [0x3c; 64];
+        let fitness = TargetFitness::new(&header, &BigUint::from(0u32));
+        let mut rng = StdRng::seed_from_u64(4);
+        let mut config = config(3, 0, false, 0);
+        config.islands.migrants = 2;
+        let mailboxes: Vec<Mailbox> = (0..3).map(|_| Mutex::new(Inbox::new(4, 8))).collect();
+
+        let mut sender = Population::random(16, 8, &mut rng);
+        sender.evaluate(&fitness, false);
+        GeneticSearch::new(&config).quiet().migrate(0, &mut sender, &mailboxes, &mut rng);
+        let best: Vec<usize> = sender.order[..2].to_vec();
+        {
+            let inbox = mailboxes[1].lock();
+            assert_eq!(inbox.scores.len(), 2);
+            for (k, &i) in best.iter().enumerate() {
+                assert_eq!(&inbox.genomes[k * 8..(k + 1) * 8], sender.genome(i));
+                assert_eq!(inbox.scores[k].hash, sender.scores[i].hash);
+            }
+        }
+        assert!(mailboxes[0].lock().scores.is_empty() && mailboxes[2].lock().scores.is_empty());
+
+        // The full topology reaches every other island
+        config.islands.topology = Topology::Full;
+        GeneticSearch::new(&config).quiet().migrate(1, &mut sender, &mailboxes, &mut rng);
+        assert_eq!(mailboxes.iter().map(|m| m.lock().scores.len()).collect::<Vec<_>>(), [2, 2, 2]);
+
+        let mut receiver = Population::random(16, 8, &mut rng);
+        receiver.evaluate(&fitness, false);
+        let (before, values) = (receiver.genomes.clone(), receiver.values.clone());
+        let inbox = mailboxes[2].lock();
+        receiver.replace_worst(&inbox.genomes, &inbox.scores);
+        let (replaced, kept): (Vec<usize>, Vec<usize>) =
+            (0..16).partition(|&i| receiver.genome(i) != &before[i * 8..(i + 1) * 8]);
+        assert_eq!(replaced.len(), 2);
+        for &i in &replaced {
+            assert!(kept.iter().all(|&j| values[i] <= values[j]));
+        }
+        for (k, score) in inbox.scores.iter().enumerate() {
+            let slot = (0..16).find(|&i| receiver.genome(i) == &inbox.genomes[k * 8..(k + 1) * 8]).unwrap();
+            assert_eq!(receiver.scores[slot].hash, score.hash);
+            assert_eq!(receiver.values[slot], score.fitness);
+        }
+        // Scores and values agree, so telemetry and solutions see the migrants' hashes
+        for i in 0..16 {
+            assert_eq!(receiver.values[i], receiver.scores[i].fitness);
+            assert_eq!(fitness.evaluate(receiver.genome(i)).hash, receiver.scores[i].hash);
+        }
+    }
+
+    #[test]
+    fn adaptation_steers_by_diversity_and_restarts_on_stagnation() {
+        let search = GeneticSearch::new(&config(1, 1, true, 3)).quiet();
+        let diversity = |hamming, fitness_cv| Some(Diversity { hamming, fitness_cv });
+        let values = [0.2, 0.1];
+
+        // Converged, by distance or by fitness spread: mutate more, select less
+        for converged in [diversity(0.05, 0.1), diversity(0.25, 0.0)] {
+            let mut state = Adaptation::default();
+            search.adapt(&mut state, 0, None, &values, converged);
+            assert!(state.mutation > 1.0 && state.pressure < 1.0, "{:?}", state);
+        }
+        // Scattered: the reverse
+        let mut state = Adaptation::default();
+        search.adapt(&mut state, 0, None, &values, diversity(0.5, 0.1));
+        assert!(state.mutation < 1.0 && state.pressure > 1.0, "{:?}", state);
+        // In between: left alone
+        let mut state = Adaptation::default();
+        search.adapt(&mut state, 0, None, &values, diversity(0.25, 0.1));
+        assert_eq!((state.mutation, state.pressure), (1.0, 1.0));
+
+        // Held within bounds
+        let mut state = Adaptation::default();
+        for gen in 0..200 {
+            search.adapt(&mut state, gen, None, &values, diversity(0.0, 0.0));
+        }
+        assert_eq!((state.mutation, state.pressure), (MUTATION_SCALE.1, PRESSURE.0));
+        for gen in 0..200 {
+            search.adapt(&mut state, gen, None, &values, diversity(0.5, 0.1));
+        }
+        assert_eq!((state.mutation, state.pressure), (MUTATION_SCALE.0, PRESSURE.1));
+
+        // Without --ga-adaptive only stagnation is tracked
+        let fixed = GeneticSearch::new(&config(1, 1, false, 3)).quiet();
+        let mut state = Adaptation::default();
+        fixed.adapt(&mut state, 0, None, &values, diversity(0.0, 0.0));
+        assert_eq!((state.mutation, state.pressure, state.best, state.stalled), (1.0, 1.0, 0.2, 0));
+        fixed.adapt(&mut state, 1, None, &values, diversity(0.0, 0.0));
+        assert_eq!(state.stalled, 1);
+        fixed.adapt(&mut state, 2, None, &[0.3], diversity(0.0, 0.0));
+        assert_eq!((state.best, state.stalled), (0.3, 0));
+
+        // A population stalled for `stagnation` generations restarts around its elite
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut pop = Population::random(8, 4, &mut rng);
+        pop.values.iter_mut().enumerate().for_each(|(i, v)| *v = i as f64 / 10.0);
+        let elite = pop.genome(7).to_vec();
+        let mut state = Adaptation {
+            mutation: 8.0,
+            pressure: 0.5,
+            best: 0.7,
+            stalled: 3,
+        };
+        search.next_generation(&mut state, 10, None, &mut pop, &mut rng);
+        assert_eq!(pop.genome(0), elite.as_slice());
+        assert_eq!((state.mutation, state.pressure, state.best, state.stalled), (1.0, 1.0, 0.7, 0));
+    }
+
+    #[test]
+    fn seeded_runs_repeat_on_any_pool_size() {
+        let header = [0x19; 48];
+        // About 2^10 hashes to a solution
+        let fitness = TargetFitness::new(&header, &((BigUint::from(1u32) << 246) - 1u32));
+        let run = |islands: usize, migration_interval: usize, threads: usize| {
+            let mut config = config(islands, 1, true, 20);
+            config.islands.migration_interval = migration_interval;
+            let search = GeneticSearch::new(&config).quiet();
+            let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+            let mut rng = crate::seed::rng(1234, &[]);
+            pool.install(|| search.run(&fitness, 64, 16, 500, &mut rng)).expect("solvable target")
+        };
+        let single = run(1, 10, 1);
+        assert_eq!(run(1, 10, 4), single);
+        assert_eq!(run(1, 10, 3), single);
+        let islands = run(4, 0, 1);
+        assert_eq!(run(4, 0, 4), islands);
+        assert_eq!(run(4, 0, 2), islands);
+    }
+
+    /// Starts counting a thread's allocations at its first evaluation, so that only the
+    /// generation loop is measured
+    struct Tracking<'a>(TargetFitness<'a>);
+
+    impl Fitness for Tracking<'_> {
+        fn evaluate(&self, genome: &[u8]) -> Evaluation {
+            alloc_count::track();
+            self.0.evaluate(genome)
+        }
+    }
+
+    /// Allocations of an unsolvable run from its first generation on
+    fn run_allocations(pool: &rayon::ThreadPool, search: &GeneticSearch, generations: usize) -> u64 {
+        let header = [0x5a; 64];
+        let fitness = Tracking(TargetFitness::new(&header, &BigUint::from(1u32)));
+        let mut rng = StdRng::seed_from_u64(42);
+        let (solution, allocations) = alloc_count::measure(|| {
+            pool.broadcast(|_| alloc_count::untrack());
+            let solution = pool.install(|| search.run(&fitness, 64, 16, generations, &mut rng));
+            pool.broadcast(|_| alloc_count::untrack());
+            solution
+        });
+        assert!(solution.is_none());
+        allocations
+    }
+
+    #[test]
+    fn generation_loop_does_not_allocate() {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(2).build().unwrap();
+        for (islands, elitism, adaptive, stagnation) in [(1, 0, false, 0), (1, 2, true, 20), (4, 1, true, 20)] {
+            // Telemetry and progress logging allocate; both are off
+            let config = config(islands, elitism, adaptive, stagnation);
+            assert!(config.telemetry.is_none());
+            let search = GeneticSearch::new(&config).quiet();
+            // Warm-up, so the pool's threads have started and their deques have grown
+            run_allocations(&pool, &search, 50);
+            for generations in [1, 100, 1000] {
+                let allocations = run_allocations(&pool, &search, generations);
+                assert_eq!(
+                    allocations, 0,
+                    "{} generation(s) on {} island(s), elitism {}, adaptive {}, stagnation {}",
+                    generations, islands, elitism, adaptive, stagnation
+                );
+            }
+        }
+    }
+}
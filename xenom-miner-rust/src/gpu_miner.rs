@@ -1,9 +1,48 @@
 #[cfg(feature = "cuda")]
 use cudarc::driver::{CudaDevice, CudaSlice, LaunchAsync, LaunchConfig};
+#[cfg(feature = "cuda")]
+use log::{debug, error, warn};
+use log::info;
+use crate::control::MinerControl;
+use crate::ga::{GaConfig, GeneticSearch, TargetFitness};
+use crate::throttle::Pacer;
+use crate::watchdog::Heartbeat;
 use num_bigint::BigUint;
-use rand::Rng;
 #[cfg(feature = "cuda")]
+use rand::Rng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::Instant;
+
+/// Iterations per thread of one `blake3_brute_force` launch (16M nonces at the default geometry),
+/// so long ranges beat the heartbeat and honor pause/refresh between launches
+#[cfg(feature = "cuda")]
+const BRUTE_FORCE_LAUNCH_ITERATIONS: u32 = 64;
+
+/// Launch geometry of the `blake3_brute_force` kernel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Launch {
+    pub threads_per_block: u32,
+    pub blocks: u32,
+}
+
+impl Default for Launch {
+    fn default() -> Self {
+        Self {
+            threads_per_block: 256,
+            blocks: 1024,
+        }
+    }
+}
+
+impl Launch {
+    /// Nonces hashed per kernel iteration
+    pub fn threads(&self) -> u64 {
+        self.threads_per_block as u64 * self.blocks as u64
+    }
+}
 
 #[allow(dead_code)]
 pub struct GpuMiner {
@@ -11,15 +50,110 @@ pub struct GpuMiner {
     device: Arc<CudaDevice>,
     population_size: usize,
     mv_len: usize,
+    device_id: usize,
+    launch: Launch,
     #[cfg(feature = "cuda")]
     has_kernels: bool,
+    /// Reports hashes (per GA generation or brute-force launch) to the worker watchdog
+    heartbeat: Option<Arc<Heartbeat>>,
+    /// Enforces the hashrate and duty-cycle limits at the same points
+    pacer: Option<Arc<Pacer>>,
+    /// Run seed; each round gets its own stream
+    seed: u64,
+    rounds: AtomicU64,
+    /// Hashes computed since the miner was created, as reported through `report`
+    hashes: AtomicU64,
+}
+
+impl GpuMiner {
+    /// Changes the GA population used by the next `mine_*` call
+    pub fn set_population_size(&mut self, population_size: usize) {
+        self.population_size = population_size.max(1);
+    }
+
+    pub fn device_id(&self) -> usize {
+        self.device_id
+    }
+
+    /// Changes the brute-force launch geometry used by the next `mine_*` call
+    pub fn set_launch(&mut self, launch: Launch) {
+        self.launch = launch;
+    }
+
+    pub fn launch(&self) -> Launch {
+        self.launch
+    }
+
+    /// Hashes per second computed by `run`, counted through a private heartbeat.
+    /// `None` if `run` reported no hashes (for instance because a launch failed).
+    pub fn measure(&mut self, run: impl FnOnce(&Self)) -> Option<f64> {
+        let heartbeat = Arc::new(Heartbeat::default());
+        let previous = self.heartbeat.replace(Arc::clone(&heartbeat));
+        let pacer = self.pacer.take();
+        let start = Instant::now();
+        run(self);
+        let elapsed = start.elapsed().as_secs_f64();
+        self.heartbeat = previous;
+        self.pacer = pacer;
+        (heartbeat.hashes() > 0).then(|| heartbeat.hashes() as f64 / elapsed)
+    }
+
+    pub fn set_heartbeat(&mut self, heartbeat: Arc<Heartbeat>) {
+        self.heartbeat = Some(heartbeat);
+    }
+
+    pub fn set_pacer(&mut self, pacer: Arc<Pacer>) {
+        self.pacer = Some(pacer);
+    }
+
+    /// Reports hashes to the watchdog, then sleeps while ahead of the limits.
+    /// 0 hashes marks the start of a round.
+    #[cfg(feature = "cuda")]
+    fn report(&self, hashes: u64) {
+        self.hashes.fetch_add(hashes, Ordering::Relaxed);
+        if let Some(heartbeat) = &self.heartbeat {
+            heartbeat.beat(hashes);
+        }
+        if let Some(pacer) = &self.pacer {
+            pacer.pace(hashes, self.heartbeat.as_deref(), &|| false);
+        }
+    }
+
+    /// Hashes computed by the `mine_*` calls so far; the difference over a call is its work
+    pub fn hashes(&self) -> u64 {
+        self.hashes.load(Ordering::Relaxed)
+    }
+
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+
+    /// RNG for the next `mine_*` call, distinct per device and round
+    #[cfg(feature = "cuda")]
+    fn next_rng(&self) -> rand::rngs::StdRng {
+        let round = self.rounds.fetch_add(1, Ordering::Relaxed);
+        crate::seed::rng(self.seed, &[self.device_id as u64, round])
+    }
+
+    /// Whether a round should stop: `found` is set, or a template refresh or shutdown
+    /// was requested. Holds the round while mining is paused.
+    #[cfg(feature = "cuda")]
+    fn interrupted(found: &AtomicBool, control: Option<&MinerControl>) -> bool {
+        if let Some(control) = control {
+            control.wait_while_paused(found);
+            if control.refresh_requested() || control.is_shutting_down() {
+                return true;
+            }
+        }
+        found.load(Ordering::Relaxed)
+    }
 }
 
 #[cfg(feature = "cuda")]
 impl GpuMiner {
     pub fn new(population_size: usize, mv_len: usize, device_id: usize) -> Result<Self, Box<dyn std::error::Error>> {
         // Initialize CUDA device
-        eprintln!("🎮 Initializing GPU {}", device_id);
+        info!("🎮 Initializing GPU {}", device_id);
         let device = CudaDevice::new(device_id)?;
 
         // Try to load compiled PTX - check multiple locations
@@ -28,7 +162,7 @@ impl GpuMiner {
         
         // 1. Try environment variable (set during build)
         if let Ok(ptx_path) = std::env::var("CUDA_BLAKE3_PTX") {
-            eprintln!("📦 Trying CUDA_BLAKE3_PTX: {}", ptx_path);
+            debug!("📦 Trying CUDA_BLAKE3_PTX: {}", ptx_path);
             if let Ok(content) = std::fs::read_to_string(&ptx_path) {
                 ptx_content = Some(content);
             }
@@ -57,7 +191,7 @@ impl GpuMiner {
             ];
             for path in paths {
                 if let Ok(content) = std::fs::read_to_string(path) {
-                    eprintln!("📦 Found PTX at: {}", path);
+                    debug!("📦 Found PTX at: {}", path);
                     ptx_content = Some(content);
                     break;
                 }
@@ -80,87 +214,113 @@ impl GpuMiner {
                     let has_brute_force = device.get_func(module_name, "blake3_brute_force").is_some();
                     
                     if has_kernels {
-                        eprintln!("✅ CUDA kernels loaded successfully");
+                        info!("✅ CUDA kernels loaded successfully");
                         if has_brute_force {
-                            eprintln!("✅ Brute-force kernel also available");
+                            info!("✅ Brute-force kernel also available");
                         }
                     } else {
-                        eprintln!("❌ PTX loaded but required kernels not found in module");
+                        error!("❌ PTX loaded but required kernels not found in module");
                     }
                 }
                 Err(e) => {
-                    eprintln!("❌ Failed to load PTX module: {}", e);
+                    error!("❌ Failed to load PTX module: {}", e);
                 }
             }
         } else {
-            eprintln!("❌ PTX file not found. Tried:");
-            eprintln!("   - CUDA_BLAKE3_PTX env var");
-            eprintln!("   - ./blake3_simple.ptx");
-            eprintln!("   - ./src/blake3_simple.ptx");
-            eprintln!("   Compile the CUDA kernel: nvcc --ptx src/blake3_simple.cu -o blake3_simple.ptx -arch=sm_60 --use_fast_math -O3");
+            error!("❌ PTX file not found. Tried:");
+            error!("   - CUDA_BLAKE3_PTX env var");
+            error!("   - ./blake3_simple.ptx");
+            error!("   - ./src/blake3_simple.ptx");
+            error!("   Compile the CUDA kernel: nvcc --ptx src/blake3_simple.cu -o blake3_simple.ptx -arch=sm_60 --use_fast_math -O3");
         }
 
         Ok(Self {
             device,
             population_size,
             mv_len,
+            device_id,
+            launch: Launch::default(),
             has_kernels,
+            heartbeat: None,
+            pacer: None,
+            seed: 0,
+            rounds: AtomicU64::new(0),
+            hashes: AtomicU64::new(0),
         })
     }
 
+    /// PCI bus number of the device, used to match HiveOS GPU stats
+    pub fn bus_number(&self) -> Option<u32> {
+        use cudarc::driver::sys::CUdevice_attribute;
+        self.device
+            .attribute(CUdevice_attribute::CU_DEVICE_ATTRIBUTE_PCI_BUS_ID)
+            .ok()
+            .map(|bus| bus as u32)
+    }
+
+    fn attribute(&self, attribute: cudarc::driver::sys::CUdevice_attribute) -> Option<u32> {
+        self.device.attribute(attribute).ok().map(|value| value as u32)
+    }
+
+    /// Model, compute capability and multiprocessor count, for the auto-tune fingerprint
+    pub fn hardware(&self) -> String {
+        use cudarc::driver::sys::CUdevice_attribute::*;
+        format!(
+            "{} (sm_{}{}, {} SMs)",
+            self.device.name().unwrap_or_else(|_| "unknown GPU".to_string()),
+            self.attribute(CU_DEVICE_ATTRIBUTE_COMPUTE_CAPABILITY_MAJOR).unwrap_or(0),
+            self.attribute(CU_DEVICE_ATTRIBUTE_COMPUTE_CAPABILITY_MINOR).unwrap_or(0),
+            self.attribute(CU_DEVICE_ATTRIBUTE_MULTIPROCESSOR_COUNT).unwrap_or(0)
+        )
+    }
+
+    /// Maximum threads per block and multiprocessor count
+    pub fn limits(&self) -> (u32, u32) {
+        use cudarc::driver::sys::CUdevice_attribute::*;
+        (
+            self.attribute(CU_DEVICE_ATTRIBUTE_MAX_THREADS_PER_BLOCK).unwrap_or(1024),
+            self.attribute(CU_DEVICE_ATTRIBUTE_MULTIPROCESSOR_COUNT).unwrap_or(1).max(1),
+        )
+    }
+
     pub fn mine_with_ga(
         &self,
         header_prefix: &[u8],
         target: &BigUint,
         generations: usize,
         mutation_rate: f32,
+        found: &AtomicBool,
+        control: Option<&MinerControl>,
     ) -> Option<(Vec<u8>, [u8; 32])> {
         if !self.has_kernels {
-            eprintln!("⚠️  CUDA kernels not loaded (missing PTX). Falling back to None.");
+            warn!("⚠️  CUDA kernels not loaded (missing PTX). Falling back to None.");
             return None;
         }
+        self.report(0);
 
         let module = "blake3_simple_kernels";
 
         // Prepare buffers
         let pop = self.population_size as u32;
         let mv_len_u32 = self.mv_len as u32;
-        let header_len_u32 = header_prefix.len() as u32;
 
-        // Device buffers
-        let d_header: CudaSlice<u8> = self.device.htod_copy(header_prefix.to_vec()).ok()?;
-        let mut rng = rand::thread_rng();
+        // Device buffers; hashing and fitness run on the CPU, only the GA operators on the GPU
+        let mut rng = self.next_rng();
         let population_bytes = self.population_size * self.mv_len;
         let mut h_population: Vec<u8> = vec![0u8; population_bytes];
         rng.fill(&mut h_population[..]);
         let mut d_population = self.device.htod_copy(h_population).ok()?;
         let mut d_population_next: CudaSlice<u8> =
             self.device.alloc_zeros(population_bytes).ok()?;
-        let mut d_hashes: CudaSlice<u8> =
-            self.device.alloc_zeros(self.population_size * 32).ok()?;
-        let mut d_fitness: CudaSlice<f32> = self.device.alloc_zeros(self.population_size).ok()?;
 
         // Random seeds
         let h_seeds: Vec<u32> = (0..self.population_size).map(|_| rng.gen()).collect();
         let mut d_seeds = self.device.htod_copy(h_seeds).ok()?;
 
-        // Target bytes (big-endian 32 bytes)
-        let mut target_bytes = target.to_bytes_be();
-        if target_bytes.len() < 32 {
-            let mut pad = vec![0u8; 32 - target_bytes.len()];
-            pad.extend_from_slice(&target_bytes);
-            target_bytes = pad;
-        } else if target_bytes.len() > 32 {
-            // Truncate to 32 (keep least significant 32 bytes)
-            let start = target_bytes.len() - 32;
-            target_bytes = target_bytes[start..].to_vec();
-        }
-        let d_target: CudaSlice<u8> = self.device.htod_copy(target_bytes).ok()?;
-
         // Optimized launch configuration for maximum GPU utilization
         // Use larger blocks (256 threads) for better occupancy
         let threads_per_block = 256u32;
-        let num_blocks = ((self.population_size as u32) + threads_per_block - 1) / threads_per_block;
+        let num_blocks = (self.population_size as u32).div_ceil(threads_per_block);
         let cfg = LaunchConfig {
             grid_dim: (num_blocks, 1, 1),
             block_dim: (threads_per_block, 1, 1),
@@ -171,6 +331,9 @@ impl GpuMiner {
         let mut h_fitness = vec![0f32; self.population_size];
 
         for gen in 0..generations {
+            if Self::interrupted(found, control) {
+                return None;
+            }
             // CPU hashing and fitness: ensure correctness for full header length
             let mut h_population_now = vec![0u8; self.population_size * self.mv_len];
             self.device
@@ -202,6 +365,7 @@ impl GpuMiner {
             }
 
             if let Some(idx) = found_idx {
+                self.report(idx as u64 + 1);
                 let mv = h_population_now[idx * self.mv_len..(idx + 1) * self.mv_len].to_vec();
                 let mut candidate = header_prefix.to_vec();
                 candidate.extend_from_slice(&mv);
@@ -212,14 +376,11 @@ impl GpuMiner {
             }
 
             // Copy fitness to device
-            d_fitness = self.device.htod_copy(h_fitness.clone()).ok()?;
+            let d_fitness = self.device.htod_copy(h_fitness.clone()).ok()?;
 
             // GA operators -> produce next generation on GPU
             unsafe {
-                let func_ga = match self.device.get_func(module, "genetic_operators") {
-                    Some(f) => f,
-                    None => return None,
-                };
+                let func_ga = self.device.get_func(module, "genetic_operators")?;
                 func_ga
                     .launch(
                         cfg,
@@ -239,11 +400,12 @@ impl GpuMiner {
             if gen % 50 == 0 {
                 // Simple progress: best fitness
                 let best = h_fitness.iter().cloned().fold(0.0f32, f32::max);
-                println!("GPU Hybrid gen={} best_fitness={:.6}", gen, best);
+                info!("GPU Hybrid gen={} best_fitness={:.6}", gen, best);
             }
 
             // Swap populations
             std::mem::swap(&mut d_population, &mut d_population_next);
+            self.report(self.population_size as u64);
         }
 
         None
@@ -258,7 +420,7 @@ impl GpuMiner {
         batches: usize,
     ) -> Option<(Vec<u8>, [u8; 32])> {
         if !self.has_kernels {
-            eprintln!("❌ GPU mining unavailable: CUDA kernels not loaded");
+            error!("❌ GPU mining unavailable: CUDA kernels not loaded");
             return None;
         }
 
@@ -271,7 +433,7 @@ impl GpuMiner {
         
         // Optimized launch configuration for brute-force
         let threads_per_block = 256u32;
-        let num_blocks = (pop_u32 + threads_per_block - 1) / threads_per_block;
+        let num_blocks = pop_u32.div_ceil(threads_per_block);
         let cfg = LaunchConfig {
             grid_dim: (num_blocks, 1, 1),
             block_dim: (threads_per_block, 1, 1),
@@ -293,13 +455,13 @@ impl GpuMiner {
         }
         
         // Debug: Show target being used
-        eprintln!("🎯 GPU Target (first 8 bytes): {:02x}{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}",
+        debug!("🎯 GPU Target (first 8 bytes): {:02x}{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}",
             target_bytes[0], target_bytes[1], target_bytes[2], target_bytes[3],
             target_bytes[4], target_bytes[5], target_bytes[6], target_bytes[7]);
         
         let d_target: CudaSlice<u8> = self.device.htod_copy(target_bytes.clone()).ok()?;
 
-        let mut rng = rand::thread_rng();
+        let mut rng = self.next_rng();
         let mut host_pop = vec![0u8; self.population_size * self.mv_len];
         let mut host_fitness = vec![0f32; self.population_size];
 
@@ -309,12 +471,8 @@ impl GpuMiner {
             let d_population: CudaSlice<u8> = self.device.htod_copy(host_pop.clone()).ok()?;
 
             unsafe {
-                let func_hash = match self.device.get_func(module, "blake3_hash_batch") { 
-                    Some(f) => f, None => return None 
-                };
-                let func_fitness = match self.device.get_func(module, "evaluate_fitness") { 
-                    Some(f) => f, None => return None 
-                };
+                let func_hash = self.device.get_func(module, "blake3_hash_batch")?;
+                let func_fitness = self.device.get_func(module, "evaluate_fitness")?;
                 
                 // Hash on GPU
                 func_hash.launch(cfg, (
@@ -329,13 +487,14 @@ impl GpuMiner {
 
             // Get results
             self.device.dtoh_sync_copy_into(&d_fitness, &mut host_fitness).ok()?;
+            self.report(self.population_size as u64);
             
             // Skip GPU Blake3 verification entirely if requested
             if batch_idx == 0 && std::env::var("SKIP_GPU_VERIFICATION").is_err() {
                 let mut gpu_hashes = vec![0u8; self.population_size * 32];
                 self.device.dtoh_sync_copy_into(&d_hashes, &mut gpu_hashes).ok()?;
                 
-                eprintln!("🔍 Verifying GPU Blake3 (first 3):");
+                debug!("🔍 Verifying GPU Blake3 (first 3):");
                 let mut all_match = true;
                 for i in 0..3.min(self.population_size) {
                     let mv = &host_pop[i * self.mv_len..(i + 1) * self.mv_len];
@@ -349,16 +508,16 @@ impl GpuMiner {
                         all_match = false;
                         "❌" 
                     };
-                    eprintln!("  [{}] GPU: {} | CPU: {}", match_status,
+                    debug!("  [{}] GPU: {} | CPU: {}", match_status,
                         hex::encode(&gpu_hash[..8]), hex::encode(&cpu_hash.as_bytes()[..8]));
                 }
                 
                 if !all_match {
-                    eprintln!("⚠️  GPU Blake3 mismatch - falling back to CPU");
+                    warn!("⚠️  GPU Blake3 mismatch - falling back to CPU");
                     // Continue with CPU fallback on mismatch
                 }
             } else if batch_idx == 0 {
-                eprintln!("🔄 GPU Blake3 verification skipped (SKIP_GPU_VERIFICATION=1)");
+                info!("🔄 GPU Blake3 verification skipped (SKIP_GPU_VERIFICATION=1)");
             }
             
             // Check for solution - use CPU verification if GPU Blake3 is buggy
@@ -374,7 +533,7 @@ impl GpuMiner {
                     if hash_uint <= *target {
                         let mut hash = [0u8; 32];
                         hash.copy_from_slice(cpu_hash.as_bytes());
-                        eprintln!("✅ Solution found via CPU verification in batch {}/{}", batch_idx + 1, batches);
+                        info!("✅ Solution found via CPU verification in batch {}/{}", batch_idx + 1, batches);
                         return Some((mv, hash));
                     }
                 }
@@ -392,7 +551,7 @@ impl GpuMiner {
                     if hash_uint <= *target {
                         let mut hash = [0u8; 32];
                         hash.copy_from_slice(cpu_hash.as_bytes());
-                        eprintln!("✅ GPU found solution, CPU verified in batch {}/{}", batch_idx + 1, batches);
+                        info!("✅ GPU found solution, CPU verified in batch {}/{}", batch_idx + 1, batches);
                         return Some((mv, hash));
                     }
                 }
@@ -401,7 +560,7 @@ impl GpuMiner {
             // Progress with hashrate
             if batch_idx > 0 && batch_idx % 1000 == 0 {
                 let total_hashes = batch_idx * self.population_size;
-                eprintln!("  Batch {}/{}, {} hashes", batch_idx, batches, total_hashes);
+                debug!("  Batch {}/{}, {} hashes", batch_idx, batches, total_hashes);
             }
         }
 
@@ -416,9 +575,15 @@ impl GpuMiner {
         target: &BigUint,
         start_nonce: u64,
         max_nonces: u64,
+        found: &AtomicBool,
+        control: Option<&MinerControl>,
     ) -> Option<(Vec<u8>, [u8; 32])> {
         if !self.has_kernels {
-            eprintln!("❌ GPU mining unavailable: CUDA kernels not loaded");
+            error!("❌ GPU mining unavailable: CUDA kernels not loaded");
+            return None;
+        }
+        self.report(0);
+        if Self::interrupted(found, control) {
             return None;
         }
 
@@ -426,7 +591,7 @@ impl GpuMiner {
         
         // Check if brute-force kernel is available
         if self.device.get_func(module, "blake3_brute_force").is_none() {
-            eprintln!("⚠️  blake3_brute_force kernel not available, falling back to batch method");
+            warn!("⚠️  blake3_brute_force kernel not available, falling back to batch method");
             return self.mine_bruteforce_gpu(header_prefix, target, (max_nonces / self.population_size as u64) as usize);
         }
 
@@ -453,55 +618,64 @@ impl GpuMiner {
         let mut d_solution_nonce: CudaSlice<u64> = self.device.alloc_zeros(1).ok()?;
 
         let header_len_u32 = pow_header_prefix.len() as u32;
-        let threads_per_block = 256u32;
-        let num_blocks = 1024u32; // Use many blocks for better GPU utilization
-        let total_threads = threads_per_block * num_blocks;
-        let iterations_per_thread = ((max_nonces + total_threads as u64 - 1) / total_threads as u64).max(1) as u32;
+        // 256 x 1024 unless tuned (`--auto-tune`)
+        let total_threads = self.launch.threads();
+        let iterations_per_thread = max_nonces.div_ceil(total_threads).max(1);
 
         let cfg = LaunchConfig {
-            grid_dim: (num_blocks, 1, 1),
-            block_dim: (threads_per_block, 1, 1),
+            grid_dim: (self.launch.blocks, 1, 1),
+            block_dim: (self.launch.threads_per_block, 1, 1),
             shared_mem_bytes: 0,
         };
 
         // Allocate device memory for solution hash
         let mut d_solution_hash = self.device.alloc_zeros::<u8>(32).ok()?;
 
-        eprintln!("🚀 Starting GPU brute-force: {} threads, {} iterations each", 
+        info!("🚀 Starting GPU brute-force: {} threads, {} iterations each", 
                  total_threads, iterations_per_thread);
-        eprintln!("🎯 Target range: {} to {}", start_nonce, start_nonce + max_nonces);
-
-        unsafe {
-            let func_brute = match self.device.get_func(module, "blake3_brute_force") {
-                Some(f) => f,
-                None => return None,
-            };
-
-            func_brute.launch(cfg, (
-                &d_header,           // const uint8_t* header_prefix
-                header_len_u32,      // uint32_t header_len
-                start_nonce,         // uint64_t start_nonce
-                &d_target,           // const uint8_t* target_bytes
-                &mut d_solution_found, // uint8_t* solution_found
-                &mut d_solution_nonce, // uint64_t* solution_nonce
-                iterations_per_thread, // uint32_t max_iterations
-                &mut d_solution_hash,  // uint8_t* solution_hash
-            )).ok()?;
-        }
+        debug!("🎯 Target range: {} to {}", start_nonce, start_nonce + max_nonces);
 
-        // Check results
+        let func_brute = self.device.get_func(module, "blake3_brute_force")?;
         let mut solution_found = vec![0u8; 1];
         let mut solution_nonce = vec![0u64; 1];
         let mut solution_hash = vec![0u8; 32];
-        
-        self.device.dtoh_sync_copy_into(&d_solution_found, &mut solution_found).ok()?;
-        
+
+        // Split into short launches so the heartbeat, pause and refresh keep up with long ranges
+        let mut launch_nonce = start_nonce;
+        let mut remaining = iterations_per_thread;
+        let mut reported = 0u64;
+        while remaining > 0 && solution_found[0] == 0 {
+            if Self::interrupted(found, control) {
+                return None;
+            }
+            let iterations = remaining.min(BRUTE_FORCE_LAUNCH_ITERATIONS as u64);
+            unsafe {
+                func_brute.clone().launch(cfg, (
+                    &d_header,           // const uint8_t* header_prefix
+                    header_len_u32,      // uint32_t header_len
+                    launch_nonce,        // uint64_t start_nonce
+                    &d_target,           // const uint8_t* target_bytes
+                    &mut d_solution_found, // uint8_t* solution_found
+                    &mut d_solution_nonce, // uint64_t* solution_nonce
+                    iterations as u32,     // uint32_t max_iterations
+                    &mut d_solution_hash,  // uint8_t* solution_hash
+                )).ok()?;
+            }
+            self.device.dtoh_sync_copy_into(&d_solution_found, &mut solution_found).ok()?;
+
+            let covered = (iterations * total_threads).min(max_nonces - reported);
+            self.report(covered);
+            reported += covered;
+            launch_nonce = launch_nonce.wrapping_add(iterations * total_threads);
+            remaining -= iterations;
+        }
+
         if solution_found[0] != 0 {
             self.device.dtoh_sync_copy_into(&d_solution_nonce, &mut solution_nonce).ok()?;
             self.device.dtoh_sync_copy_into(&d_solution_hash, &mut solution_hash).ok()?;
             let nonce = solution_nonce[0];
             
-            eprintln!("✅ GPU brute-force found solution at nonce: {}", nonce);
+            info!("✅ GPU brute-force found solution at nonce: {}", nonce);
             
             // GPU has already computed and verified the hash - use it directly!
             let mut hash = [0u8; 32];
@@ -510,9 +684,7 @@ impl GpuMiner {
             // Debug: verify the solution on CPU
             // Build the full mutation vector (16 bytes: nonce + zeros to match GPU)
             let mut mutation_vector = vec![0u8; 16];
-            for i in 0..8 {
-                mutation_vector[i] = ((nonce >> (i * 8)) & 0xFF) as u8;
-            }
+            mutation_vector[..8].copy_from_slice(&nonce.to_le_bytes());
             // Remaining 8 bytes are zeros (matching GPU kernel)
             
             let mut input = pow_header_prefix.to_vec();
@@ -520,18 +692,18 @@ impl GpuMiner {
             let cpu_hash = blake3::hash(&input);
             let cpu_hash_uint = num_bigint::BigUint::from_bytes_be(cpu_hash.as_bytes());
             
-            eprintln!("🔍 Debug verification:");
-            eprintln!("   Nonce: {} (0x{:016x})", nonce, nonce);
-            eprintln!("   Header prefix len: {}", pow_header_prefix.len());
-            eprintln!("   Total input len: {} (header + 16-byte MV)", input.len());
-            eprintln!("   Input (first 32 bytes): {}", hex::encode(&input[..input.len().min(32)]));
-            eprintln!("   Input (last 16 bytes): {}", hex::encode(&input[input.len().saturating_sub(16)..]));
-            eprintln!("   GPU hash: {}", hex::encode(&hash));
-            eprintln!("   CPU hash: {}", hex::encode(cpu_hash.as_bytes()));
-            eprintln!("   Hashes match? {}", &hash[..] == cpu_hash.as_bytes());
-            eprintln!("   Target:   {}", hex::encode(&target_bytes));
-            eprintln!("   GPU hash <= target? {}", num_bigint::BigUint::from_bytes_be(&hash) <= *target);
-            eprintln!("   CPU hash <= target? {}", cpu_hash_uint <= *target);
+            debug!("🔍 Debug verification:");
+            debug!("   Nonce: {} (0x{:016x})", nonce, nonce);
+            debug!("   Header prefix len: {}", pow_header_prefix.len());
+            debug!("   Total input len: {} (header + 16-byte MV)", input.len());
+            debug!("   Input (first 32 bytes): {}", hex::encode(&input[..input.len().min(32)]));
+            debug!("   Input (last 16 bytes): {}", hex::encode(&input[input.len().saturating_sub(16)..]));
+            debug!("   GPU hash: {}", hex::encode(hash));
+            debug!("   CPU hash: {}", hex::encode(cpu_hash.as_bytes()));
+            debug!("   Hashes match? {}", &hash[..] == cpu_hash.as_bytes());
+            debug!("   Target:   {}", hex::encode(target_bytes));
+            debug!("   GPU hash <= target? {}", num_bigint::BigUint::from_bytes_be(&hash) <= *target);
+            debug!("   CPU hash <= target? {}", cpu_hash_uint <= *target);
             
             // Return the mutation vector we used (same as what we hashed)
             return Some((mutation_vector, hash));
@@ -552,12 +724,26 @@ impl GpuMiner {
         Err("CUDA support not compiled. Build with --features cuda".into())
     }
 
+    pub fn bus_number(&self) -> Option<u32> {
+        None
+    }
+
+    pub fn hardware(&self) -> String {
+        String::new()
+    }
+
+    pub fn limits(&self) -> (u32, u32) {
+        (1024, 1)
+    }
+
     pub fn mine_with_ga(
         &self,
         _header_prefix: &[u8],
         _target: &BigUint,
         _generations: usize,
         _mutation_rate: f32,
+        _found: &AtomicBool,
+        _control: Option<&MinerControl>,
     ) -> Option<(Vec<u8>, [u8; 32])> {
         None
     }
@@ -577,6 +763,8 @@ impl GpuMiner {
         _target: &BigUint,
         _start_nonce: u64,
         _max_nonces: u64,
+        _found: &AtomicBool,
+        _control: Option<&MinerControl>,
     ) -> Option<(Vec<u8>, [u8; 32])> {
         None
     }
@@ -589,102 +777,19 @@ pub fn cpu_ga_mine(
     population_size: usize,
     mv_len: usize,
     generations: usize,
-    mutation_rate: f32,
+    ga_config: &GaConfig,
+    rng: &mut (dyn RngCore + Send),
 ) -> Option<(Vec<u8>, [u8; 32])> {
-    use blake3::Hasher;
-
-    // Individual: (mutation_vector, fitness)
-    let mut population: Vec<(Vec<u8>, f32)> = (0..population_size)
-        .map(|_| {
-            let mut mv = vec![0u8; mv_len];
-            rand::thread_rng().fill(&mut mv[..]);
-            (mv, 0.0)
-        })
-        .collect();
-
-    let mut rng = rand::thread_rng();
-    let mut best_fitness = 0.0f32;
-
-    for gen in 0..generations {
-        // Evaluate fitness
-        for (mv, fitness) in &mut population {
-            let mut candidate = header_prefix.to_vec();
-            candidate.extend_from_slice(mv);
-
-            let mut hasher = Hasher::new();
-            hasher.update(&candidate);
-            let hash = hasher.finalize();
-            let hash_bytes = hash.as_bytes();
-
-            // Check if solution
-            let hash_bigint = BigUint::from_bytes_be(hash_bytes);
-            if &hash_bigint <= target {
-                let mut result = [0u8; 32];
-                result.copy_from_slice(hash_bytes);
-                println!("✅ CPU GA Solution found at generation {}", gen);
-                return Some((mv.clone(), result));
-            }
-
-            // Calculate fitness (inverse of distance)
-            let diff = if &hash_bigint > target {
-                &hash_bigint - target
-            } else {
-                BigUint::from(0u32)
-            };
-
-            let bits = diff.bits() as f32;
-            *fitness = 1.0 / (1.0 + bits.ln());
-
-            if *fitness > best_fitness {
-                best_fitness = *fitness;
-            }
+    let fitness = TargetFitness::new(header_prefix, target);
+    let search = GeneticSearch::new(ga_config);
+    match search.run(&fitness, population_size, mv_len, generations, rng) {
+        Some((solution, gen)) => {
+            info!("✅ CPU GA Solution found at generation {}", gen);
+            Some(solution)
         }
-
-        if gen % 100 == 0 {
-            println!("CPU Gen {}: best_fitness={:.6}", gen, best_fitness);
+        None => {
+            info!("❌ CPU GA: No solution found after {} generations", generations);
+            None
         }
-
-        // Create next generation
-        let mut next_gen = Vec::with_capacity(population_size);
-
-        for _ in 0..population_size {
-            // Tournament selection
-            let parent1 = tournament_select(&population, &mut rng);
-            let parent2 = tournament_select(&population, &mut rng);
-
-            // Crossover
-            let crossover_point = rng.gen_range(0..mv_len);
-            let mut child = vec![0u8; mv_len];
-            child[..crossover_point].copy_from_slice(&parent1[..crossover_point]);
-            child[crossover_point..].copy_from_slice(&parent2[crossover_point..]);
-
-            // Mutation
-            for byte in &mut child {
-                if rng.gen::<f32>() < mutation_rate {
-                    *byte = rng.gen();
-                }
-            }
-
-            next_gen.push((child, 0.0));
-        }
-
-        population = next_gen;
-    }
-
-    println!(
-        "❌ CPU GA: No solution found after {} generations",
-        generations
-    );
-    None
-}
-
-fn tournament_select<'a>(population: &'a [(Vec<u8>, f32)], rng: &mut impl Rng) -> &'a Vec<u8> {
-    let idx1 = rng.gen_range(0..population.len());
-    let idx2 = rng.gen_range(0..population.len());
-
-    if population[idx1].1 > population[idx2].1 {
-        &population[idx1].0
-    } else {
-        &population[idx2].0
     }
 }
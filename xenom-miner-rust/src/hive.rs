@@ -0,0 +1,200 @@
+use crate::stats::MinerStats;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Algorithm name reported to HiveOS
+pub const HIVE_ALGO: &str = "xenom-pow";
+
+/// `stats` object expected by the HiveOS agent from `h-stats.sh`
+#[derive(Debug, Clone, Serialize)]
+pub struct HiveStats {
+    pub hs: Vec<f64>,
+    pub hs_units: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temp: Option<Vec<Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fan: Option<Vec<Value>>,
+    pub uptime: u64,
+    pub ver: String,
+    /// `[accepted, rejected, invalid, "a0;a1", "r0;r1", "i0;i1"]`
+    pub ar: Vec<Value>,
+    pub algo: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bus_numbers: Option<Vec<u32>>,
+}
+
+/// Total hashrate in kH/s plus the per-device `stats` object
+#[derive(Debug, Clone, Serialize)]
+pub struct HiveReport {
+    pub khs: f64,
+    pub stats: HiveStats,
+}
+
+fn per_device(values: impl Iterator<Item = u64>) -> Value {
+    Value::String(values.map(|v| v.to_string()).collect::<Vec<_>>().join(";"))
+}
+
+impl HiveReport {
+    pub fn from_stats(stats: &MinerStats) -> Self {
+        let hs: Vec<f64> = stats.per_gpu_hashrate_mhs.iter().map(|mhs| mhs * 1000.0).collect();
+        let bus_numbers: Option<Vec<u32>> = stats.devices.iter().map(|d| d.bus_number).collect();
+
+        Self {
+            khs: stats.hashrate_mhs * 1000.0,
+            stats: HiveStats {
+                hs,
+                hs_units: "khs",
+                temp: None,
+                fan: None,
+                uptime: stats.now(),
+                ver: stats.version.clone(),
+                ar: vec![
+                    stats.accepted_shares.into(),
+                    stats.rejected_shares.into(),
+                    stats.invalid_shares.into(),
+                    per_device(stats.devices.iter().map(|d| d.accepted)),
+                    per_device(stats.devices.iter().map(|d| d.rejected)),
+                    per_device(stats.devices.iter().map(|d| d.invalid)),
+                ],
+                algo: HIVE_ALGO,
+                bus_numbers: bus_numbers.filter(|b| !b.is_empty()),
+            },
+        }
+    }
+
+    /// Fills `temp` and `fan` from HiveOS's `$GPU_STATS_JSON`, matching devices by bus number
+    pub fn merge_gpu_stats(&mut self, gpu_stats: &Value) {
+        let Some(bus_numbers) = &self.stats.bus_numbers else {
+            return;
+        };
+        let busids = gpu_stats["busids"].as_array().cloned().unwrap_or_default();
+        let lookup = |field: &str| -> Vec<Value> {
+            bus_numbers
+                .iter()
+                .map(|bus| {
+                    busids
+                        .iter()
+                        .position(|id| id.as_str().and_then(parse_bus_number) == Some(*bus))
+                        .and_then(|idx| gpu_stats[field].get(idx).cloned())
+                        .unwrap_or(Value::from(0))
+                })
+                .collect()
+        };
+        self.stats.temp = Some(lookup("temp"));
+        self.stats.fan = Some(lookup("fan"));
+    }
+
+    /// `khs=...` and `stats='...'` lines for `eval` in `h-stats.sh`
+    pub fn to_shell(&self) -> String {
+        let stats = serde_json::to_string(&self.stats).unwrap_or_else(|_| "null".to_string());
+        format!("khs={}\nstats='{}'\n", self.khs, stats.replace('\'', r"'\''"))
+    }
+}
+
+/// Parses the bus part of a PCI id like `01:00.0` or `0000:01:00.0` (hex) into a number
+fn parse_bus_number(busid: &str) -> Option<u32> {
+    u32::from_str_radix(busid.rsplit(':').nth(1)?, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::DeviceStats;
+    use serde_json::json;
+
+    fn stats() -> MinerStats {
+        let mut stats = MinerStats::default();
+        stats.uptime_secs = 3600;
+        stats.version = "1.2.3".to_string();
+        stats.hashrate_mhs = 3.5;
+        stats.per_gpu_hashrate_mhs = vec![2.25, 1.25];
+        stats.accepted_shares = 7;
+        stats.rejected_shares = 2;
+        stats.invalid_shares = 1;
+        let mut gpu0 = DeviceStats::new("GPU 0".to_string(), Some(1));
+        (gpu0.accepted, gpu0.rejected, gpu0.invalid) = (5, 2, 0);
+        let mut gpu1 = DeviceStats::new("GPU 1".to_string(), Some(0x2b));
+        (gpu1.accepted, gpu1.rejected, gpu1.invalid) = (2, 0, 1);
+        stats.devices = vec![gpu0, gpu1];
+        stats
+    }
+
+    #[test]
+    fn report_follows_the_hive_stats_format() {
+        let report = HiveReport::from_stats(&stats());
+        assert_eq!(report.khs, 3500.0);
+        assert_eq!(
+            serde_json::to_value(&report.stats).unwrap(),
+            json!({
+                "hs": [2250.0, 1250.0],
+                "hs_units": "khs",
+                "uptime": 3600,
+                "ver": "1.2.3",
+                "ar": [7, 2, 1, "5;2", "2;0", "0;1"],
+                "algo": "xenom-pow",
+                "bus_numbers": [1, 43],
+            })
+        );
+
+        // Without bus numbers for every device, HiveOS cannot match them, so none are sent
+        let mut stats = stats();
+        stats.devices[1].bus_number = None;
+        assert_eq!(HiveReport::from_stats(&stats).stats.bus_numbers, None);
+        stats.devices.clear();
+        let report = HiveReport::from_stats(&stats);
+        assert_eq!(report.stats.bus_numbers, None);
+        assert_eq!(report.stats.ar[3], "");
+    }
+
+    #[test]
+    fn gpu_stats_are_matched_by_bus_number() {
+        let mut report = HiveReport::from_stats(&stats());
+        // HiveOS lists the cards in its own order, including ones the miner does not use
+        report.merge_gpu_stats(&json!({
+            "busids": ["00:02.0", "2b:00.0", "0000:01:00.0"],
+            "temp": [40, 71, 65],
+            "fan": [0, 80, 55],
+        }));
+        assert_eq!(report.stats.temp, Some(vec![json!(65), json!(71)]));
+        assert_eq!(report.stats.fan, Some(vec![json!(55), json!(80)]));
+
+        // Unknown cards read 0
+        report.merge_gpu_stats(&json!({"busids": ["2b:00.0"], "temp": [70]}));
+        assert_eq!(report.stats.temp, Some(vec![json!(0), json!(70)]));
+        assert_eq!(report.stats.fan, Some(vec![json!(0), json!(0)]));
+
+        let mut unmatched = HiveReport::from_stats(&MinerStats::default());
+        unmatched.merge_gpu_stats(&json!({"busids": ["01:00.0"], "temp": [70]}));
+        assert_eq!(unmatched.stats.temp, None);
+    }
+
+    #[test]
+    fn bus_numbers_parse_from_pci_ids() {
+        assert_eq!(parse_bus_number("01:00.0"), Some(1));
+        assert_eq!(parse_bus_number("0000:2b:00.0"), Some(0x2b));
+        assert_eq!(parse_bus_number("0001:FF:1f.7"), Some(0xff));
+        for malformed in ["", "01", "zz:00.0", ":00.0", "-1:00.0"] {
+            assert_eq!(parse_bus_number(malformed), None, "{}", malformed);
+        }
+    }
+
+    #[test]
+    fn shell_output_survives_eval() {
+        let mut stats = stats();
+        stats.version = "it's $HOME `id` \"v\"".to_string();
+        let shell = HiveReport::from_stats(&stats).to_shell();
+        assert!(shell.starts_with("khs=3500\nstats='{"));
+        assert!(shell.contains(r#""ver":"it'\''s $HOME `id` \"v\"""#));
+
+        let script = format!("{}printf '%s\\n%s' \"$khs\" \"$stats\"", shell);
+        let output = std::process::Command::new("sh").arg("-c").arg(&script).output();
+        // sh is missing on some platforms; the quoting above is what matters there
+        if let Ok(output) = output {
+            let output = String::from_utf8(output.stdout).unwrap();
+            let (khs, json) = output.split_once('\n').unwrap();
+            assert_eq!(khs, "3500");
+            let parsed: Value = serde_json::from_str(json).unwrap();
+            assert_eq!(parsed["ver"], stats.version);
+        }
+    }
+}
@@ -0,0 +1,539 @@
+#[cfg(unix)]
+use log::Level;
+use log::{LevelFilter, Log, Metadata, Record};
+use parking_lot::Mutex;
+use serde_json::{Map, Value};
+use std::cell::RefCell;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::marker::PhantomData;
+#[cfg(unix)]
+use std::os::unix::net::UnixDatagram;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Module path prefix stripped from log targets for display and filtering
+const CRATE_PREFIX: &str = "xenom_miner_rust";
+#[cfg(unix)]
+const SYSLOG_SOCKET: &str = "/dev/log";
+#[cfg(unix)]
+const JOURNALD_SOCKET: &str = "/run/systemd/journal/socket";
+#[cfg(unix)]
+const IDENTIFIER: &str = "xenom-miner";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum LogFormat {
+    /// Timestamped text lines
+    Human,
+    /// One JSON object per line
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum RotateInterval {
+    Hourly,
+    Daily,
+}
+
+impl RotateInterval {
+    fn secs(self) -> u64 {
+        match self {
+            RotateInterval::Hourly => 3600,
+            RotateInterval::Daily => 86400,
+        }
+    }
+}
+
+/// Logging setup, built from the `--log-*` flags
+#[derive(Debug, Clone)]
+pub struct LogConfig {
+    /// `level[,module=level...]`, e.g. `info,gpu_miner=debug,api=warn`
+    pub filter: String,
+    pub format: LogFormat,
+    pub file: Option<PathBuf>,
+    pub rotate_size: Option<u64>,
+    pub rotate_interval: Option<RotateInterval>,
+    /// Rotated files kept next to the active one (`miner.log.1` is the newest); at least 1
+    pub keep: usize,
+    /// Unix only, like `journald`
+    pub syslog: bool,
+    pub journald: bool,
+}
+
+/// Parses sizes like `1048576`, `512K`, `10M` or `1G`
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let (digits, multiplier) = match s.char_indices().last() {
+        Some((i, 'k' | 'K')) => (&s[..i], 1 << 10),
+        Some((i, 'm' | 'M')) => (&s[..i], 1 << 20),
+        Some((i, 'g' | 'G')) => (&s[..i], 1 << 30),
+        _ => (s, 1),
+    };
+    let n = digits.parse::<u64>().map_err(|_| format!("invalid size: {}", s))?;
+    n.checked_mul(multiplier).ok_or_else(|| format!("size too large: {}", s))
+}
+
+/// Default level plus per-module overrides; the longest matching module wins
+struct Filter {
+    default: LevelFilter,
+    modules: Vec<(String, LevelFilter)>,
+}
+
+impl Filter {
+    fn parse(spec: &str) -> Result<Self, String> {
+        let parse_level = |s: &str| s.trim().parse::<LevelFilter>().map_err(|_| format!("invalid log level: {}", s));
+        let mut filter = Filter {
+            default: LevelFilter::Info,
+            modules: Vec::new(),
+        };
+        for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            match part.split_once('=') {
+                Some((module, level)) => filter.modules.push((module.trim().to_string(), parse_level(level)?)),
+                None => filter.default = parse_level(part)?,
+            }
+        }
+        filter.modules.sort_by_key(|(m, _)| std::cmp::Reverse(m.len()));
+        Ok(filter)
+    }
+
+    fn level_for(&self, target: &str) -> LevelFilter {
+        let short = short_target(target);
+        let matches = |t: &str, m: &str| t == m || t.strip_prefix(m).is_some_and(|rest| rest.starts_with("::"));
+        self.modules
+            .iter()
+            .find(|(m, _)| matches(target, m) || matches(short, m))
+            .map(|(_, level)| *level)
+            .unwrap_or(self.default)
+    }
+
+    fn max_level(&self) -> LevelFilter {
+        self.modules.iter().map(|(_, l)| *l).fold(self.default, Ord::max)
+    }
+}
+
+/// Checks a `--log-level` filter without installing a logger
+pub fn check_filter(spec: &str) -> Result<(), String> {
+    Filter::parse(spec).map(|_| ())
+}
+
+/// `xenom_miner_rust::gpu_miner` -> `gpu_miner`; the crate root becomes empty
+fn short_target(target: &str) -> &str {
+    match target.strip_prefix(CRATE_PREFIX) {
+        Some(rest) => rest.trim_start_matches("::"),
+        None => target,
+    }
+}
+
+thread_local! {
+    static CONTEXT: RefCell<Vec<(&'static str, String)>> = const { RefCell::new(Vec::new()) };
+}
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Short process-unique correlation ID such as `tpl-12`
+pub fn next_id(prefix: &str) -> String {
+    format!("{}-{}", prefix, NEXT_ID.fetch_add(1, Ordering::Relaxed))
+}
+
+/// Removes the correlation fields it added when dropped. Bound to its thread.
+pub struct ContextGuard {
+    len: usize,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for ContextGuard {
+    fn drop(&mut self) {
+        CONTEXT.with(|c| c.borrow_mut().truncate(self.len));
+    }
+}
+
+/// Attaches `key=id` to every record logged by this thread until the guard is dropped
+pub fn scope(key: &'static str, id: impl Into<String>) -> ContextGuard {
+    enter(vec![(key, id.into())])
+}
+
+/// Correlation fields of the current thread, to hand over to worker threads
+pub fn context() -> Vec<(&'static str, String)> {
+    CONTEXT.with(|c| c.borrow().clone())
+}
+
+/// Adds fields captured with [`context`] on another thread
+pub fn enter(fields: Vec<(&'static str, String)>) -> ContextGuard {
+    CONTEXT.with(|c| {
+        let mut c = c.borrow_mut();
+        let len = c.len();
+        c.extend(fields);
+        ContextGuard {
+            len,
+            _not_send: PhantomData,
+        }
+    })
+}
+
+/// RFC 3339 UTC timestamp with milliseconds
+fn timestamp(now: SystemTime) -> String {
+    let since_epoch = now.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (days, rem) = (secs / 86400, secs % 86400);
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+/// Log file that moves itself to `<path>.1` when it grows past a size or a period ends
+struct RotatingFile {
+    path: PathBuf,
+    max_size: Option<u64>,
+    interval: Option<u64>,
+    keep: usize,
+    file: File,
+    size: u64,
+    period: u64,
+}
+
+fn unix_secs(t: SystemTime) -> u64 {
+    t.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+fn numbered(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+impl RotatingFile {
+    fn open(path: &Path, max_size: Option<u64>, interval: Option<RotateInterval>, keep: usize) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let metadata = file.metadata()?;
+        let interval = interval.map(RotateInterval::secs);
+        // An existing file from an earlier period is rotated on the first write
+        let modified = metadata.modified().map(unix_secs).unwrap_or(0);
+        Ok(Self {
+            path: path.to_path_buf(),
+            max_size,
+            interval,
+            keep,
+            file,
+            size: metadata.len(),
+            period: interval.map(|i| modified / i).unwrap_or(0),
+        })
+    }
+
+    fn write_line(&mut self, line: &str, now: SystemTime) {
+        let period = self.interval.map(|i| unix_secs(now) / i).unwrap_or(0);
+        let too_big = self.max_size.is_some_and(|max| self.size > 0 && self.size + line.len() as u64 > max);
+        if too_big || period != self.period {
+            self.rotate();
+            self.period = period;
+        }
+        if self.file.write_all(line.as_bytes()).is_ok() {
+            self.size += line.len() as u64;
+        }
+    }
+
+    fn rotate(&mut self) {
+        for n in (1..self.keep).rev() {
+            let _ = std::fs::rename(numbered(&self.path, n), numbered(&self.path, n + 1));
+        }
+        let _ = std::fs::rename(&self.path, numbered(&self.path, 1));
+        let reopened = OpenOptions::new().create(true).write(true).truncate(true).open(&self.path);
+        if let Ok(file) = reopened {
+            self.file = file;
+            self.size = 0;
+        }
+    }
+}
+
+struct Logger {
+    filter: Filter,
+    format: LogFormat,
+    file: Option<Mutex<RotatingFile>>,
+    #[cfg(unix)]
+    syslog: Option<UnixDatagram>,
+    #[cfg(unix)]
+    journald: Option<UnixDatagram>,
+}
+
+impl Logger {
+    fn format_line(&self, record: &Record, now: SystemTime, fields: &[(&'static str, String)]) -> String {
+        let target = short_target(record.target());
+        match self.format {
+            LogFormat::Human => {
+                let mut line = format!("{} {:<5} ", timestamp(now), record.level());
+                if !fields.is_empty() {
+                    let ids: Vec<&str> = fields.iter().map(|(_, id)| id.as_str()).collect();
+                    line.push_str(&format!("[{}] ", ids.join(" ")));
+                }
+                if !target.is_empty() {
+                    line.push_str(&format!("{}: ", target));
+                }
+                format!("{}{}\n", line, record.args())
+            }
+            LogFormat::Json => {
+                let mut obj = Map::new();
+                obj.insert("ts".into(), timestamp(now).into());
+                obj.insert("level".into(), record.level().as_str().to_lowercase().into());
+                obj.insert("target".into(), if target.is_empty() { "main" } else { target }.into());
+                obj.insert("msg".into(), record.args().to_string().into());
+                for (key, id) in fields {
+                    obj.insert((*key).into(), Value::String(id.clone()));
+                }
+                format!("{}\n", Value::Object(obj))
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    fn send_syslog(&self, socket: &UnixDatagram, record: &Record, fields: &[(&'static str, String)]) {
+        let severity = match record.level() {
+            Level::Error => 3,
+            Level::Warn => 4,
+            Level::Info => 6,
+            Level::Debug | Level::Trace => 7,
+        };
+        // Facility "user" (1)
+        let mut msg = format!("<{}>{}[{}]: ", 8 + severity, IDENTIFIER, std::process::id());
+        for (key, id) in fields {
+            msg.push_str(&format!("{}={} ", key, id));
+        }
+        msg.push_str(&record.args().to_string());
+        let _ = socket.send(msg.as_bytes());
+    }
+
+    #[cfg(unix)]
+    fn send_journald(&self, socket: &UnixDatagram, record: &Record, fields: &[(&'static str, String)]) {
+        let priority = match record.level() {
+            Level::Error => "3",
+            Level::Warn => "4",
+            Level::Info => "6",
+            Level::Debug | Level::Trace => "7",
+        };
+        let mut datagram = Vec::new();
+        let mut field = |key: &str, value: &str| {
+            // Native journal protocol: multi-line values are length-prefixed
+            if value.contains('\n') {
+                datagram.extend_from_slice(key.as_bytes());
+                datagram.push(b'\n');
+                datagram.extend_from_slice(&(value.len() as u64).to_le_bytes());
+                datagram.extend_from_slice(value.as_bytes());
+                datagram.push(b'\n');
+            } else {
+                datagram.extend_from_slice(format!("{}={}\n", key, value).as_bytes());
+            }
+        };
+        field("MESSAGE", &record.args().to_string());
+        field("PRIORITY", priority);
+        field("SYSLOG_IDENTIFIER", IDENTIFIER);
+        field("TARGET", record.target());
+        for (key, id) in fields {
+            field(&format!("XENOM_{}", key.to_uppercase()), id);
+        }
+        let _ = socket.send_to(&datagram, JOURNALD_SOCKET);
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.filter.level_for(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let now = SystemTime::now();
+        let fields = context();
+        let line = self.format_line(record, now, &fields);
+
+        // stdout is reserved for command results (solutions, `hive-stats`)
+        let _ = std::io::stderr().write_all(line.as_bytes());
+        if let Some(file) = &self.file {
+            file.lock().write_line(&line, now);
+        }
+        #[cfg(unix)]
+        if let Some(socket) = &self.syslog {
+            self.send_syslog(socket, record, &fields);
+        }
+        #[cfg(unix)]
+        if let Some(socket) = &self.journald {
+            self.send_journald(socket, record, &fields);
+        }
+    }
+
+    fn flush(&self) {
+        let _ = std::io::stderr().flush();
+        if let Some(file) = &self.file {
+            let _ = file.lock().file.flush();
+        }
+    }
+}
+
+/// Installs the global logger. Must be called once, before anything logs.
+pub fn init(config: &LogConfig) -> Result<(), String> {
+    let filter = Filter::parse(&config.filter)?;
+    if config.keep == 0 && (config.rotate_size.is_some() || config.rotate_interval.is_some()) {
+        return Err("log rotation needs at least 1 kept file".to_string());
+    }
+    let file = match &config.file {
+        Some(path) => Some(Mutex::new(
+            RotatingFile::open(path, config.rotate_size, config.rotate_interval, config.keep)
+                .map_err(|e| format!("cannot open log file {}: {}", path.display(), e))?,
+        )),
+        None => None,
+    };
+    #[cfg(not(unix))]
+    if config.syslog || config.journald {
+        return Err("syslog and journald logging are only supported on Unix".to_string());
+    }
+    #[cfg(unix)]
+    let syslog = if config.syslog {
+        let socket = UnixDatagram::unbound().map_err(|e| format!("syslog: {}", e))?;
+        socket
+            .connect(SYSLOG_SOCKET)
+            .map_err(|e| format!("cannot connect to syslog at {}: {}", SYSLOG_SOCKET, e))?;
+        Some(socket)
+    } else {
+        None
+    };
+    #[cfg(unix)]
+    let journald = if config.journald {
+        if !Path::new(JOURNALD_SOCKET).exists() {
+            return Err(format!("journald socket {} not found", JOURNALD_SOCKET));
+        }
+        Some(UnixDatagram::unbound().map_err(|e| format!("journald: {}", e))?)
+    } else {
+        None
+    };
+
+    let max_level = filter.max_level();
+    log::set_boxed_logger(Box::new(Logger {
+        filter,
+        format: config.format,
+        file,
+        #[cfg(unix)]
+        syslog,
+        #[cfg(unix)]
+        journald,
+    }))
+    .map_err(|e| e.to_string())?;
+    log::set_max_level(max_level);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+    use std::time::Duration;
+
+    #[test]
+    fn filter_picks_the_longest_module() {
+        let filter = Filter::parse("warn, gpu_miner=debug ,gpu_miner::kernel=error,api=off").unwrap();
+        assert_eq!(filter.level_for("xenom_miner_rust"), LevelFilter::Warn);
+        assert_eq!(filter.level_for("xenom_miner_rust::gpu_miner"), LevelFilter::Debug);
+        assert_eq!(filter.level_for("xenom_miner_rust::gpu_miner::kernel"), LevelFilter::Error);
+        assert_eq!(filter.level_for("xenom_miner_rust::gpu_miner_extra"), LevelFilter::Warn);
+        assert_eq!(filter.level_for("xenom_miner_rust::api"), LevelFilter::Off);
+        assert_eq!(filter.level_for("reqwest::connect"), LevelFilter::Warn);
+        assert_eq!(filter.max_level(), LevelFilter::Debug);
+
+        assert_eq!(Filter::parse("").unwrap().level_for("x"), LevelFilter::Info);
+        assert_eq!(Filter::parse("reqwest=trace").unwrap().level_for("reqwest::connect"), LevelFilter::Trace);
+        assert!(Filter::parse("loud").is_err());
+        assert!(check_filter("info,api=verbose").is_err());
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(parse_size("1048576"), Ok(1 << 20));
+        assert_eq!(parse_size(" 512K"), Ok(512 << 10));
+        assert_eq!(parse_size("10m"), Ok(10 << 20));
+        assert_eq!(parse_size("1G"), Ok(1 << 30));
+        assert!(parse_size("10MB").is_err());
+        assert!(parse_size("M").is_err());
+        assert_eq!(parse_size("16777215G"), Ok(16_777_215 << 30));
+        assert_eq!(parse_size("99999999999G"), Err("size too large: 99999999999G".to_string()));
+        assert!(parse_size("18446744073709551616").is_err());
+    }
+
+    #[test]
+    fn timestamps_are_rfc3339_utc() {
+        assert_eq!(timestamp(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+        let leap_day = UNIX_EPOCH + Duration::from_millis(951_782_400_000 + 3_723_456);
+        assert_eq!(timestamp(leap_day), "2000-02-29T01:02:03.456Z");
+    }
+
+    fn read(path: &Path) -> String {
+        std::fs::read_to_string(path).unwrap_or_default()
+    }
+
+    #[test]
+    fn rotates_by_size_and_keeps_the_newest() {
+        let dir = TestDir::new("log-size");
+        let path = dir.path().join("miner.log");
+        let mut file = RotatingFile::open(&path, Some(10), None, 2).unwrap();
+        let now = SystemTime::now();
+        for line in ["aaaaaa\n", "bbbbbb\n", "cccccc\n", "dddddd\n"] {
+            file.write_line(line, now);
+        }
+        assert_eq!(read(&path), "dddddd\n");
+        assert_eq!(read(&numbered(&path, 1)), "cccccc\n");
+        assert_eq!(read(&numbered(&path, 2)), "bbbbbb\n");
+        assert!(!numbered(&path, 3).exists());
+
+        // A line longer than the limit still goes into a fresh file
+        file.write_line("a line over ten bytes\n", now);
+        assert_eq!(read(&path), "a line over ten bytes\n");
+        assert_eq!(read(&numbered(&path, 1)), "dddddd\n");
+    }
+
+    #[test]
+    fn rotates_when_the_period_ends() {
+        let dir = TestDir::new("log-interval");
+        // Appends to the file of an earlier run
+        let path = dir.write("miner.log", "first\n");
+        let mut file = RotatingFile::open(&path, None, Some(RotateInterval::Hourly), 1).unwrap();
+        let hour = SystemTime::now();
+        file.period = unix_secs(hour) / 3600;
+        file.write_line("second\n", hour);
+        file.write_line("next hour\n", hour + Duration::from_secs(3600));
+        assert_eq!(read(&path), "next hour\n");
+        assert_eq!(read(&numbered(&path, 1)), "first\nsecond\n");
+    }
+
+    #[test]
+    fn rotation_needs_a_kept_file() {
+        let dir = TestDir::new("log-keep");
+        let config = LogConfig {
+            filter: "info".to_string(),
+            format: LogFormat::Human,
+            file: Some(dir.path().join("miner.log")),
+            rotate_size: Some(1 << 20),
+            rotate_interval: None,
+            keep: 0,
+            syslog: false,
+            journald: false,
+        };
+        assert!(init(&config).unwrap_err().contains("at least 1"));
+    }
+}
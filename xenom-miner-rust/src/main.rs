@@ -1,22 +1,55 @@
 use blake3::Hasher;
-use clap::Parser;
-use rand::prelude::*;
-use rayon::prelude::*;
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use log::{error, info, warn};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Instant;
 
+mod affinity;
+#[cfg(test)]
+mod alloc_count;
+mod api;
+mod compat;
+mod config;
+mod control;
+mod cpu_miner;
+mod evaluate;
+mod events;
+mod ga;
 mod gpu_miner;
+mod hive;
+mod logging;
 mod node_client;
+mod seed;
 mod stats;
+mod systemd;
+mod telemetry;
+#[cfg(test)]
+mod test_dir;
+mod thermal;
+mod throttle;
+mod tune;
+mod watchdog;
 
 /// Rust で実装された最適化マイナー。並列 GA（CPU）に対応し、GPU/OpenCL 統合用のフックを備える
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone)]
 #[command(author, version, about = "Xenom optimized miner (Rust) - BLAKE3 + GA", long_about = None)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// TOML config file with options by long name and `[profile.<name>]` tables
+    /// (also XENOM_CONFIG). Every option can be overridden by XENOM_<OPTION> variables.
+    #[arg(long)]
+    config: Option<std::path::PathBuf>,
+
+    /// Config profile applied over the file's top level (repeatable, later ones win; also XENOM_PROFILE)
+    #[arg(long)]
+    profile: Vec<String>,
+
     /// ヘッダバイトの 16 進文字列（`BlockHeader.bytes()` のシリアライズ形式に準拠）
     /// Not required in --mine-loop mode
-    #[arg(short, long)]
+    #[arg(long)]
     header_hex: Option<String>,
 
     /// difficulty bits（compact uint32）の 16 進表記。例: 1f00ffff
@@ -44,10 +77,56 @@ struct Args {
     #[arg(short = 't', long, default_value_t = 0usize)]
     threads: usize,
 
+    /// CPUs the CPU mining threads are pinned to, e.g. `0-7,16-23`
+    /// (default: not pinned, unless another --cpu-* option selects CPUs)
+    #[arg(long, value_parser = affinity::parse_list)]
+    cpu_affinity: Option<affinity::IdList>,
+
+    /// Only mine on CPUs of these NUMA nodes, e.g. `1` or `0,1`. Pinned threads allocate
+    /// their memory from their own node
+    #[arg(long, value_parser = affinity::parse_list)]
+    cpu_numa_nodes: Option<affinity::IdList>,
+
+    /// Pin one CPU mining thread per physical core, skipping hyperthread siblings
+    #[arg(long, default_value_t = false)]
+    cpu_no_smt: bool,
+
+    /// Physical cores (with their hyperthread siblings) left free for the node and the
+    /// system, lowest-numbered first
+    #[arg(long, default_value_t = 0)]
+    cpu_reserve: usize,
+
+    /// Scheduling policy of the CPU mining threads (`idle` only uses otherwise idle CPU time)
+    #[arg(long, value_enum, default_value_t = affinity::Priority::Normal)]
+    cpu_priority: affinity::Priority,
+
+    /// Nice value of the CPU mining threads, -20 to 19 (below 0 needs CAP_SYS_NICE)
+    #[arg(long, allow_hyphen_values = true, value_parser = clap::value_parser!(i32).range(-20..=19))]
+    cpu_nice: Option<i32>,
+
+    /// Where sysfs is mounted, read for the CPU topology and temperature sensors
+    /// (point it at a fake tree to test placement or thermal throttling)
+    #[arg(long, default_value = "/sys")]
+    sysfs_root: std::path::PathBuf,
+
+    /// Seed for every search RNG, to replay a run (random and logged if unset).
+    /// The same seed, template and options produce the same candidates
+    #[arg(long)]
+    seed: Option<u64>,
+
     /// GA を使わず CPU ブルートフォースのみ
     #[arg(long, default_value_t = false)]
     brute: bool,
 
+    /// First nonce of the CPU brute-force search (to resume an earlier run)
+    #[arg(long, default_value_t = 0)]
+    start_nonce: u64,
+
+    /// Extranonce (mutation vector bytes 8-15) of the CPU brute-force search
+    /// (default: derived from the seed; 0 matches the GPU brute-force kernel)
+    #[arg(long)]
+    extranonce: Option<u64>,
+
     /// GPU (CUDA) を使用して GA を実行
     #[arg(long, default_value_t = false)]
     gpu: bool,
@@ -60,14 +139,124 @@ struct Args {
     #[arg(long, default_value_t = 2000usize)]
     batches: usize,
 
-    /// GPU mutation rate (0.0-1.0)
+    /// GA mutation rate (0.0-1.0): per byte, or per bit with `--ga-mutation bit-flip`.
+    /// The GPU GA kernel uses it per byte
     #[arg(long, default_value_t = 0.01)]
     mutation_rate: f32,
 
+    /// CPU GA parent selection
+    #[arg(long, value_enum, default_value_t = ga::SelectionKind::Tournament)]
+    ga_selection: ga::SelectionKind,
+
+    /// Parents drawn per tournament with `--ga-selection tournament`
+    #[arg(long, default_value_t = 2)]
+    ga_tournament_size: usize,
+
+    /// CPU GA crossover
+    #[arg(long, value_enum, default_value_t = ga::CrossoverKind::OnePoint)]
+    ga_crossover: ga::CrossoverKind,
+
+    /// CPU GA mutation
+    #[arg(long, value_enum, default_value_t = ga::MutationKind::Byte)]
+    ga_mutation: ga::MutationKind,
+
+    /// Best individuals carried unchanged into the next CPU GA generation (per island)
+    #[arg(long, default_value_t = 0)]
+    ga_elitism: usize,
+
+    /// CPU GA islands the population is split into (0 = one per thread, logged at startup;
+    /// 1 = single population)
+    #[arg(long, default_value_t = 0)]
+    ga_islands: usize,
+
+    /// Generations between migrations between islands (0 = never)
+    #[arg(long, default_value_t = 50)]
+    ga_migration_interval: usize,
+
+    /// Best individuals each island sends per migration
+    #[arg(long, default_value_t = 2)]
+    ga_migrants: usize,
+
+    /// Islands that receive an island's migrants
+    #[arg(long, value_enum, default_value_t = ga::Topology::Ring)]
+    ga_topology: ga::Topology,
+
+    /// Adapt the CPU GA mutation rate and selection pressure to population diversity
+    #[arg(long, default_value_t = false)]
+    ga_adaptive: bool,
+
+    /// Generations without improvement before a CPU GA population restarts around its elites (0 = never)
+    #[arg(long, default_value_t = 0)]
+    ga_stagnation: usize,
+
+    /// Log every CPU GA adaptation step at info level (debug otherwise)
+    #[arg(long, default_value_t = false)]
+    ga_adaptive_trace: bool,
+
+    /// Write CPU GA statistics for every generation (and island) to this file
+    #[arg(long)]
+    ga_telemetry: Option<std::path::PathBuf>,
+
+    /// Format of --ga-telemetry
+    #[arg(long, value_enum, default_value_t = telemetry::TelemetryFormat::Jsonl)]
+    ga_telemetry_format: telemetry::TelemetryFormat,
+
     /// Mine in loop mode: fetch templates from node, mine until solution found, submit
     #[arg(long, default_value_t = false)]
     mine_loop: bool,
 
+    /// Measure each device before --mine-loop starts and pick the CPU threads, GPU launch
+    /// geometry, batches and population so that a search round takes about --tune-latency
+    #[arg(long, default_value_t = false)]
+    auto_tune: bool,
+
+    /// Target duration of one search round for --auto-tune, in milliseconds
+    #[arg(long, default_value_t = 2000)]
+    tune_latency: u64,
+
+    /// Cache of --auto-tune profiles by hardware fingerprint
+    /// (default: $XDG_CACHE_HOME/xenom-miner/tune.json or ~/.cache/xenom-miner/tune.json)
+    #[arg(long)]
+    tune_cache: Option<std::path::PathBuf>,
+
+    /// Measure again even if the cache has a profile for this hardware
+    #[arg(long, default_value_t = false, requires = "auto_tune")]
+    retune: bool,
+
+    /// Hashrate cap of all --mine-loop devices together in H/s, e.g. `500k` or `2.5M` (0 = unlimited)
+    #[arg(long, value_parser = throttle::parse_hashrate, default_value = "0")]
+    max_hashrate: f64,
+
+    /// Hashrate cap of each --mine-loop device in H/s (0 = unlimited)
+    #[arg(long, value_parser = throttle::parse_hashrate, default_value = "0")]
+    device_max_hashrate: f64,
+
+    /// Fraction of the time each --mine-loop device mines, above 0.0 up to 1.0
+    #[arg(long, default_value_t = 1.0)]
+    duty_cycle: f64,
+
+    /// Back off CPU mining while a CPU temperature sensor reads above this many °C (0 = never;
+    /// CPU backend only, ignored with --gpu)
+    #[arg(long, default_value_t = 0.0)]
+    thermal_limit: f64,
+
+    /// °C below which a thermal back-off is undone step by step (default: 5 below --thermal-limit)
+    #[arg(long)]
+    thermal_resume: Option<f64>,
+
+    /// What a thermal back-off reduces
+    #[arg(long, value_enum, default_value_t = thermal::ThermalAction::Threads)]
+    thermal_action: thermal::ThermalAction,
+
+    /// Temperature sensors to watch by name, e.g. `coretemp` or `Package` (repeatable;
+    /// default: CPU sensors, or every thermal zone if none is recognised)
+    #[arg(long)]
+    thermal_sensor: Vec<String>,
+
+    /// Seconds between temperature readings
+    #[arg(long, default_value_t = 2)]
+    thermal_interval: u64,
+
     /// Node URL for loop mining mode
     #[arg(long, default_value = "http://localhost:36669")]
     node_url: String,
@@ -83,6 +272,161 @@ struct Args {
     /// API server port for stats endpoint (default: 3333)
     #[arg(long, default_value_t = 3333)]
     api_port: u16,
+
+    /// Additional node URLs used for failover in loop mode (repeatable)
+    #[arg(long)]
+    fallback_node: Vec<String>,
+
+    /// Bearer token enabling the `/control` endpoints of the API server (disabled if unset)
+    #[arg(long)]
+    control_token: Option<String>,
+
+    /// API listen address, `host:port`, `unix:/path/to/socket` or `systemd[:name]` for a
+    /// socket-activated listener (repeatable).
+    /// Defaults to 127.0.0.1:<api-port>; use 0.0.0.0:<port> to serve other hosts
+    #[arg(long)]
+    api_bind: Vec<String>,
+
+    /// Bearer token required to read the stats API (`/health` stays open)
+    #[arg(long)]
+    api_token: Option<String>,
+
+    /// PEM certificate chain for serving the API over TLS (requires --features tls)
+    #[arg(long, requires = "api_tls_key")]
+    api_tls_cert: Option<std::path::PathBuf>,
+
+    /// PEM private key matching --api-tls-cert
+    #[arg(long, requires = "api_tls_cert")]
+    api_tls_key: Option<std::path::PathBuf>,
+
+    /// Browser origin allowed to read the API via CORS (repeatable, `*` for any)
+    #[arg(long)]
+    api_cors_origin: Vec<String>,
+
+    /// Serve Claymore `miner_getstat1` JSON-RPC on this TCP address (e.g. 0.0.0.0:3333)
+    #[arg(long)]
+    claymore_bind: Option<String>,
+
+    /// Serve xmrig-style `/1/summary` HTTP stats on this address
+    #[arg(long)]
+    xmrig_bind: Option<String>,
+
+    /// Seconds to finish in-flight work after SIGINT/SIGTERM before exiting anyway
+    #[arg(long, default_value_t = 15)]
+    shutdown_timeout: u64,
+
+    /// `/health/ready` fails when the last template is older than this many seconds, and
+    /// `/health/live` when the mining loop has been inactive as long (0 = never)
+    #[arg(long, default_value_t = 120)]
+    health_template_age: u64,
+
+    /// `/health/ready` fails after this many failed submissions in a row (0 = never)
+    #[arg(long, default_value_t = 5)]
+    health_submit_failures: u64,
+
+    /// Seconds a device may go without computing hashes before its worker is restarted (0 = never)
+    #[arg(long, default_value_t = 60)]
+    worker_timeout: u64,
+
+    /// Seconds between `hashrate` events on the `/events` stream
+    #[arg(long, default_value_t = 10)]
+    hashrate_event_interval: u64,
+
+    /// Log filter: default level plus per-module levels, e.g. `info,gpu_miner=debug`
+    #[arg(long, default_value = "info")]
+    log_level: String,
+
+    /// Log line format
+    #[arg(long, value_enum, default_value_t = logging::LogFormat::Human)]
+    log_format: logging::LogFormat,
+
+    /// Also write logs to this file
+    #[arg(long)]
+    log_file: Option<std::path::PathBuf>,
+
+    /// Rotate the log file when it reaches this size (e.g. 50M)
+    #[arg(long, value_parser = logging::parse_size, requires = "log_file")]
+    log_rotate_size: Option<u64>,
+
+    /// Rotate the log file every hour or day
+    #[arg(long, value_enum, requires = "log_file")]
+    log_rotate_interval: Option<logging::RotateInterval>,
+
+    /// Number of rotated log files to keep (at least 1)
+    #[arg(long, default_value_t = 5, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    log_keep: usize,
+
+    /// Also send logs to the local syslog daemon (/dev/log, Unix only)
+    #[arg(long, default_value_t = false)]
+    log_syslog: bool,
+
+    /// Also send logs to the systemd journal with correlation IDs as fields (Unix only)
+    #[arg(long, default_value_t = false)]
+    log_journald: bool,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum Command {
+    /// Print HiveOS `khs`/`stats` from a running miner's API (for h-stats.sh)
+    HiveStats {
+        /// Base URL of the miner's stats API
+        #[arg(long, default_value = "http://127.0.0.1:3333")]
+        api_url: String,
+
+        /// Bearer token if the miner runs with --api-token
+        #[arg(long)]
+        token: Option<String>,
+
+        /// HiveOS GPU stats file ($GPU_STATS_JSON) to merge temperatures and fans from
+        #[arg(long)]
+        gpu_stats: Option<std::path::PathBuf>,
+
+        /// Print `khs=`/`stats=` shell assignments instead of JSON
+        #[arg(long, default_value_t = false)]
+        shell: bool,
+    },
+    /// Compare search strategies over many trials at easy targets (uses --mv-len,
+    /// --population and the --ga-* options)
+    Evaluate {
+        /// Trials per strategy and target
+        #[arg(long, default_value_t = 1000)]
+        trials: usize,
+
+        /// Targets as leading zero bits of the hash (comma-separated)
+        #[arg(long, value_delimiter = ',', default_value = "12,16")]
+        target_bits: Vec<u32>,
+
+        /// Strategies to compare (comma-separated)
+        #[arg(long, value_enum, value_delimiter = ',', default_value = "brute,sequential,ga")]
+        strategy: Vec<evaluate::Strategy>,
+
+        /// Hashes after which a trial counts as unsolved (0 = 32x the expected hashes)
+        #[arg(long, default_value_t = 0)]
+        max_hashes: u64,
+
+        /// Bytes of random header prefix per trial
+        #[arg(long, default_value_t = 64)]
+        header_len: usize,
+
+        /// Write every trial to this CSV file
+        #[arg(long)]
+        csv: Option<std::path::PathBuf>,
+
+        /// Write the summary and comparisons to this JSON file
+        #[arg(long)]
+        json: Option<std::path::PathBuf>,
+    },
+    /// Inspect the merged configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum ConfigCommand {
+    /// Print the effective configuration with the source of each value and report errors
+    Check,
 }
 
 fn hex_to_bytes(s: &str) -> Vec<u8> {
@@ -120,71 +464,74 @@ fn hash_to_biguint(hash: &[u8]) -> num_bigint::BigUint {
     num_bigint::BigUint::from_bytes_be(hash)
 }
 
-// 目的値（target）の BigUint と比較可能な適応度へ変換。ハッシュが小さいほど良い。
-fn fitness_from_hash_biguint(hash: &[u8], target: &num_bigint::BigUint) -> f64 {
-    // 適応度は [0,1]。hash <= target なら 1 に近づく
-    let h = hash_to_biguint(hash);
-    if &h <= target {
-        1.0
-    } else {
-        // 対数的な距離を (0,1) に写像
-        let diff = &h - target;
-        // スケールした逆数: 1/(1+log(bits)) を利用
-        let bits = (diff.bits() as f64).max(1.0);
-        1.0 / (1.0 + bits.ln())
-    }
+/// Re-hashes a device's solution on the CPU before it is submitted
+fn verify_solution(header_prefix: &[u8], mv: &[u8], target: &num_bigint::BigUint) -> bool {
+    let mut candidate = header_prefix.to_vec();
+    candidate.extend_from_slice(mv);
+    hash_to_biguint(&blake3_hash(&candidate)) <= *target
 }
 
 fn parse_bits_hex(s: &str) -> u32 {
     u32::from_str_radix(s, 16).expect("invalid bits hex")
 }
 
-fn run_bruteforce(header_prefix: Arc<Vec<u8>>, bits: &num_bigint::BigUint, mv_len: usize) {
-    let found = Arc::new(AtomicBool::new(false));
-    let attempts = Arc::new(AtomicU64::new(0));
+/// Nonces per `run_bruteforce` progress report
+const BRUTEFORCE_CHUNK: u64 = 1 << 22;
+
+fn run_bruteforce(
+    header_prefix: Arc<Vec<u8>>,
+    bits: &num_bigint::BigUint,
+    mv_len: usize,
+    threads: usize,
+    start_nonce: u64,
+    extranonce: u64,
+) {
+    let found = AtomicBool::new(false);
     let start = Instant::now();
-    let threads = num_cpus::get();
-
-    // Thread pool already initialized in main(), just use it
-    (0..threads).into_par_iter().for_each(|tid| {
-        let mut rng = rand::thread_rng();
-        let mut local_nonce: u64 = tid as u64;
-        while !found.load(Ordering::Relaxed) {
-            let mut mv = vec![0u8; mv_len];
-            rng.fill_bytes(&mut mv);
-            // ヘッダ組み立て: header_prefix + mv（header_prefix は mutationVector を含まない部分）
-            let mut candidate = header_prefix.as_ref().clone();
-            candidate.extend_from_slice(&mv);
-            let digest = blake3_hash(&candidate);
-            let ok = hash_to_biguint(&digest) <= *bits;
-            let tot = attempts.fetch_add(1, Ordering::Relaxed) + 1;
-            if ok {
-                if !found.swap(true, Ordering::SeqCst) {
-                    let elapsed = start.elapsed();
-                    println!(
-                        "FOUND! tid={} nonce={} mv={} digest={} attempts={} time={:?}",
-                        tid,
-                        local_nonce,
-                        hex::encode(&mv),
-                        hex::encode(digest),
-                        tot,
-                        elapsed
-                    );
-                }
-                break;
-            }
-            if tot % 1_000_000 == 0 {
-                let elapsed = start.elapsed();
-                println!(
-                    "attempts={} time={:?} rate={} H/s",
-                    tot,
-                    elapsed,
-                    tot as f64 / elapsed.as_secs_f64()
-                );
-            }
-            local_nonce = local_nonce.wrapping_add(threads as u64);
+    let threads = if threads > 0 { threads } else { affinity::current().threads() };
+    let target = cpu_miner::target_to_bytes(bits);
+    let end = cpu_miner::nonce_space(mv_len);
+    info!("🔢 Enumerating nonces from {} with extranonce {} on {} thread(s)", start_nonce, extranonce, threads);
+
+    let (mut next, mut attempts) = (start_nonce, 0u64);
+    while next < end {
+        let range = cpu_miner::NonceRange {
+            extranonce,
+            start: next,
+            count: BRUTEFORCE_CHUNK,
+        };
+        let outcome = cpu_miner::search_range(&header_prefix, mv_len, &target, range, threads, &found, &|_| true);
+        attempts += outcome.hashes;
+        if let Some(((mv, digest), nonce)) = outcome.solution {
+            println!(
+                "FOUND! nonce={} extranonce={} mv={} digest={} attempts={} time={:?}",
+                nonce,
+                extranonce,
+                hex::encode(&mv),
+                hex::encode(digest),
+                attempts,
+                start.elapsed()
+            );
+            return;
         }
-    });
+        next = outcome.resume;
+        let elapsed = start.elapsed();
+        info!(
+            "attempts={} time={:?} rate={:.0} H/s (resume with --start-nonce {} --extranonce {})",
+            attempts,
+            elapsed,
+            attempts as f64 / elapsed.as_secs_f64(),
+            next,
+            extranonce
+        );
+    }
+    info!("❌ Nonce space of a {}-byte mutation vector exhausted without a solution", mv_len);
+}
+
+/// Extranonce of the CPU brute-force search: `--extranonce` or one derived from the seed
+fn extranonce(args: &Args) -> u64 {
+    args.extranonce
+        .unwrap_or_else(|| seed::derive(args.seed.unwrap_or_default(), &[cpu_miner::EXTRANONCE_STREAM]))
 }
 
 fn run_ga(
@@ -193,187 +540,698 @@ fn run_ga(
     mv_len: usize,
     population: usize,
     generations: usize,
+    ga_config: &ga::GaConfig,
+    seed: u64,
 ) {
-    // 個体群: mutation vector の Vec<Vec<u8>>
-    let mut rng = rand::thread_rng();
-    let mut population_vec: Vec<Vec<u8>> = (0..population)
-        .map(|_| {
-            let mut v = vec![0u8; mv_len];
-            rng.fill_bytes(&mut v);
-            v
-        })
-        .collect();
-
     let start = Instant::now();
-    let target = bits.clone();
-
-    for gen in 0..generations {
-        // 適応度の並列評価
-        let fitness: Vec<f64> = population_vec
-            .par_iter()
-            .map(|mv| {
-                let mut candidate = header_prefix.as_ref().clone();
-                candidate.extend_from_slice(mv);
-                let digest = blake3_hash(&candidate);
-                fitness_from_hash_biguint(&digest, &target)
-            })
-            .collect();
-
-        // 解が存在するか検査
-        for (i, f) in fitness.iter().enumerate() {
-            if *f == 1.0 {
-                let elapsed = start.elapsed();
-                println!(
-                    "FOUND solution generation={} idx={} mv={} time={:?}",
-                    gen,
-                    i,
-                    hex::encode(&population_vec[i]),
-                    elapsed
-                );
-                return;
+    let fitness = ga::TargetFitness::new(&header_prefix, bits);
+    let search = ga::GeneticSearch::new(ga_config);
+    match search.run(&fitness, population, mv_len, generations, &mut seed::rng(seed, &[])) {
+        Some(((mv, _), gen)) => {
+            println!("FOUND solution generation={} mv={} time={:?}", gen, hex::encode(&mv), start.elapsed());
+        }
+        None => info!("GA finished without finding solution after {} generations", generations),
+    }
+}
+
+/// Sizes the global rayon pool of the CPU GA and applies the CPU placement to its threads
+fn init_thread_pool(threads: usize) {
+    let placement = affinity::current();
+    if threads == 0 && !placement.is_pinned() && !placement.changes_priority() {
+        return;
+    }
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(if threads > 0 { threads } else { placement.threads() })
+        .start_handler(|worker| affinity::current().apply(worker))
+        .build_global()
+        .unwrap();
+}
+
+/// CPU placement options of the command line
+fn placement_config(args: &Args) -> affinity::PlacementConfig {
+    affinity::PlacementConfig {
+        cpus: args.cpu_affinity.clone(),
+        numa_nodes: args.cpu_numa_nodes.clone(),
+        no_smt: args.cpu_no_smt,
+        reserve: args.cpu_reserve,
+        priority: args.cpu_priority,
+        nice: args.cpu_nice,
+        sysfs: args.sysfs_root.clone(),
+    }
+}
+
+fn run_evaluate(args: &Args) {
+    let Some(Command::Evaluate { trials, target_bits, strategy, max_hashes, header_len, csv, json }) = &args.command
+    else {
+        return;
+    };
+    init_thread_pool(args.threads);
+    let config = evaluate::EvalConfig {
+        trials: *trials,
+        target_bits: target_bits.clone(),
+        strategies: strategy.clone(),
+        max_hashes: *max_hashes,
+        header_len: *header_len,
+        mv_len: args.mv_len,
+        population: args.population,
+        ga: ga_config(args),
+        csv: csv.clone(),
+        json: json.clone(),
+    };
+    match evaluate::run(&config, &mut seed::rng(args.seed.unwrap_or_default(), &[])) {
+        Ok(report) => print!("{}", report.to_text()),
+        Err(e) => {
+            error!("❌ Evaluation failed: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn ga_config(args: &Args) -> ga::GaConfig {
+    ga::GaConfig {
+        selection: args.ga_selection,
+        tournament_size: args.ga_tournament_size,
+        crossover: args.ga_crossover,
+        mutation: args.ga_mutation,
+        mutation_rate: args.mutation_rate as f64,
+        elitism: args.ga_elitism,
+        islands: ga::IslandConfig {
+            islands: args.ga_islands,
+            migration_interval: args.ga_migration_interval,
+            migrants: args.ga_migrants,
+            topology: args.ga_topology,
+        },
+        adaptive: args.ga_adaptive,
+        stagnation: args.ga_stagnation,
+        adaptive_trace: args.ga_adaptive_trace,
+        telemetry: None,
+    }
+}
+
+/// `ga_config` with the --ga-telemetry file opened, for the single-shot GA runs
+fn ga_config_with_telemetry(args: &Args) -> ga::GaConfig {
+    let Some(path) = &args.ga_telemetry else {
+        return ga_config(args);
+    };
+    match telemetry::TelemetrySink::create(path, args.ga_telemetry_format) {
+        Ok(sink) => {
+            info!("📈 GA telemetry: {} ({:?})", path.display(), args.ga_telemetry_format);
+            ga::GaConfig {
+                telemetry: Some(Arc::new(sink)),
+                ..ga_config(args)
             }
         }
+        Err(e) => {
+            error!("❌ Cannot create GA telemetry file {}: {}", path.display(), e);
+            std::process::exit(1);
+        }
+    }
+}
 
-        // 選択（トーナメント）、交叉（1 点）、突然変異
-        // 新しい個体群の構築
-        let mut new_pop = Vec::with_capacity(population);
-        for _ in 0..population {
-            // トーナメント選択
-            let idx1 = rng.gen_range(0..population);
-            let idx2 = rng.gen_range(0..population);
-            let parent = if fitness[idx1] > fitness[idx2] {
-                &population_vec[idx1]
-            } else {
-                &population_vec[idx2]
-            };
-            let idx3 = rng.gen_range(0..population);
-            let idx4 = rng.gen_range(0..population);
-            let parent2 = if fitness[idx3] > fitness[idx4] {
-                &population_vec[idx3]
-            } else {
-                &population_vec[idx4]
-            };
-
-            // 交叉
-            let mut child = vec![0u8; mv_len];
-            let cross_point = rng.gen_range(0..mv_len);
-            for i in 0..mv_len {
-                child[i] = if i < cross_point {
-                    parent[i]
+/// Mining device used by `mine_loop`
+enum Backend {
+    Gpu(gpu_miner::GpuMiner),
+    Cpu(cpu_miner::CpuMiner),
+}
+
+impl Backend {
+    /// Name and PCI bus number reported in the stats
+    fn describe(&self) -> stats::DeviceStats {
+        match self {
+            Backend::Gpu(miner) => stats::DeviceStats::new(format!("GPU {}", miner.device_id()), miner.bus_number()),
+            Backend::Cpu(_) => stats::DeviceStats::new("CPU".to_string(), None),
+        }
+    }
+
+    /// Applies runtime intensity settings between templates
+    fn apply(&mut self, settings: &control::RuntimeSettings) {
+        match self {
+            Backend::Gpu(miner) => miner.set_population_size(settings.population),
+            Backend::Cpu(miner) => miner.set_threads(settings.threads),
+        }
+    }
+
+    /// Searches one template. Returns the solution (if any) and the number of hashes computed.
+    fn mine(
+        &self,
+        args: &Args,
+        settings: &control::RuntimeSettings,
+        header_prefix: &[u8],
+        target: &num_bigint::BigUint,
+        found: &AtomicBool,
+        control: &control::MinerControl,
+    ) -> (Option<cpu_miner::Solution>, u64) {
+        match self {
+            Backend::Gpu(miner) => {
+                // What the kernels computed, which an interrupted or early-solved round cuts short
+                let before = miner.hashes();
+                let res = if args.gpu_brute {
+                    let max_nonces = (settings.batches as u64).saturating_mul(miner.launch().threads());
+                    miner.mine_bruteforce_nonce_gpu(header_prefix, target, 0, max_nonces, found, Some(control))
                 } else {
-                    parent2[i]
+                    miner.mine_with_ga(header_prefix, target, args.generations, args.mutation_rate, found, Some(control))
                 };
+                (res, miner.hashes() - before)
             }
-            // 突然変異: ランダムなバイトを反転/置換
-            if rng.gen_bool(0.02) {
-                let mpos = rng.gen_range(0..mv_len);
-                child[mpos] = rng.gen();
+            Backend::Cpu(miner) => {
+                let work = (settings.batches as u64).saturating_mul(settings.population as u64);
+                miner.mine_bruteforce(header_prefix, target, work, found, control)
             }
-            new_pop.push(child);
         }
+    }
+}
 
-        population_vec = new_pop;
+/// A device's backend as seen by the worker watchdog. Each round runs on a detached
+/// thread holding the backend's lock, so a hung device can be abandoned and
+/// re-initialized in a fresh backend while its old thread stays blocked.
+struct Worker {
+    backend: Arc<parking_lot::Mutex<Backend>>,
+    heartbeat: Arc<watchdog::Heartbeat>,
+    /// Kept across restarts, so a restarted device stays within its hashrate cap
+    pacer: Arc<throttle::Pacer>,
+    /// CUDA device to re-initialize, `None` for the CPU backend
+    gpu_id: Option<usize>,
+    /// Launch geometry carried over to a re-initialized GPU backend
+    launch: gpu_miner::Launch,
+    health: stats::WorkerHealth,
+    restarts: u64,
+}
 
-        if gen % 10 == 0 {
-            let best_f = fitness.iter().cloned().fold(f64::NAN, f64::max);
-            let elapsed = start.elapsed();
-            println!("gen={} best_f={} time={:?}", gen, best_f, elapsed);
+impl Worker {
+    fn new(mut backend: Backend, pacer: Arc<throttle::Pacer>) -> Self {
+        let heartbeat = Arc::new(watchdog::Heartbeat::default());
+        let (gpu_id, launch) = match &mut backend {
+            Backend::Gpu(miner) => {
+                miner.set_heartbeat(Arc::clone(&heartbeat));
+                miner.set_pacer(Arc::clone(&pacer));
+                (Some(miner.device_id()), miner.launch())
+            }
+            Backend::Cpu(miner) => {
+                miner.set_heartbeat(Arc::clone(&heartbeat));
+                miner.set_pacer(Arc::clone(&pacer));
+                (None, gpu_miner::Launch::default())
+            }
+        };
+        Self {
+            backend: Arc::new(parking_lot::Mutex::new(backend)),
+            heartbeat,
+            pacer,
+            gpu_id,
+            launch,
+            health: stats::WorkerHealth::Healthy,
+            restarts: 0,
         }
     }
-    println!(
-        "GA finished without finding solution after {} generations",
-        generations
+
+    /// Replaces a stalled backend with a freshly initialized one, or drops the device
+    /// once it has stalled `MAX_RESTARTS` times or cannot be re-initialized
+    fn recover(&mut self, args: &Args, settings: &control::RuntimeSettings) -> Result<(), String> {
+        if self.restarts >= watchdog::MAX_RESTARTS {
+            self.health = stats::WorkerHealth::Dropped;
+            return Err(format!("stalled {} times", self.restarts + 1));
+        }
+        let backend = match self.gpu_id {
+            Some(id) => gpu_miner::GpuMiner::new(settings.population, args.mv_len, id)
+                .map(|mut miner| {
+                    miner.set_seed(args.seed.unwrap_or_default());
+                    miner.set_launch(self.launch);
+                    Backend::Gpu(miner)
+                })
+                .map_err(|e| e.to_string()),
+            None => {
+                let mut miner = cpu_miner::CpuMiner::new(settings.threads, args.mv_len);
+                miner.set_extranonce(extranonce(args));
+                Ok(Backend::Cpu(miner))
+            }
+        };
+        match backend {
+            Ok(backend) => {
+                let restarts = self.restarts + 1;
+                *self = Worker::new(backend, Arc::clone(&self.pacer));
+                self.restarts = restarts;
+                // Healthy again once it completes a round
+                self.health = stats::WorkerHealth::Stalled;
+                Ok(())
+            }
+            Err(e) => {
+                self.health = stats::WorkerHealth::Dropped;
+                Err(format!("re-initialization failed: {}", e))
+            }
+        }
+    }
+}
+
+/// Options `mine_loop` can pick up from a reloaded config without a restart
+const LIVE_OPTIONS: &[&str] = &[
+    "node_url",
+    "fallback_node",
+    "miner_address",
+    "threads",
+    "batches",
+    "population",
+    "max_hashrate",
+    "device_max_hashrate",
+    "duty_cycle",
+    "api_token",
+    "api_cors_origin",
+    "control_token",
+    "health_template_age",
+    "health_submit_failures",
+];
+
+fn throttle_settings(args: &Args) -> throttle::ThrottleSettings {
+    throttle::ThrottleSettings {
+        max_hashrate: args.max_hashrate,
+        device_max_hashrate: args.device_max_hashrate,
+        duty_cycle: args.duty_cycle,
+    }
+}
+
+fn thermal_config(args: &Args) -> thermal::ThermalConfig {
+    thermal::ThermalConfig {
+        sysfs: args.sysfs_root.clone(),
+        sensors: args.thermal_sensor.clone(),
+        limit: args.thermal_limit,
+        resume: args.thermal_resume,
+        action: args.thermal_action,
+        interval: std::time::Duration::from_secs(args.thermal_interval),
+    }
+}
+
+fn health_policy(args: &Args) -> stats::HealthPolicy {
+    stats::HealthPolicy {
+        max_template_age: args.health_template_age,
+        max_submit_failures: args.health_submit_failures,
+    }
+}
+
+/// Re-resolves the config and applies live changes. `current` holds the applied
+/// option values, so changes that need a restart are reported on every reload.
+fn reload_config(
+    argv: &[std::ffi::OsString],
+    current: &mut std::collections::BTreeMap<String, Vec<String>>,
+    control: &control::MinerControl,
+    api_config: &parking_lot::RwLock<api::ApiConfig>,
+) {
+    let resolved = config::resolve(Args::command(), argv.to_vec());
+    let mut errors = resolved.errors.clone();
+    let parsed = Args::command()
+        .try_get_matches_from(&resolved.argv)
+        .map_err(|e| e.to_string())
+        .and_then(|m| Args::from_arg_matches(&m).map(|a| (a, m)).map_err(|e| e.to_string()));
+    match &parsed {
+        Ok((args, _)) => errors.extend(validate(args)),
+        Err(e) => errors.push(e.lines().next().unwrap_or_default().to_string()),
+    }
+    let (args, matches) = match parsed {
+        Ok(parsed) if errors.is_empty() => parsed,
+        _ => {
+            for e in &errors {
+                error!("❌ Config reload failed, keeping current settings: {}", e.trim_start_matches("error: "));
+            }
+            return;
+        }
+    };
+
+    let values = config::values(&Args::command(), &matches);
+    let changed: Vec<&String> = values.keys().filter(|id| current.get(*id) != values.get(*id)).collect();
+    let (live, restart): (Vec<&String>, Vec<&String>) =
+        changed.into_iter().partition(|id| LIVE_OPTIONS.contains(&id.as_str()));
+    for id in &restart {
+        warn!("⚠️  `{}` changed in the config; restart the miner to apply it", id);
+    }
+    if live.is_empty() {
+        if restart.is_empty() {
+            info!("🔁 Config reloaded, nothing changed");
+        }
+        return;
+    }
+
+    // Nodes, address, intensity and limits set through the control API or by --auto-tune stay
+    // unless the config changed them
+    let settings = control.settings();
+    let changed = |id: &str| live.iter().any(|live| live.as_str() == id);
+    let node_urls = if changed("node_url") || changed("fallback_node") {
+        std::iter::once(args.node_url.clone()).chain(args.fallback_node.iter().cloned()).collect()
+    } else {
+        settings.node_urls.clone()
+    };
+    let throttle = throttle::ThrottleSettings {
+        max_hashrate: if changed("max_hashrate") { args.max_hashrate } else { settings.throttle.max_hashrate },
+        device_max_hashrate: if changed("device_max_hashrate") {
+            args.device_max_hashrate
+        } else {
+            settings.throttle.device_max_hashrate
+        },
+        duty_cycle: if changed("duty_cycle") { args.duty_cycle } else { settings.throttle.duty_cycle },
+    };
+    control.reload(
+        node_urls,
+        if changed("miner_address") { args.miner_address.clone() } else { settings.miner_address.clone() },
+        match (changed("threads"), args.threads) {
+            (false, _) => settings.threads,
+            (true, 0) => affinity::current().threads(),
+            (true, threads) => threads,
+        },
+        if changed("batches") { args.batches } else { settings.batches },
+        if changed("population") { args.population } else { settings.population },
+        throttle,
     );
+    control.set_token(args.control_token.clone());
+    {
+        let mut api = api_config.write();
+        api.token = args.api_token.clone();
+        api.cors_origins = args.api_cors_origin.clone();
+        api.health = health_policy(&args);
+    }
+    let live: Vec<&str> = live.iter().map(|id| id.as_str()).collect();
+    info!("🔁 Config reloaded; {} take effect at the next template", live.join(", "));
+    for id in live {
+        current.insert(id.to_string(), values[id].clone());
+    }
 }
 
-fn mine_loop(args: &Args) {
+/// Applies a tuned profile to every backend, measuring the ones without a cached profile.
+/// Launch geometry is set per GPU; threads, batches and population are shared, so each
+/// takes the smallest value any device needs to stay within the latency.
+fn auto_tune(args: &Args, backends: &mut [Backend], control: &control::MinerControl) {
+    let latency = std::time::Duration::from_millis(args.tune_latency);
+    let mut cache = tune::Cache::load(&args.tune_cache.clone().unwrap_or_else(tune::default_cache_path));
+    let population = control.settings().population;
+    let mut intensity = control::IntensityRequest {
+        threads: None,
+        batches: None,
+        population: None,
+    };
+    let mut measured = false;
+
+    for backend in backends.iter_mut() {
+        let (workload, device) = match backend {
+            Backend::Gpu(miner) if args.gpu_brute => (tune::Workload::GpuBrute, tune::Device::gpu(miner)),
+            Backend::Gpu(miner) => (tune::Workload::GpuGa, tune::Device::gpu(miner)),
+            Backend::Cpu(_) => (tune::Workload::CpuBrute, tune::Device::cpu()),
+        };
+        let cached = cache.get(workload, &device, latency, args.mv_len).filter(|_| !args.retune).cloned();
+        let profile = match (cached, &mut *backend) {
+            (Some(profile), _) => {
+                info!("🎛️  Using the cached profile of {} from {}", device.description, cache.path().display());
+                profile
+            }
+            (None, backend) => {
+                let tuned = match backend {
+                    Backend::Cpu(_) => Some(tune::cpu(&device, args.mv_len, latency)),
+                    Backend::Gpu(miner) if args.gpu_brute => tune::gpu_bruteforce(miner, &device, args.mv_len, latency),
+                    Backend::Gpu(miner) => tune::gpu_ga(miner, &device, args.mv_len, latency, args.mutation_rate),
+                };
+                let Some(profile) = tuned else {
+                    warn!("⚠️  Could not tune {}, keeping its settings", device.description);
+                    continue;
+                };
+                cache.insert(profile.clone());
+                measured = true;
+                profile
+            }
+        };
+
+        let batches = match backend {
+            Backend::Gpu(miner) => {
+                if let Some(launch) = profile.launch {
+                    miner.set_launch(launch);
+                }
+                profile.launch.map(|launch| profile.batches(launch.threads()))
+            }
+            Backend::Cpu(_) => {
+                intensity.threads = profile.threads;
+                Some(profile.batches(population as u64))
+            }
+        };
+        let min = |current: Option<usize>, tuned: Option<usize>| match (current, tuned) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        intensity.batches = min(intensity.batches, batches);
+        intensity.population = min(intensity.population, profile.population);
+        info!(
+            "🎛️  {}: {:.2} MH/s{}{}{}",
+            device.description,
+            profile.hashrate / 1e6,
+            profile.threads.map_or(String::new(), |t| format!(", {} threads", t)),
+            profile
+                .launch
+                .map_or(String::new(), |l| format!(", {} blocks x {} threads", l.blocks, l.threads_per_block)),
+            profile.population.map_or(String::new(), |p| format!(", population {}", p))
+        );
+    }
+
+    if measured {
+        match cache.save() {
+            Ok(()) => info!("🎛️  Tuned profiles saved to {}", cache.path().display()),
+            Err(e) => warn!("⚠️  Cannot save tuned profiles to {}: {}", cache.path().display(), e),
+        }
+    }
+    if let Err(e) = control.set_intensity(intensity) {
+        warn!("⚠️  Tuned settings rejected: {}", e);
+    }
+}
+
+fn mine_loop(args: &Args, resolved: &config::Resolved) {
+    use control::{MinerControl, RuntimeSettings};
+    use events::{EventBus, MinerEvent};
     use node_client::NodeClient;
-    
-    let mut client = NodeClient::new(args.node_url.clone());
-    
+    use api::{start_api_server, ApiConfig};
+    use stats::StatsTracker;
+
+    let mut node_urls = vec![args.node_url.clone()];
+    node_urls.extend(args.fallback_node.iter().cloned());
+    let control = MinerControl::new(
+        RuntimeSettings {
+            node_urls,
+            active_node: 0,
+            miner_address: args.miner_address.clone(),
+            threads: if args.threads > 0 { args.threads } else { affinity::current().threads() },
+            batches: args.batches,
+            population: args.population,
+            throttle: throttle_settings(args),
+        },
+        args.control_token.clone(),
+    );
+    if let Err(e) = control.handle_signals(std::time::Duration::from_secs(args.shutdown_timeout)) {
+        warn!("⚠️  Cannot install signal handlers: {}", e);
+    }
+
     // Set miner address if provided
     if let Some(addr) = &args.miner_address {
-        println!("💰 Mining rewards will go to: {}", addr);
-        client = client.with_miner_address(addr.clone());
+        info!("💰 Mining rewards will go to: {}", addr);
     }
-    
-    #[cfg(feature = "cuda")]
-    let gpu_miners = if args.gpu {
-        use stats::{StatsTracker, start_api_server};
-        use std::sync::Arc;
+
+    let mut backends: Vec<Backend> = Vec::new();
+    if args.gpu {
         // Auto-detect number of GPUs or use single GPU
-        let miners = if args.gpu_id == 0 && std::env::var("MULTI_GPU").is_ok() {
+        if args.gpu_id == 0 && std::env::var("MULTI_GPU").is_ok() {
             // Try to detect all GPUs
-            (0..8).filter_map(|id| {
-                gpu_miner::GpuMiner::new(args.population, args.mv_len, id).ok()
-            }).collect()
+            backends.extend((0..8).filter_map(|id| {
+                gpu_miner::GpuMiner::new(args.population, args.mv_len, id).ok().map(Backend::Gpu)
+            }));
         } else {
             // Use specified GPU only
             match gpu_miner::GpuMiner::new(args.population, args.mv_len, args.gpu_id) {
-                Ok(miner) => vec![miner],
-                Err(e) => {
-                    eprintln!("❌ Failed to initialize GPU {}: {}", args.gpu_id, e);
-                    vec![]
-                }
+                Ok(miner) => backends.push(Backend::Gpu(miner)),
+                Err(e) => error!("❌ Failed to initialize GPU {}: {}", args.gpu_id, e),
             }
-        };
-        
-        if miners.is_empty() {
-            eprintln!("❌ No GPUs available");
+        }
+
+        if backends.is_empty() {
+            error!("❌ No GPUs available");
             return;
         }
-        
-        // Initialize stats tracker
-        let stats_tracker = StatsTracker::new(miners.len());
-        let stats_handle = stats_tracker.get_stats();
-        
-        // Start API server
-        start_api_server(Arc::clone(&stats_handle), args.api_port);
-        
-        println!("🔄 Starting continuous mining loop");
-        println!("   Node: {}", args.node_url);
-        println!("   GPUs: {} device(s)", miners.len());
-        println!("   GPU Brute-force: {}", args.gpu_brute);
-        println!("   Batches: {}", args.batches);
-        println!("   Population per GPU: {}", args.population);
-        println!("   API Port: {}", args.api_port);
-        println!("");
-        
-        (miners, stats_tracker)
     } else {
-        println!("❌ --gpu flag required for mining loop");
-        return;
-    };
-    
-    #[cfg(not(feature = "cuda"))]
-    {
-        eprintln!("❌ CUDA support not compiled");
-        return;
+        backends.push(Backend::Cpu(cpu_miner::CpuMiner::new(args.threads, args.mv_len)));
     }
-    
-    #[cfg(feature = "cuda")]
+    for backend in &mut backends {
+        match backend {
+            Backend::Gpu(miner) => miner.set_seed(args.seed.unwrap_or_default()),
+            Backend::Cpu(miner) => miner.set_extranonce(extranonce(args)),
+        }
+    }
+    if args.auto_tune {
+        auto_tune(args, &mut backends, &control);
+    }
+
+    // Initialize stats tracker and event stream
+    let stats_tracker = StatsTracker::new(backends.iter().map(Backend::describe).collect());
+    // Only the CPU backend heats the CPU
+    if args.gpu && args.thermal_limit > 0.0 {
+        warn!("⚠️  --thermal-limit only backs off the CPU backend; ignored with --gpu");
+    }
+    let thermal = if args.gpu { None } else { thermal::Monitor::new(thermal_config(args)) };
+    if let Some(monitor) = &thermal {
+        monitor.start(stats_tracker.get_stats());
+    }
+    let throttle = throttle::Throttle::new(Arc::clone(&control));
+    let mut workers: Vec<Worker> = backends
+        .into_iter()
+        .map(|backend| Worker::new(backend, throttle::Pacer::new(&throttle, thermal.clone())))
+        .collect();
+    let worker_timeout = (args.worker_timeout > 0).then(|| std::time::Duration::from_secs(args.worker_timeout));
+    let shared_args = Arc::new(args.clone());
+    let events = EventBus::new();
+    events.start_hashrate_sampler(
+        stats_tracker.get_stats(),
+        std::time::Duration::from_secs(args.hashrate_event_interval.max(1)),
+    );
+
+    // Start API server
+    let api_config = Arc::new(parking_lot::RwLock::new(ApiConfig {
+        binds: if args.api_bind.is_empty() {
+            vec![format!("127.0.0.1:{}", args.api_port)]
+        } else {
+            args.api_bind.clone()
+        },
+        token: args.api_token.clone(),
+        tls_cert: args.api_tls_cert.clone(),
+        tls_key: args.api_tls_key.clone(),
+        cors_origins: args.api_cors_origin.clone(),
+        health: health_policy(args),
+    }));
+    start_api_server(stats_tracker.get_stats(), Arc::clone(&control), Arc::clone(&events), Arc::clone(&api_config));
+    // Monitoring-tool compatibility listeners share the API token (Claymore passes it as `psw`)
+    if let Some(addr) = &args.claymore_bind {
+        compat::start_claymore_server(stats_tracker.get_stats(), addr, Arc::clone(&api_config));
+    }
+    if let Some(addr) = &args.xmrig_bind {
+        compat::start_xmrig_server(stats_tracker.get_stats(), addr, Arc::clone(&api_config));
+    }
+
+    systemd::start_status_reporter(stats_tracker.get_stats());
+
+    // Re-read the config on SIGHUP or when the file changes
     {
-    let (gpu_miners, stats_tracker) = gpu_miners;
-    
-    loop {
+        let (control, api_config) = (Arc::clone(&control), Arc::clone(&api_config));
+        let argv: Vec<std::ffi::OsString> = std::env::args_os().collect();
+        let mut watcher = config::Watcher::new(resolved.file.clone());
+        let mut current = config::values(&Args::command(), &Args::command().get_matches_from(&resolved.argv));
+        std::thread::spawn(move || loop {
+            std::thread::sleep(std::time::Duration::from_secs(1));
+            if watcher.changed() {
+                reload_config(&argv, &mut current, &control, &api_config);
+            }
+        });
+    }
+
+    info!("🔄 Starting continuous mining loop");
+    info!("   Node: {}", args.node_url);
+    if args.gpu {
+        info!("   GPUs: {} device(s)", workers.len());
+        info!("   GPU Brute-force: {}", args.gpu_brute);
+        info!("   Batches: {}", control.settings().batches);
+        info!("   Population per GPU: {}", control.settings().population);
+    } else {
+        info!("   CPU threads: {}", control.settings().threads);
+    }
+    if control.throttle().is_limited() {
+        info!("   Throttle: {}", control.throttle());
+    }
+    if let Some(monitor) = &thermal {
+        info!("   Temperature: {}", monitor);
+    }
+    info!("   API: {}", api_config.read().binds.join(", "));
+    if control.enabled() {
+        info!("   Control API: enabled");
+    }
+
+    let mut revision = u64::MAX;
+    let mut settings = control.settings();
+    let mut client = NodeClient::new(settings.active_node_url().to_string());
+    let mut ready = false;
+    let mut failed = false;
+
+    while !control.is_shutting_down() {
+        if control.is_paused() {
+            info!("⏸️  Mining paused");
+            stats_tracker.set_paused(true);
+            while control.is_paused() && !control.is_shutting_down() {
+                std::thread::sleep(std::time::Duration::from_millis(200));
+            }
+            stats_tracker.set_paused(false);
+            info!("▶️  Mining resumed");
+        }
+
+        // Fetching a new template below satisfies any pending refresh request
+        control.take_refresh();
+
+        // Apply settings changed through the control API at the template boundary
+        if control.revision() != revision {
+            revision = control.revision();
+            settings = control.settings();
+            client = NodeClient::new(settings.active_node_url().to_string());
+            if let Some(addr) = &settings.miner_address {
+                client = client.with_miner_address(addr.clone());
+            }
+            // Dropped workers may still be locked by their hung thread
+            for worker in workers.iter().filter(|w| w.health != stats::WorkerHealth::Dropped) {
+                worker.backend.lock().apply(&settings);
+            }
+            if revision > 0 {
+                info!("⚙️  Settings updated");
+                info!("   Node: {}", settings.active_node_url());
+                info!("   Miner address: {}", settings.miner_address.as_deref().unwrap_or("(node default)"));
+                info!(
+                    "   Threads: {}  Batches: {}  Population: {}",
+                    settings.threads, settings.batches, settings.population
+                );
+                info!("   Throttle: {}", settings.throttle);
+            }
+        }
+
         // Fetch template from node
+        let fetch_start = Instant::now();
         let template = match client.get_template() {
-            Ok(t) => t,
+            Ok(t) => {
+                stats_tracker.record_node(settings.active_node_url(), Ok(fetch_start.elapsed()));
+                t
+            }
             Err(e) => {
-                eprintln!("❌ Failed to fetch template: {}", e);
-                std::thread::sleep(std::time::Duration::from_secs(5));
+                error!("❌ Failed to fetch template: {}", e);
+                stats_tracker.record_node(settings.active_node_url(), Err(e.to_string()));
+                if let Some(next) = control.fail_over() {
+                    warn!("🔀 Failing over to node {}", next);
+                    events.publish(MinerEvent::NodeFailover {
+                        from: settings.active_node_url().to_string(),
+                        to: next,
+                        error: e.to_string(),
+                    });
+                    control.sleep(std::time::Duration::from_secs(1));
+                } else {
+                    control.sleep(std::time::Duration::from_secs(5));
+                }
                 continue;
             }
         };
-        
-        println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-        println!("📋 Template received");
-        println!("   Height: {}", template.height);
-        println!("   Difficulty: 0x{}", template.difficulty_bits);
-        println!("   Target: {}...", &template.target_hex[..16]);
-        
-        stats_tracker.update_height(template.height);
-        
+
+        // Everything logged until the next template carries its correlation ID
+        let _template_ctx = logging::scope("template", logging::next_id("tpl"));
+        info!("📋 Template received");
+        info!("   Height: {}", template.height);
+        info!("   Difficulty: 0x{}", template.difficulty_bits);
+        info!("   Target: {}...", &template.target_hex[..16.min(template.target_hex.len())]);
+        info!("   Reward address: {}", template.miner_address);
+
+        stats_tracker.set_template(template.height, &template.difficulty_bits, &template.target_hex);
+        // Backends are initialized and the node answers: tell systemd the service is up
+        let status = systemd::status_line(&stats_tracker.get_stats().read());
+        if !ready {
+            ready = true;
+            systemd::notify(&format!("READY=1\nSTATUS={}", status));
+        } else {
+            systemd::notify(&format!("STATUS={}", status));
+        }
+        events.publish(MinerEvent::NewTemplate {
+            height: template.height,
+            difficulty_bits: template.difficulty_bits.clone(),
+            target_hex: template.target_hex.clone(),
+            node: settings.active_node_url().to_string(),
+        });
+
         // Parse header and target
         let header_prefix = hex_to_bytes(&template.header_prefix_hex);
         let target = if !template.target_hex.is_empty() && template.target_hex != "null" {
@@ -388,134 +1246,439 @@ fn mine_loop(args: &Args) {
             let bits_u32 = parse_bits_hex(&template.difficulty_bits);
             compact_bits_to_target(bits_u32)
         };
-        
-        println!("⛏️  Mining block {} on {} GPU(s)...", template.height, gpu_miners.len());
-        let start = Instant::now();
-        let block_start = start.clone();
-        
+
+        let active = workers.iter().filter(|w| w.health != stats::WorkerHealth::Dropped).count();
+        info!("⛏️  Mining block {} on {} device(s)...", template.height, active);
+        let block_start = Instant::now();
+
         stats_tracker.set_mining(true);
-        
-        // Mine with all GPUs in parallel using threads
-        let result = {
-            use std::sync::{Arc, Mutex};
-            use std::sync::atomic::{AtomicBool, Ordering};
-            let found = Arc::new(AtomicBool::new(false));
-            let solution = Arc::new(Mutex::new(None));
-            let header_arc = Arc::new(header_prefix.clone());
-            let target_arc = Arc::new(target.clone());
-            
-            std::thread::scope(|s| {
-                for (gpu_id, miner) in gpu_miners.iter().enumerate() {
-                    let found = Arc::clone(&found);
-                    let solution = Arc::clone(&solution);
-                    let header = Arc::clone(&header_arc);
-                    let target = Arc::clone(&target_arc);
-                    
-                    s.spawn(move || {
-                        let res = if args.gpu_brute {
-                            let max_nonces = args.batches as u64 * 256 * 1024; // batches * threads_per_block * num_blocks
-                            miner.mine_bruteforce_nonce_gpu(&header, &target, 0, max_nonces)
-                        } else {
-                            miner.mine_with_ga(&header, &target, args.generations, args.mutation_rate)
-                        };
-                        
-                        if let Some((mv, hash)) = res {
-                            if !found.swap(true, Ordering::SeqCst) {
-                                // First GPU to find solution
-                                *solution.lock().unwrap() = Some((mv, hash, gpu_id));
-                            }
-                        }
-                    });
+
+        // Mine with all devices in parallel. Rounds run on detached threads rather than a
+        // scope so that a hung device cannot block the loop: the watchdog abandons it instead.
+        let found = Arc::new(AtomicBool::new(false));
+        let (header_prefix, target) = (Arc::new(header_prefix), Arc::new(target));
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut pending = Vec::new();
+
+        for (device_id, worker) in workers.iter().enumerate() {
+            if worker.health == stats::WorkerHealth::Dropped {
+                continue;
+            }
+            pending.push(device_id);
+            let (backend, heartbeat, tx) = (Arc::clone(&worker.backend), Arc::clone(&worker.heartbeat), tx.clone());
+            let (args, settings, control, events) =
+                (Arc::clone(&shared_args), settings.clone(), Arc::clone(&control), Arc::clone(&events));
+            let (header_prefix, target, found) = (Arc::clone(&header_prefix), Arc::clone(&target), Arc::clone(&found));
+            let log_ctx = logging::context();
+
+            std::thread::spawn(move || {
+                let _log_ctx = logging::enter(log_ctx);
+                let _job_ctx = logging::scope("job", logging::next_id("job"));
+                let device_start = Instant::now();
+                heartbeat.start();
+                // A failing device must not take the whole loop down
+                let mined = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                    backend.lock().mine(&args, &settings, &header_prefix, &target, &found, &control)
+                }));
+                heartbeat.finish();
+                let mined = match mined {
+                    Ok(r) => r,
+                    Err(panic) => {
+                        let error = panic
+                            .downcast_ref::<&str>()
+                            .map(|s| s.to_string())
+                            .or_else(|| panic.downcast_ref::<String>().cloned())
+                            .unwrap_or_else(|| "worker panicked".to_string());
+                        error!("❌ Device {} failed: {}", device_id, error);
+                        events.publish(MinerEvent::DeviceError { device: device_id, error });
+                        (None, 0)
+                    }
+                };
+                if mined.0.is_some() {
+                    found.store(true, Ordering::SeqCst);
                 }
+                // The loop may have given up on this device already
+                let _ = tx.send((device_id, mined, device_start.elapsed()));
             });
-            
-            // Extract solution after all threads complete
-            Arc::try_unwrap(solution)
-                .ok()
-                .and_then(|mutex| mutex.into_inner().ok())
-                .flatten()
-        };
-        
-        
-        match result {
-            Some((mv, hash, gpu_id)) => {
-                let elapsed = block_start.elapsed();
-                let total_hashes = (args.batches * args.population) as u64;
-                let hashrate = total_hashes as f64 / elapsed.as_secs_f64();
-                println!("✅ SOLUTION FOUND by GPU {} in {:.2}s!", gpu_id, elapsed.as_secs_f64());
-                println!("   Hashrate: {:.2} MH/s", hashrate / 1_000_000.0);
-                println!("   MV: {}", hex::encode(&mv));
-                println!("   Hash: {}", hex::encode(&hash));
-                
-                stats_tracker.update_hashrate(total_hashes, elapsed, Some(gpu_id));
-                
+        }
+        drop(tx);
+
+        let mut solution = None;
+        let mut round_hashes = 0u64;
+        while !pending.is_empty() {
+            match rx.recv_timeout(std::time::Duration::from_secs(1)) {
+                Ok((device_id, (res, hashes), device_elapsed)) => {
+                    if !pending.contains(&device_id) {
+                        // Finished after being abandoned; its backend was already replaced
+                        continue;
+                    }
+                    pending.retain(|&d| d != device_id);
+                    stats_tracker.update_hashrate(hashes, device_elapsed, Some(device_id));
+                    round_hashes += hashes;
+                    let worker = &mut workers[device_id];
+                    if worker.health == stats::WorkerHealth::Stalled {
+                        worker.health = stats::WorkerHealth::Healthy;
+                        info!("✅ Device {} is mining again after a restart", device_id);
+                    }
+                    // First device to find a solution wins
+                    if let (Some((mv, hash)), None) = (res, &solution) {
+                        solution = Some((mv, hash, device_id));
+                    }
+                }
+                Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {}
+                Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => break,
+            }
+
+            // Waiting in a pause is not a stall
+            if control.is_paused() {
+                workers.iter().for_each(|w| w.heartbeat.hold());
+            }
+            for device_id in pending.clone() {
+                let worker = &mut workers[device_id];
+                let Some(stalled) = worker.heartbeat.stalled(worker_timeout) else {
+                    continue;
+                };
+                pending.retain(|&d| d != device_id);
+                warn!("🐕 Device {} computed no hashes for {:.0}s, abandoning its worker", device_id, stalled.as_secs_f64());
+                let error = match worker.recover(args, &settings) {
+                    Ok(()) => {
+                        warn!("🔁 Device {} restarted ({}/{})", device_id, worker.restarts, watchdog::MAX_RESTARTS);
+                        format!("stalled for {:.0}s, restarted", stalled.as_secs_f64())
+                    }
+                    Err(e) => {
+                        error!("❌ Device {} dropped: {}", device_id, e);
+                        format!("stalled for {:.0}s, dropped: {}", stalled.as_secs_f64(), e)
+                    }
+                };
+                events.publish(MinerEvent::DeviceError { device: device_id, error });
+            }
+            for (device_id, worker) in workers.iter().enumerate() {
+                stats_tracker.set_worker(device_id, worker.health, worker.restarts, &worker.heartbeat);
+            }
+        }
+
+        let elapsed = block_start.elapsed();
+        let hashrate = round_hashes as f64 / elapsed.as_secs_f64();
+
+        match solution {
+            Some((mv, hash, device_id)) if !verify_solution(&header_prefix, &mv, &target) => {
+                let _solution_ctx = logging::scope("solution", logging::next_id("sol"));
+                warn!("⚠️  Device {} reported an invalid solution (MV {}), not submitting", device_id, hex::encode(&mv));
+                warn!("   Reported hash: {}", hex::encode(hash));
+                stats_tracker.record_invalid(device_id);
+            }
+            Some((mv, hash, device_id)) => {
+                let _solution_ctx = logging::scope("solution", logging::next_id("sol"));
+                info!("✅ SOLUTION FOUND by device {} in {:.2}s!", device_id, elapsed.as_secs_f64());
+                info!("   Hashrate: {:.2} MH/s", hashrate / 1_000_000.0);
+                info!("   MV: {}", hex::encode(&mv));
+                info!("   Hash: {}", hex::encode(hash));
+                events.publish(MinerEvent::SolutionFound {
+                    height: template.height,
+                    device: device_id,
+                    mutation_vector: hex::encode(&mv),
+                    hash: hex::encode(hash),
+                });
+
                 // Submit to node
-                println!("📤 Submitting solution...");
-                match client.submit_solution(template.height, &hex::encode(&mv), template.timestamp) {
+                info!("📤 Submitting solution...");
+                let submitted = {
+                    // Bounded by its own timeout, so a shutdown never cuts it off mid-request
+                    let timeout = control.submit_timeout();
+                    let _submission = control.submitting(timeout);
+                    client.submit_solution(template.height, &hex::encode(&mv), template.timestamp, timeout)
+                };
+                match submitted {
                     Ok(response) => {
                         if response.success {
-                            println!("🎉 BLOCK ACCEPTED!");
-                            println!("   Message: {}", response.message);
+                            info!("🎉 BLOCK ACCEPTED!");
+                            info!("   Message: {}", response.message);
                             if let Some(h) = response.hash {
-                                println!("   Hash: {}...", &h[..64]);
+                                info!("   Hash: {}...", &h[..64.min(h.len())]);
                             }
-                            stats_tracker.increment_accepted();
+                            stats_tracker.record_block(template.height, device_id, &hash, true, &response.message);
                         } else {
-                            println!("❌ Solution rejected: {}", response.message);
-                            stats_tracker.increment_rejected();
+                            warn!("❌ Solution rejected: {}", response.message);
+                            stats_tracker.record_block(template.height, device_id, &hash, false, &response.message);
                         }
+                        events.publish(MinerEvent::SubmitResult {
+                            height: template.height,
+                            device: device_id,
+                            accepted: response.success,
+                            message: response.message,
+                        });
                     }
                     Err(e) => {
-                        eprintln!("❌ Failed to submit: {}", e);
+                        error!("❌ Failed to submit: {}", e);
+                        stats_tracker.record_submit_error();
+                        stats_tracker.record_node(settings.active_node_url(), Err(e.to_string()));
+                        events.publish(MinerEvent::SubmitResult {
+                            height: template.height,
+                            device: device_id,
+                            accepted: false,
+                            message: format!("submit failed: {}", e),
+                        });
                     }
                 }
-                println!("");
             }
             None => {
-                let elapsed = block_start.elapsed();
-                let total_hashes = (args.batches * args.population * gpu_miners.len()) as u64;
-                let hashrate = total_hashes as f64 / elapsed.as_secs_f64();
-                println!("⏭️  No solution found in {:.2}s", elapsed.as_secs_f64());
-                println!("   Total hashrate: {:.2} MH/s ({} GPUs)", hashrate / 1_000_000.0, gpu_miners.len());
-                println!("   Per-GPU: {:.2} MH/s", hashrate / 1_000_000.0 / gpu_miners.len() as f64);
-                println!("");
-                
-                let per_gpu_hashes = (args.batches * args.population) as u64;
-                for gpu_id in 0..gpu_miners.len() {
-                    stats_tracker.update_hashrate(per_gpu_hashes, elapsed, Some(gpu_id));
+                if control.is_shutting_down() {
+                    info!("🛑 Mining stopped after {:.2}s", elapsed.as_secs_f64());
+                } else if control.refresh_requested() {
+                    info!("🔄 Template refresh requested after {:.2}s", elapsed.as_secs_f64());
+                    events.publish(MinerEvent::StaleAbort {
+                        height: template.height,
+                        reason: "template refresh requested".to_string(),
+                    });
+                } else {
+                    info!("⏭️  No solution found in {:.2}s", elapsed.as_secs_f64());
                 }
+                info!("   Total hashrate: {:.2} MH/s ({} devices)", hashrate / 1_000_000.0, active);
+                info!("   Per-device: {:.2} MH/s", hashrate / 1_000_000.0 / active as f64);
             }
         }
-        
+
         stats_tracker.set_mining(false);
-        
+
+        if workers.iter().all(|w| w.health == stats::WorkerHealth::Dropped) {
+            error!("❌ The worker watchdog dropped every device, stopping");
+            failed = true;
+            break;
+        }
+
         // Small delay before next iteration
-        std::thread::sleep(std::time::Duration::from_millis(500));
+        if !control.refresh_requested() {
+            control.sleep(std::time::Duration::from_millis(500));
+        }
+    }
+
+    // Graceful shutdown: devices are released as the backends drop
+    systemd::notify("STOPPING=1\nSTATUS=Shutting down");
+    drop(workers);
+    let stats = stats_tracker.get_stats().read().clone();
+    info!(
+        "👋 Miner stopped after {}s: {} hashes, {} accepted, {} rejected, {} invalid",
+        stats.uptime_secs, stats.total_hashes, stats.accepted_shares, stats.rejected_shares, stats.invalid_shares
+    );
+    for bind in &api_config.read().binds {
+        if let Some(path) = bind.strip_prefix("unix:") {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+    log::logger().flush();
+    if failed {
+        std::process::exit(1);
     }
-    } // end cfg(feature = "cuda")
+}
+
+fn hive_stats(api_url: &str, token: Option<&str>, gpu_stats: Option<&std::path::Path>, shell: bool) {
+    let fetch = || -> Result<hive::HiveReport, Box<dyn std::error::Error>> {
+        let client = reqwest::blocking::Client::builder()
+            .timeout(std::time::Duration::from_secs(2))
+            .build()?;
+        let mut request = client.get(format!("{}/stats", api_url.trim_end_matches('/')));
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        let stats: stats::MinerStats = request.send()?.error_for_status()?.json()?;
+        Ok(hive::HiveReport::from_stats(&stats))
+    };
+
+    match fetch() {
+        Ok(mut report) => {
+            if let Some(path) = gpu_stats {
+                match std::fs::read_to_string(path).map(|s| serde_json::from_str(&s)) {
+                    Ok(Ok(json)) => report.merge_gpu_stats(&json),
+                    _ => warn!("⚠️  Could not read GPU stats from {}", path.display()),
+                }
+            }
+            if shell {
+                print!("{}", report.to_shell());
+            } else {
+                println!("{}", serde_json::to_string(&report).unwrap_or_default());
+            }
+        }
+        Err(e) => {
+            // HiveOS treats a zero hashrate and null stats as "miner offline"
+            error!("❌ Miner API unavailable: {}", e);
+            if shell {
+                println!("khs=0\nstats=null");
+            } else {
+                println!(r#"{{"khs":0,"stats":null}}"#);
+            }
+        }
+    }
+}
+
+/// Checks beyond what the argument parser enforces
+fn validate(args: &Args) -> Vec<String> {
+    let mut errors = Vec::new();
+    for url in std::iter::once(&args.node_url).chain(&args.fallback_node) {
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            errors.push(format!("node url `{}` must start with http:// or https://", url));
+        }
+    }
+    if args.mv_len == 0 || args.population == 0 || args.batches == 0 {
+        errors.push("mv_len, population and batches must be greater than 0".to_string());
+    }
+    if !(0.0..=1.0).contains(&args.mutation_rate) {
+        errors.push(format!("mutation_rate {} is outside 0.0-1.0", args.mutation_rate));
+    }
+    if let Err(e) = throttle_settings(args).validate() {
+        errors.push(e);
+    }
+    if let Err(e) = thermal_config(args).validate() {
+        errors.push(e);
+    }
+    if args.tune_latency == 0 {
+        errors.push("tune_latency must be greater than 0".to_string());
+    }
+    if args.ga_tournament_size == 0 {
+        errors.push("ga_tournament_size must be greater than 0".to_string());
+    }
+    if args.ga_elitism >= args.population {
+        errors.push(format!("ga_elitism {} must be below population {}", args.ga_elitism, args.population));
+    }
+    if args.ga_islands * 2 > args.population {
+        errors.push(format!(
+            "ga_islands {} needs a population of at least {} (2 per island)",
+            args.ga_islands,
+            args.ga_islands * 2
+        ));
+    }
+    if let Err(e) = affinity::Placement::resolve(&placement_config(args)) {
+        errors.push(format!("CPU placement: {}", e));
+    }
+    if let Err(e) = logging::check_filter(&args.log_level) {
+        errors.push(format!("log_level: {}", e));
+    }
+    for path in args.api_tls_cert.iter().chain(&args.api_tls_key) {
+        if !path.exists() {
+            errors.push(format!("TLS file {} does not exist", path.display()));
+        }
+    }
+    errors
+}
+
+fn config_check(resolved: &config::Resolved) -> bool {
+    let mut errors = resolved.errors.clone();
+    match Args::command().try_get_matches_from(&resolved.argv) {
+        Ok(matches) => {
+            match &resolved.file {
+                Some(path) => println!("Config file: {}", path.display()),
+                None => println!("Config file: (none)"),
+            }
+            if !resolved.profiles.is_empty() {
+                println!("Profiles: {}", resolved.profiles.join(", "));
+            }
+            println!();
+            for (id, value, origin) in config::describe(&Args::command(), &matches, resolved) {
+                println!("  {:<24} = {:<40} [{}]", id, value, origin);
+            }
+            println!();
+            match Args::from_arg_matches(&matches) {
+                Ok(args) => errors.extend(validate(&args)),
+                Err(e) => errors.push(e.to_string()),
+            }
+        }
+        Err(e) => errors.push(e.to_string().lines().next().unwrap_or_default().to_string()),
+    }
+
+    if errors.is_empty() {
+        println!("✅ Configuration is valid");
+        return true;
+    }
+    println!("❌ {} error(s):", errors.len());
+    for e in &errors {
+        println!("   - {}", e.trim_start_matches("error: "));
+    }
+    false
 }
 
 fn main() {
-    let args = Args::parse();
+    let resolved = config::resolve(Args::command(), std::env::args_os().collect());
+    if resolved.cli.subcommand_name() == Some("config") {
+        std::process::exit(if config_check(&resolved) { 0 } else { 1 });
+    }
+    if !resolved.errors.is_empty() {
+        for e in &resolved.errors {
+            eprintln!("Error: {}", e);
+        }
+        std::process::exit(1);
+    }
+    let mut args = Args::from_arg_matches(&Args::command().get_matches_from(&resolved.argv)).unwrap_or_else(|e| e.exit());
+
+    let log_config = logging::LogConfig {
+        filter: args.log_level.clone(),
+        format: args.log_format,
+        file: args.log_file.clone(),
+        rotate_size: args.log_rotate_size,
+        rotate_interval: args.log_rotate_interval,
+        keep: args.log_keep,
+        syslog: args.log_syslog,
+        journald: args.log_journald,
+    };
+    if let Err(e) = logging::init(&log_config) {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+
+    if let Some(Command::HiveStats { api_url, token, gpu_stats, shell }) = &args.command {
+        hive_stats(api_url, token.as_deref(), gpu_stats.as_deref(), *shell);
+        return;
+    }
+
+    match affinity::Placement::resolve(&placement_config(&args)) {
+        Ok(placement) => {
+            if placement.is_pinned() || placement.changes_priority() {
+                info!("📌 CPU mining threads: {}", placement);
+            }
+            affinity::init(placement);
+        }
+        Err(e) => {
+            error!("❌ CPU placement: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    // Every search RNG derives from this, so logging it makes any run replayable
+    let seed = *args.seed.get_or_insert_with(rand::random);
+    if args.ga_islands == 0 {
+        // One island per thread, fixed here so the replay line also covers the island count
+        args.ga_islands = if args.threads > 0 { args.threads } else { affinity::current().threads() };
+    }
+    info!("🎲 RNG seed {} (replay with --seed {} --ga-islands {})", seed, seed, args.ga_islands);
+
+    if let Some(Command::Evaluate { .. }) = &args.command {
+        run_evaluate(&args);
+        return;
+    }
     
+    if args.auto_tune && !args.mine_loop {
+        warn!("⚠️  --auto-tune only applies to --mine-loop");
+    }
+    if let Err(e) = throttle_settings(&args).validate() {
+        error!("❌ {}", e);
+        std::process::exit(1);
+    }
+    if let Err(e) = thermal_config(&args).validate() {
+        error!("❌ {}", e);
+        std::process::exit(1);
+    }
+
     // Check if loop mining mode
     if args.mine_loop {
-        mine_loop(&args);
+        mine_loop(&args, &resolved);
         return;
     }
     
     // Validate required arguments for single-shot mode
     if args.header_hex.is_none() || args.bits_hex.is_none() {
-        eprintln!("Error: --header-hex and --bits-hex are required when not using --mine-loop");
+        error!("--header-hex and --bits-hex are required when not using --mine-loop");
         std::process::exit(1);
     }
     
-    if args.threads > 0 {
-        rayon::ThreadPoolBuilder::new()
-            .num_threads(args.threads)
-            .build_global()
-            .unwrap();
-    }
+    init_thread_pool(args.threads);
 
     // 入力の解析
     let header_bytes_all = hex_to_bytes(args.header_hex.as_ref().unwrap());
@@ -534,12 +1697,12 @@ fn main() {
             let start = tbytes.len() - 32;
             tbytes = tbytes[start..].to_vec();
         }
-        println!("Using target_hex from template");
+        info!("Using target_hex from template");
         num_bigint::BigUint::from_bytes_be(&tbytes)
     } else {
         let bits_u32 = parse_bits_hex(args.bits_hex.as_ref().unwrap());
         let t = compact_bits_to_target(bits_u32);
-        println!("Using bits_hex for target");
+        info!("Using bits_hex for target");
         t
     };
     {
@@ -554,35 +1717,38 @@ fn main() {
             t_bytes = t_bytes[start..].to_vec();
         }
         let hex_str = hex::encode(&t_bytes);
-        println!("Computed target (hex, big-endian): {}", hex_str);
+        info!("Computed target (hex, big-endian): {}", hex_str);
     }
 
     if args.gpu {
         #[cfg(feature = "cuda")]
         {
-            println!("🚀 GPU (CUDA) mode enabled");
-            println!("   Population: {}", args.population);
-            println!("   Generations: {}", args.generations);
-            println!("   Mutation rate: {}", args.mutation_rate);
-            println!("   MV length: {}", args.mv_len);
-            println!(
+            info!("🚀 GPU (CUDA) mode enabled");
+            info!("   Population: {}", args.population);
+            info!("   Generations: {}", args.generations);
+            info!("   Mutation rate: {}", args.mutation_rate);
+            info!("   MV length: {}", args.mv_len);
+            info!(
                 "   Mode: {}",
                 if args.gpu_brute { "brute-force" } else { "GA" }
             );
 
             match gpu_miner::GpuMiner::new(args.population, args.mv_len, args.gpu_id) {
-                Ok(miner) => {
+                Ok(mut miner) => {
+                    miner.set_seed(seed);
                     let start = Instant::now();
                     let res = if args.gpu_brute {
                         // Use batches to calculate max_nonces (batches * threads)
-                        let max_nonces = args.batches as u64 * 256 * 1024; // batches * threads_per_block * num_blocks
-                        miner.mine_bruteforce_nonce_gpu(&header_prefix, &target, 0, max_nonces)
+                        let max_nonces = (args.batches as u64).saturating_mul(miner.launch().threads());
+                        miner.mine_bruteforce_nonce_gpu(&header_prefix, &target, 0, max_nonces, &AtomicBool::new(false), None)
                     } else {
                         miner.mine_with_ga(
                             &header_prefix,
                             &target,
                             args.generations,
                             args.mutation_rate,
+                            &AtomicBool::new(false),
+                            None,
                         )
                     };
                     match res {
@@ -590,7 +1756,7 @@ fn main() {
                             let elapsed = start.elapsed();
                             println!("\n✅ SOLUTION FOUND!");
                             println!("   Mutation vector: {}", hex::encode(&mv));
-                            println!("   Hash: {}", hex::encode(&hash));
+                            println!("   Hash: {}", hex::encode(hash));
                             println!("   Time: {:?}", elapsed);
                         }
                         None => {
@@ -599,8 +1765,8 @@ fn main() {
                     }
                 }
                 Err(e) => {
-                    eprintln!("❌ GPU initialization failed: {}", e);
-                    eprintln!("   Falling back to CPU GA...");
+                    error!("❌ GPU initialization failed: {}", e);
+                    warn!("   Falling back to CPU GA...");
                     let start = Instant::now();
                     match gpu_miner::cpu_ga_mine(
                         &header_prefix,
@@ -608,13 +1774,14 @@ fn main() {
                         args.population,
                         args.mv_len,
                         args.generations,
-                        args.mutation_rate,
+                        &ga_config_with_telemetry(&args),
+                        &mut seed::rng(seed, &[]),
                     ) {
                         Some((mv, hash)) => {
                             let elapsed = start.elapsed();
                             println!("\n✅ CPU SOLUTION FOUND!");
                             println!("   Mutation vector: {}", hex::encode(&mv));
-                            println!("   Hash: {}", hex::encode(&hash));
+                            println!("   Hash: {}", hex::encode(hash));
                             println!("   Time: {:?}", elapsed);
                         }
                         None => {
@@ -626,8 +1793,8 @@ fn main() {
         }
         #[cfg(not(feature = "cuda"))]
         {
-            eprintln!("❌ CUDA support not compiled. Rebuild with --features cuda");
-            eprintln!("   Falling back to CPU GA...");
+            error!("❌ CUDA support not compiled. Rebuild with --features cuda");
+            warn!("   Falling back to CPU GA...");
             let start = Instant::now();
             match gpu_miner::cpu_ga_mine(
                 &header_prefix,
@@ -635,13 +1802,14 @@ fn main() {
                 args.population,
                 args.mv_len,
                 args.generations,
-                args.mutation_rate,
+                &ga_config_with_telemetry(&args),
+                &mut seed::rng(seed, &[]),
             ) {
                 Some((mv, hash)) => {
                     let elapsed = start.elapsed();
                     println!("\n✅ CPU SOLUTION FOUND!");
                     println!("   Mutation vector: {}", hex::encode(&mv));
-                    println!("   Hash: {}", hex::encode(&hash));
+                    println!("   Hash: {}", hex::encode(hash));
                     println!("   Time: {:?}", elapsed);
                 }
                 None => {
@@ -650,7 +1818,7 @@ fn main() {
             }
         }
     } else if args.brute {
-        run_bruteforce(header_prefix, &target, args.mv_len);
+        run_bruteforce(header_prefix, &target, args.mv_len, args.threads, args.start_nonce, extranonce(&args));
     } else {
         run_ga(
             header_prefix,
@@ -658,10 +1826,12 @@ fn main() {
             args.mv_len,
             args.population,
             args.generations,
+            &ga_config_with_telemetry(&args),
+            seed,
         );
     }
 
-    println!("\n✅ Mining completed");
+    info!("✅ Mining completed");
 }
 
 // メモ: GPU 連携
@@ -678,3 +1848,169 @@ fn main() {
 
 // 実行例:
 // cargo run --release -- --header-hex <prefix-hex> --bits-hex 1f00ffff --mv-len 16 --population 1024 --generations 2000
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+    use std::collections::BTreeMap;
+    use std::ffi::OsString;
+
+    const CONFIG: &str = "node_url = \"http://a:36669\"\nbatches = 10\npopulation = 100\napi_token = \"old\"\n";
+
+    /// Miner state as `mine_loop` sets it up from `--config <file>`
+    struct Running {
+        argv: Vec<OsString>,
+        current: BTreeMap<String, Vec<String>>,
+        control: Arc<control::MinerControl>,
+        api_config: parking_lot::RwLock<api::ApiConfig>,
+    }
+
+    fn start(file: &std::path::Path) -> Running {
+        let argv: Vec<OsString> = vec!["xenom-miner-rust".into(), "--config".into(), file.into()];
+        let resolved = config::resolve(Args::command(), argv.clone());
+        assert!(resolved.errors.is_empty(), "{:?}", resolved.errors);
+        let matches = Args::command().get_matches_from(&resolved.argv);
+        let args = Args::from_arg_matches(&matches).unwrap();
+        let control = control::MinerControl::new(
+            control::RuntimeSettings {
+                node_urls: vec![args.node_url.clone()],
+                active_node: 0,
+                miner_address: args.miner_address.clone(),
+                threads: 2,
+                batches: args.batches,
+                population: args.population,
+                throttle: throttle_settings(&args),
+            },
+            args.control_token.clone(),
+        );
+        let api_config = parking_lot::RwLock::new(api::ApiConfig {
+            token: args.api_token.clone(),
+            ..Default::default()
+        });
+        Running {
+            argv,
+            current: config::values(&Args::command(), &matches),
+            control,
+            api_config,
+        }
+    }
+
+    impl Running {
+        fn reload(&mut self) {
+            reload_config(&self.argv, &mut self.current, &self.control, &self.api_config);
+        }
+    }
+
+    #[test]
+    fn reload_applies_live_options() {
+        let _env = config::ENV_LOCK.lock();
+        let dir = TestDir::new("reload-live");
+        let file = dir.write("miner.toml", CONFIG);
+        let mut miner = start(&file);
+
+        dir.write(
+            "miner.toml",
+            "node_url = \"http://b:36669\"\nbatches = 20\npopulation = 100\napi_token = \"new\"\n\
+             duty_cycle = 0.5\ncontrol_token = \"ctl\"\nmv_len = 8\n",
+        );
+        miner.reload();
+        let settings = miner.control.settings();
+        assert_eq!(settings.active_node_url(), "http://b:36669");
+        assert_eq!((settings.threads, settings.batches, settings.population), (2, 20, 100));
+        assert_eq!(settings.throttle.duty_cycle, 0.5);
+        assert_eq!(miner.api_config.read().token.as_deref(), Some("new"));
+        assert!(miner.control.authorize(Some("Bearer ctl")));
+
+        // Only live options count as applied; mv_len needs a restart and is reported again
+        assert_eq!(miner.current["batches"], ["20"]);
+        assert_eq!(miner.current["mv_len"], ["16"]);
+        let revision = miner.control.revision();
+        miner.reload();
+        assert_eq!(miner.control.revision(), revision, "nothing live changed");
+    }
+
+    #[test]
+    fn reload_keeps_runtime_changes_the_config_does_not_touch() {
+        let _env = config::ENV_LOCK.lock();
+        let dir = TestDir::new("reload-runtime");
+        let file = dir.write("miner.toml", CONFIG);
+        let mut miner = start(&file);
+        miner
+            .control
+            .set_intensity(control::IntensityRequest {
+                threads: None,
+                batches: None,
+                population: Some(999),
+            })
+            .unwrap();
+        miner
+            .control
+            .set_node(control::NodeRequest {
+                url: "http://added:36669".to_string(),
+                switch: true,
+            })
+            .unwrap();
+        miner
+            .control
+            .set_address(control::AddressRequest {
+                address: Some("3Mruntime".to_string()),
+            })
+            .unwrap();
+
+        dir.write("miner.toml", &CONFIG.replace("batches = 10", "batches = 30"));
+        miner.reload();
+        let settings = miner.control.settings();
+        assert_eq!((settings.batches, settings.population), (30, 999));
+        assert_eq!(settings.node_urls.len(), 2);
+        assert_eq!(settings.active_node_url(), "http://added:36669");
+        assert_eq!(settings.miner_address.as_deref(), Some("3Mruntime"));
+    }
+
+    #[test]
+    fn invalid_reload_keeps_the_current_settings() {
+        let _env = config::ENV_LOCK.lock();
+        let dir = TestDir::new("reload-invalid");
+        let file = dir.write("miner.toml", CONFIG);
+        let mut miner = start(&file);
+
+        for broken in ["batches = 40\nbogus = 1\n", "batches = 0\n", "batches = 40\nnode_url = \"ftp://x\"\n"] {
+            dir.write("miner.toml", broken);
+            miner.reload();
+            assert_eq!(miner.control.settings().batches, 10, "{}", broken);
+            assert_eq!(miner.control.revision(), 0, "{}", broken);
+            assert_eq!(miner.api_config.read().token.as_deref(), Some("old"));
+        }
+    }
+
+    #[test]
+    fn stalled_workers_restart_up_to_the_limit_then_drop() {
+        let args = Args::parse_from(["xenom-miner-rust", "--mine-loop", "--threads", "1"]);
+        let settings = control::RuntimeSettings {
+            node_urls: vec![args.node_url.clone()],
+            active_node: 0,
+            miner_address: None,
+            threads: 1,
+            batches: args.batches,
+            population: args.population,
+            throttle: throttle_settings(&args),
+        };
+        let control = control::MinerControl::new(settings.clone(), None);
+        let pacer = throttle::Pacer::new(&throttle::Throttle::new(control), None);
+        let mut worker = Worker::new(Backend::Cpu(cpu_miner::CpuMiner::new(1, args.mv_len)), Arc::clone(&pacer));
+
+        for restart in 1..=watchdog::MAX_RESTARTS {
+            let stale = Arc::clone(&worker.heartbeat);
+            assert_eq!(worker.recover(&args, &settings), Ok(()));
+            assert_eq!((worker.health, worker.restarts), (stats::WorkerHealth::Stalled, restart));
+            assert!(!Arc::ptr_eq(&stale, &worker.heartbeat), "a restart starts a fresh heartbeat");
+            assert!(Arc::ptr_eq(&pacer, &worker.pacer), "the pacer is kept across restarts");
+        }
+        assert_eq!(
+            worker.recover(&args, &settings),
+            Err(format!("stalled {} times", watchdog::MAX_RESTARTS + 1))
+        );
+        assert_eq!(worker.health, stats::WorkerHealth::Dropped);
+        assert_eq!(worker.restarts, watchdog::MAX_RESTARTS);
+    }
+}
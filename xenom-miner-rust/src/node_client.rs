@@ -73,6 +73,7 @@ impl NodeClient {
         height: u64,
         mutation_vector_hex: &str,
         timestamp: u64,
+        timeout: std::time::Duration,
     ) -> Result<SubmissionResponse, Box<dyn Error>> {
         let url = format!("{}/mining/submit", self.base_url);
         
@@ -86,6 +87,7 @@ impl NodeClient {
         let response = self.client
             .post(&url)
             .json(&submission)
+            .timeout(timeout)
             .send()?;
         
         if !response.status().is_success() {
@@ -0,0 +1,25 @@
+//! Seeded random numbers for reproducible runs (`--seed`). Every search RNG is
+//! derived from the run seed and stream ids (device, round, thread), so a seed,
+//! template and configuration replay the same candidates. `StdRng` may change
+//! between `rand` releases: replay with the same build.
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+/// SplitMix64 step, spreading nearby inputs over the whole range
+fn mix(z: u64) -> u64 {
+    let mut z = z.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Seed of the stream identified by `ids` under the run seed
+pub fn derive(seed: u64, ids: &[u64]) -> u64 {
+    ids.iter().fold(mix(seed), |acc, &id| mix(acc ^ mix(id)))
+}
+
+/// RNG of the stream identified by `ids` under the run seed
+pub fn rng(seed: u64, ids: &[u64]) -> StdRng {
+    StdRng::seed_from_u64(derive(seed, ids))
+}
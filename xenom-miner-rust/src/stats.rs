@@ -1,8 +1,101 @@
+use crate::watchdog::Heartbeat;
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+/// Number of submitted blocks kept in `MinerStats::recent_blocks`
+const BLOCK_HISTORY_LEN: usize = 50;
+
+/// A heartbeat at most this old counts as mining activity
+const ACTIVITY_WINDOW: Duration = Duration::from_secs(2);
+
+/// Template currently being mined. Times are seconds since miner start.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateInfo {
+    pub height: u64,
+    pub difficulty_bits: String,
+    pub target_hex: String,
+    pub received_at: u64,
+}
+
+/// Result of one solution submission
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockRecord {
+    pub height: u64,
+    pub device: usize,
+    pub hash: String,
+    pub accepted: bool,
+    pub message: String,
+    pub submitted_at: u64,
+}
+
+/// State of a device's worker as judged by the watchdog
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkerHealth {
+    Healthy,
+    /// Stopped making progress; restarted and not yet through a full round
+    Stalled,
+    /// Stalled too often or could not be restarted; no longer mining
+    Dropped,
+}
+
+/// Identity, share counters and worker health of one mining device
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceStats {
+    pub name: String,
+    /// PCI bus number, as used by HiveOS to match devices
+    pub bus_number: Option<u32>,
+    pub accepted: u64,
+    pub rejected: u64,
+    /// Solutions reported by the device that failed CPU verification
+    pub invalid: u64,
+    pub health: WorkerHealth,
+    /// Times the watchdog restarted the worker
+    pub restarts: u64,
+    /// Seconds since the worker last reported hashes, while it is mining
+    pub heartbeat_age_secs: Option<f64>,
+    /// Hashes reported through the worker's heartbeats
+    pub hashes: u64,
+}
+
+impl DeviceStats {
+    pub fn new(name: String, bus_number: Option<u32>) -> Self {
+        Self {
+            name,
+            bus_number,
+            accepted: 0,
+            rejected: 0,
+            invalid: 0,
+            health: WorkerHealth::Healthy,
+            restarts: 0,
+            heartbeat_age_secs: None,
+            hashes: 0,
+        }
+    }
+}
+
+/// Latest reading of a temperature sensor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Temperature {
+    pub sensor: String,
+    pub celsius: f64,
+}
+
+/// Reachability of a node as seen by the mining loop
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeHealth {
+    pub url: String,
+    pub active: bool,
+    pub reachable: bool,
+    pub latency_ms: Option<u64>,
+    pub last_success: Option<u64>,
+    pub last_error: Option<String>,
+    pub consecutive_failures: u64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MinerStats {
     pub uptime_secs: u64,
@@ -11,11 +104,99 @@ pub struct MinerStats {
     pub hashrate_mhs: f64,
     pub accepted_shares: u64,
     pub rejected_shares: u64,
+    pub invalid_shares: u64,
     pub gpu_count: usize,
     pub per_gpu_hashrate_mhs: Vec<f64>,
     pub mining: bool,
+    pub paused: bool,
     pub last_solution_time: Option<u64>,
     pub version: String,
+    pub devices: Vec<DeviceStats>,
+    pub current_template: Option<TemplateInfo>,
+    pub nodes: Vec<NodeHealth>,
+    pub recent_blocks: Vec<BlockRecord>,
+    pub reject_reasons: BTreeMap<String, u64>,
+    /// Submissions rejected or failed in a row since the last accepted one
+    pub consecutive_submit_failures: u64,
+    /// Last time the mining loop fetched a template or made progress on a round
+    pub last_activity: Option<u64>,
+    /// CPU temperatures watched by the thermal monitor
+    pub temperatures: Vec<Temperature>,
+    /// Share of the CPU threads or duty cycle left by a thermal back-off, with `--thermal-limit`
+    pub thermal_capacity: Option<f64>,
+    #[serde(skip)]
+    started: Option<Instant>,
+}
+
+/// Thresholds behind `/health/live` and `/health/ready`; 0 disables a check
+#[derive(Debug, Clone, Default)]
+pub struct HealthPolicy {
+    /// Seconds after which the current template, or the mining loop's last activity, is stale
+    pub max_template_age: u64,
+    /// Failed submissions in a row after which the miner is not ready
+    pub max_submit_failures: u64,
+}
+
+impl MinerStats {
+    /// Seconds since miner start; `uptime_secs` only moves when stats are updated
+    pub fn now(&self) -> u64 {
+        self.started.map(|s| s.elapsed().as_secs()).unwrap_or(self.uptime_secs)
+    }
+
+    /// Reasons the miner should be restarted: no working device, or a mining loop that stopped
+    /// doing anything. Empty when live.
+    pub fn liveness(&self, policy: &HealthPolicy) -> Vec<String> {
+        let mut reasons = Vec::new();
+        if !self.devices.is_empty() && self.devices.iter().all(|d| d.health != WorkerHealth::Healthy) {
+            reasons.push("no working devices".to_string());
+        }
+        let idle = self.now().saturating_sub(self.last_activity.unwrap_or(0));
+        if policy.max_template_age > 0 && !self.paused && idle > policy.max_template_age {
+            reasons.push(format!("mining loop inactive for {}s", idle));
+        }
+        reasons
+    }
+
+    /// Reasons the miner is not earning: liveness failures, a stale or missing template,
+    /// an unreachable node, failing submissions or a pause. Empty when ready.
+    pub fn readiness(&self, policy: &HealthPolicy) -> Vec<String> {
+        let mut reasons = self.liveness(policy);
+        if self.paused {
+            reasons.push("mining paused".to_string());
+        }
+        match &self.current_template {
+            None => reasons.push("no block template received yet".to_string()),
+            Some(t) => {
+                let age = self.now().saturating_sub(t.received_at);
+                if policy.max_template_age > 0 && age > policy.max_template_age {
+                    reasons.push(format!("last template is {}s old", age));
+                }
+            }
+        }
+        if let Some(node) = self.nodes.iter().find(|n| n.active && !n.reachable && n.consecutive_failures > 0) {
+            reasons.push(format!(
+                "node {} unreachable: {}",
+                node.url,
+                node.last_error.as_deref().unwrap_or("unknown error")
+            ));
+        }
+        if policy.max_submit_failures > 0 && self.consecutive_submit_failures >= policy.max_submit_failures {
+            reasons.push(format!("last {} submissions failed", self.consecutive_submit_failures));
+        }
+        reasons
+    }
+
+    /// `ok` when every device is healthy, `degraded` when only some are and `unhealthy` when none is
+    pub fn health(&self) -> &'static str {
+        let healthy = self.devices.iter().filter(|d| d.health == WorkerHealth::Healthy).count();
+        if healthy == self.devices.len() {
+            "ok"
+        } else if healthy > 0 {
+            "degraded"
+        } else {
+            "unhealthy"
+        }
+    }
 }
 
 impl Default for MinerStats {
@@ -27,11 +208,23 @@ impl Default for MinerStats {
             hashrate_mhs: 0.0,
             accepted_shares: 0,
             rejected_shares: 0,
+            invalid_shares: 0,
             gpu_count: 0,
             per_gpu_hashrate_mhs: Vec::new(),
             mining: false,
+            paused: false,
             last_solution_time: None,
             version: env!("CARGO_PKG_VERSION").to_string(),
+            devices: Vec::new(),
+            current_template: None,
+            nodes: Vec::new(),
+            recent_blocks: Vec::new(),
+            reject_reasons: BTreeMap::new(),
+            consecutive_submit_failures: 0,
+            last_activity: None,
+            temperatures: Vec::new(),
+            thermal_capacity: None,
+            started: None,
         }
     }
 }
@@ -42,10 +235,14 @@ pub struct StatsTracker {
 }
 
 impl StatsTracker {
-    pub fn new(gpu_count: usize) -> Self {
-        let mut stats = MinerStats::default();
-        stats.gpu_count = gpu_count;
-        stats.per_gpu_hashrate_mhs = vec![0.0; gpu_count];
+    pub fn new(devices: Vec<DeviceStats>) -> Self {
+        let stats = MinerStats {
+            started: Some(Instant::now()),
+            gpu_count: devices.len(),
+            per_gpu_hashrate_mhs: vec![0.0; devices.len()],
+            devices,
+            ..Default::default()
+        };
         
         Self {
             stats: Arc::new(RwLock::new(stats)),
@@ -57,15 +254,11 @@ impl StatsTracker {
         Arc::clone(&self.stats)
     }
 
-    pub fn update_height(&self, height: u64) {
-        let mut stats = self.stats.write();
-        stats.current_height = height;
-    }
-
     pub fn update_hashrate(&self, total_hashes: u64, elapsed: Duration, gpu_id: Option<usize>) {
         let mut stats = self.stats.write();
         stats.total_hashes += total_hashes;
         stats.uptime_secs = self.start_time.elapsed().as_secs();
+        stats.last_activity = Some(stats.uptime_secs);
         
         let hashrate_mhs = total_hashes as f64 / elapsed.as_secs_f64() / 1_000_000.0;
         
@@ -79,72 +272,297 @@ impl StatsTracker {
         stats.hashrate_mhs = stats.per_gpu_hashrate_mhs.iter().sum();
     }
 
-    pub fn increment_accepted(&self) {
+    fn now(&self) -> u64 {
+        self.start_time.elapsed().as_secs()
+    }
+
+    pub fn set_template(&self, height: u64, difficulty_bits: &str, target_hex: &str) {
+        let now = self.now();
         let mut stats = self.stats.write();
-        stats.accepted_shares += 1;
-        stats.last_solution_time = Some(self.start_time.elapsed().as_secs());
+        stats.uptime_secs = now;
+        stats.current_height = height;
+        stats.current_template = Some(TemplateInfo {
+            height,
+            difficulty_bits: difficulty_bits.to_string(),
+            target_hex: target_hex.to_string(),
+            received_at: now,
+        });
     }
 
-    pub fn increment_rejected(&self) {
+    /// Records the outcome of a request to `url`, which becomes the active node
+    pub fn record_node(&self, url: &str, result: Result<Duration, String>) {
+        let now = self.now();
         let mut stats = self.stats.write();
-        stats.rejected_shares += 1;
+        stats.uptime_secs = now;
+        stats.last_activity = Some(now);
+        for node in &mut stats.nodes {
+            node.active = node.url == url;
+        }
+        let node = match stats.nodes.iter().position(|n| n.url == url) {
+            Some(idx) => &mut stats.nodes[idx],
+            None => {
+                stats.nodes.push(NodeHealth {
+                    url: url.to_string(),
+                    active: true,
+                    reachable: false,
+                    latency_ms: None,
+                    last_success: None,
+                    last_error: None,
+                    consecutive_failures: 0,
+                });
+                stats.nodes.last_mut().unwrap()
+            }
+        };
+        match result {
+            Ok(latency) => {
+                node.reachable = true;
+                node.latency_ms = Some(latency.as_millis() as u64);
+                node.last_success = Some(now);
+                node.consecutive_failures = 0;
+            }
+            Err(e) => {
+                node.reachable = false;
+                node.last_error = Some(e);
+                node.consecutive_failures += 1;
+            }
+        }
+    }
+
+    /// Records a submission and updates the accepted/rejected counters
+    pub fn record_block(&self, height: u64, device: usize, hash: &[u8; 32], accepted: bool, message: &str) {
+        let now = self.now();
+        let mut stats = self.stats.write();
+        stats.uptime_secs = now;
+        if accepted {
+            stats.accepted_shares += 1;
+            stats.last_solution_time = Some(now);
+            stats.consecutive_submit_failures = 0;
+        } else {
+            stats.rejected_shares += 1;
+            stats.consecutive_submit_failures += 1;
+            *stats.reject_reasons.entry(message.to_string()).or_insert(0) += 1;
+        }
+        if let Some(dev) = stats.devices.get_mut(device) {
+            if accepted {
+                dev.accepted += 1;
+            } else {
+                dev.rejected += 1;
+            }
+        }
+        if stats.recent_blocks.len() == BLOCK_HISTORY_LEN {
+            stats.recent_blocks.remove(0);
+        }
+        stats.recent_blocks.push(BlockRecord {
+            height,
+            device,
+            hash: hex::encode(hash),
+            accepted,
+            message: message.to_string(),
+            submitted_at: now,
+        });
+    }
+
+    /// Counts a submission that never got an answer from the node
+    pub fn record_submit_error(&self) {
+        self.stats.write().consecutive_submit_failures += 1;
+    }
+
+    /// Counts a solution that failed CPU verification and was not submitted
+    pub fn record_invalid(&self, device: usize) {
+        let mut stats = self.stats.write();
+        stats.invalid_shares += 1;
+        if let Some(dev) = stats.devices.get_mut(device) {
+            dev.invalid += 1;
+        }
     }
 
     pub fn set_mining(&self, mining: bool) {
         let mut stats = self.stats.write();
         stats.mining = mining;
     }
-}
 
-pub fn start_api_server(stats: Arc<RwLock<MinerStats>>, port: u16) {
-    std::thread::spawn(move || {
-        let server = match tiny_http::Server::http(format!("0.0.0.0:{}", port)) {
-            Ok(s) => {
-                println!("📊 Stats API server started on http://0.0.0.0:{}", port);
-                s
-            }
-            Err(e) => {
-                eprintln!("❌ Failed to start stats API server: {}", e);
-                return;
+    /// Publishes what the watchdog knows about `device`'s worker
+    pub fn set_worker(&self, device: usize, health: WorkerHealth, restarts: u64, heartbeat: &Heartbeat) {
+        let now = self.now();
+        let mut stats = self.stats.write();
+        // Called by the mining loop while it waits on a round; a hung round must not look active
+        let beating = heartbeat.stalled_for().is_some_and(|age| age <= ACTIVITY_WINDOW);
+        let counted = stats.devices.get(device).is_some_and(|dev| dev.hashes != heartbeat.hashes());
+        if beating || counted {
+            stats.last_activity = Some(now);
+        }
+        if let Some(dev) = stats.devices.get_mut(device) {
+            dev.health = health;
+            dev.restarts = restarts;
+            dev.heartbeat_age_secs = heartbeat.stalled_for().map(|d| d.as_secs_f64());
+            dev.hashes = heartbeat.hashes();
+        }
+        if health == WorkerHealth::Dropped {
+            if let Some(rate) = stats.per_gpu_hashrate_mhs.get_mut(device) {
+                *rate = 0.0;
             }
-        };
+            stats.hashrate_mhs = stats.per_gpu_hashrate_mhs.iter().sum();
+        }
+    }
 
-        for request in server.incoming_requests() {
-            let path = request.url();
-            
-            match path {
-                "/stats" | "/api/stats" => {
-                    let stats_data = stats.read().clone();
-                    let json = serde_json::to_string_pretty(&stats_data).unwrap_or_else(|_| "{}".to_string());
-                    
-                    let response = tiny_http::Response::from_string(json)
-                        .with_header(
-                            tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap()
-                        )
-                        .with_header(
-                            tiny_http::Header::from_bytes(&b"Access-Control-Allow-Origin"[..], &b"*"[..]).unwrap()
-                        );
-                    
-                    let _ = request.respond(response);
-                }
-                "/health" => {
-                    let response = tiny_http::Response::from_string(r#"{"status":"ok"}"#)
-                        .with_header(
-                            tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap()
-                        );
-                    
-                    let _ = request.respond(response);
-                }
-                _ => {
-                    let response = tiny_http::Response::from_string(r#"{"error":"not found"}"#)
-                        .with_status_code(404)
-                        .with_header(
-                            tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap()
-                        );
-                    
-                    let _ = request.respond(response);
-                }
-            }
+    pub fn set_paused(&self, paused: bool) {
+        let mut stats = self.stats.write();
+        stats.paused = paused;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(reachable: bool) -> NodeHealth {
+        NodeHealth {
+            url: "http://a:36669".to_string(),
+            active: true,
+            reachable,
+            latency_ms: None,
+            last_success: None,
+            last_error: (!reachable).then(|| "connection refused".to_string()),
+            consecutive_failures: if reachable { 0 } else { 3 },
+        }
+    }
+
+    /// A miner 1000s into a healthy run: fresh template and activity, one working device
+    fn healthy() -> MinerStats {
+        MinerStats {
+            uptime_secs: 1000,
+            last_activity: Some(990),
+            devices: vec![DeviceStats::new("GPU 0".to_string(), None)],
+            current_template: Some(TemplateInfo {
+                height: 1,
+                difficulty_bits: "1d00ffff".to_string(),
+                target_hex: "00ff".to_string(),
+                received_at: 950,
+            }),
+            nodes: vec![node(true)],
+            ..Default::default()
+        }
+    }
+
+    /// Name, change to a healthy miner, and the expected liveness and readiness reasons
+    type Case = (&'static str, fn(&mut MinerStats), &'static [&'static str], &'static [&'static str]);
+
+    #[test]
+    fn health_reasons() {
+        let policy = HealthPolicy {
+            max_template_age: 120,
+            max_submit_failures: 3,
+        };
+        let cases: Vec<Case> = vec![
+            ("healthy", |_| {}, &[], &[]),
+            (
+                "all devices down",
+                |s| s.devices[0].health = WorkerHealth::Dropped,
+                &["no working devices"],
+                &["no working devices"],
+            ),
+            (
+                "one of two devices down",
+                |s| {
+                    let mut stalled = DeviceStats::new("GPU 1".to_string(), None);
+                    stalled.health = WorkerHealth::Stalled;
+                    s.devices.push(stalled);
+                },
+                &[],
+                &[],
+            ),
+            (
+                "loop inactive",
+                |s| s.last_activity = Some(870),
+                &["mining loop inactive for 130s"],
+                &["mining loop inactive for 130s"],
+            ),
+            ("inactive while paused", |s| (s.last_activity, s.paused) = (Some(0), true), &[], &["mining paused"]),
+            (
+                "no template",
+                |s| s.current_template = None,
+                &[],
+                &["no block template received yet"],
+            ),
+            (
+                "stale template",
+                |s| s.current_template.as_mut().unwrap().received_at = 800,
+                &[],
+                &["last template is 200s old"],
+            ),
+            (
+                "node unreachable",
+                |s| s.nodes = vec![node(false)],
+                &[],
+                &["node http://a:36669 unreachable: connection refused"],
+            ),
+            (
+                "unreachable backup node",
+                |s| s.nodes = vec![NodeHealth { active: false, ..node(false) }, node(true)],
+                &[],
+                &[],
+            ),
+            ("submit failures below the limit", |s| s.consecutive_submit_failures = 2, &[], &[]),
+            (
+                "submit failures",
+                |s| s.consecutive_submit_failures = 3,
+                &[],
+                &["last 3 submissions failed"],
+            ),
+        ];
+        for (name, change, live, ready) in cases {
+            let mut stats = healthy();
+            change(&mut stats);
+            assert_eq!(stats.liveness(&policy), live, "{}", name);
+            assert_eq!(stats.readiness(&policy), ready, "{}", name);
         }
-    });
+
+        // 0 turns the age and submit checks off
+        let mut stats = healthy();
+        stats.last_activity = Some(0);
+        stats.current_template.as_mut().unwrap().received_at = 0;
+        stats.consecutive_submit_failures = 100;
+        assert!(stats.readiness(&HealthPolicy::default()).is_empty());
+    }
+
+    #[test]
+    fn device_health_summary() {
+        let mut stats = healthy();
+        assert_eq!(stats.health(), "ok");
+        stats.devices.push(DeviceStats::new("GPU 1".to_string(), None));
+        stats.devices[1].health = WorkerHealth::Stalled;
+        assert_eq!(stats.health(), "degraded");
+        stats.devices[0].health = WorkerHealth::Dropped;
+        assert_eq!(stats.health(), "unhealthy");
+    }
+
+    #[test]
+    fn only_progress_counts_as_activity() {
+        let tracker = StatsTracker::new(vec![DeviceStats::new("CPU".to_string(), None)]);
+        let idle = || tracker.get_stats().write().last_activity = None;
+        let active = || tracker.get_stats().read().last_activity.is_some();
+
+        // A round whose device never beats (or stopped long ago) is a hung round
+        let hung = Heartbeat::default();
+        idle();
+        tracker.set_worker(0, WorkerHealth::Healthy, 0, &hung);
+        assert!(!active());
+
+        let beating = Heartbeat::default();
+        beating.start();
+        tracker.set_worker(0, WorkerHealth::Healthy, 0, &beating);
+        assert!(active());
+
+        // Hashes counted since the last check are progress even without a running round
+        let finished = Heartbeat::default();
+        finished.start();
+        finished.beat(1024);
+        finished.finish();
+        idle();
+        tracker.set_worker(0, WorkerHealth::Healthy, 0, &finished);
+        assert!(active());
+        idle();
+        tracker.set_worker(0, WorkerHealth::Healthy, 0, &finished);
+        assert!(!active());
+    }
 }
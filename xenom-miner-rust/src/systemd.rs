@@ -0,0 +1,266 @@
+//! systemd service integration: `sd_notify` messages and socket activation.
+//! Everything here is a no-op when the miner is not started by systemd.
+
+use crate::stats::MinerStats;
+use log::info;
+use parking_lot::RwLock;
+#[cfg(unix)]
+use parking_lot::Mutex;
+#[cfg(unix)]
+use std::sync::OnceLock;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// First file descriptor passed by socket activation (`SD_LISTEN_FDS_START`)
+#[cfg(unix)]
+const LISTEN_FDS_START: i32 = 3;
+
+/// How often `STATUS=` is refreshed (more often if the watchdog needs it)
+const STATUS_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Sends `state` (e.g. `READY=1`, `STATUS=...`) to `$NOTIFY_SOCKET`.
+/// Returns false when there is no notify socket or sending failed.
+pub fn notify(state: &str) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::net::UnixDatagram;
+
+        let Some(path) = std::env::var_os("NOTIFY_SOCKET") else {
+            return false;
+        };
+        let Ok(socket) = UnixDatagram::unbound() else {
+            return false;
+        };
+        let path = path.to_string_lossy();
+        // `@name` is a socket in the abstract namespace
+        let sent = match path.strip_prefix('@') {
+            #[cfg(target_os = "linux")]
+            Some(name) => {
+                use std::os::linux::net::SocketAddrExt;
+                std::os::unix::net::SocketAddr::from_abstract_name(name)
+                    .and_then(|addr| socket.send_to_addr(state.as_bytes(), &addr))
+            }
+            _ => socket.send_to(state.as_bytes(), path.as_ref()),
+        };
+        if let Err(e) = &sent {
+            log::debug!("sd_notify to {} failed: {}", path, e);
+        }
+        sent.is_ok()
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = state;
+        false
+    }
+}
+
+/// Watchdog timeout requested through `WatchdogSec=`, if it applies to this process
+pub fn watchdog_timeout() -> Option<Duration> {
+    let usec: u64 = std::env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+    if let Ok(pid) = std::env::var("WATCHDOG_PID") {
+        if pid.parse::<u32>().ok()? != std::process::id() {
+            return None;
+        }
+    }
+    (usec > 0).then(|| Duration::from_micros(usec))
+}
+
+/// Sockets passed by systemd socket activation, with their `FileDescriptorName=`.
+/// Each one can be taken once.
+#[cfg(unix)]
+fn listen_fds() -> &'static Mutex<Vec<(i32, String)>> {
+    static FDS: OnceLock<Mutex<Vec<(i32, String)>>> = OnceLock::new();
+    FDS.get_or_init(|| {
+        let for_us = std::env::var("LISTEN_PID")
+            .ok()
+            .and_then(|p| p.parse::<u32>().ok())
+            .is_some_and(|pid| pid == std::process::id());
+        let count: i32 = std::env::var("LISTEN_FDS").ok().and_then(|n| n.parse().ok()).unwrap_or(0);
+        if !for_us || count <= 0 {
+            return Mutex::new(Vec::new());
+        }
+        let names = std::env::var("LISTEN_FDNAMES").unwrap_or_default();
+        let mut names = names.split(':');
+        let fds = (0..count)
+            .map(|i| {
+                let name = names.next().filter(|n| !n.is_empty()).unwrap_or("unknown");
+                (LISTEN_FDS_START + i, name.to_string())
+            })
+            .collect();
+        Mutex::new(fds)
+    })
+}
+
+/// Takes a socket-activated listener: the first one left, or the one named `name`.
+/// Returns the fd number with a listener for `tiny_http`, which is TCP or Unix depending on the socket.
+#[cfg(unix)]
+pub fn take_listener(name: Option<&str>) -> Result<(i32, tiny_http::Listener), String> {
+    use std::os::unix::io::{FromRawFd, IntoRawFd};
+
+    let mut fds = listen_fds().lock();
+    let index = match name {
+        Some(name) => fds.iter().position(|(_, n)| n == name),
+        None => (!fds.is_empty()).then_some(0),
+    };
+    let Some(index) = index else {
+        return Err(match name {
+            Some(name) => format!("no socket named `{}` passed by systemd (LISTEN_FDNAMES)", name),
+            None => "no sockets passed by systemd (LISTEN_FDS)".to_string(),
+        });
+    };
+    let (fd, _) = fds.remove(index);
+
+    // SAFETY: systemd hands the fd to this process and it is removed from the list above,
+    // so nothing else owns it
+    let tcp = unsafe { std::net::TcpListener::from_raw_fd(fd) };
+    if tcp.local_addr().is_ok() {
+        return Ok((fd, tcp.into()));
+    }
+    // Not an inet socket: `local_addr` fails on AF_UNIX
+    let unix = unsafe { std::os::unix::net::UnixListener::from_raw_fd(tcp.into_raw_fd()) };
+    match unix.local_addr() {
+        Ok(_) => Ok((fd, unix.into())),
+        Err(e) => Err(format!("fd {} is not a listening TCP or Unix socket: {}", fd, e)),
+    }
+}
+
+/// One-line `STATUS=` text shown by `systemctl status`
+pub fn status_line(stats: &MinerStats) -> String {
+    if stats.paused {
+        return "Paused".to_string();
+    }
+    if let Some(node) = stats.nodes.iter().find(|n| n.active && !n.reachable && n.consecutive_failures > 0) {
+        return format!("Node {} unreachable ({} failures)", node.url, node.consecutive_failures);
+    }
+    match &stats.current_template {
+        Some(template) => format!(
+            "Mining block {} at {:.2} MH/s, {} accepted, {} rejected",
+            template.height, stats.hashrate_mhs, stats.accepted_shares, stats.rejected_shares
+        ),
+        None => "Waiting for a block template".to_string(),
+    }
+}
+
+/// Hashes counted so far: at the end of rounds and through the device heartbeats during them
+fn hashes_seen(stats: &MinerStats) -> (u64, u64) {
+    (stats.total_hashes, stats.devices.iter().map(|d| d.hashes).sum())
+}
+
+/// Whether the miner is making progress, given the hashes seen at the previous check:
+/// hashes were counted since, a device's heartbeat is fresher than `window` while the
+/// mining loop is still checking on it (a long round or a paced worker), or mining is paused.
+fn making_progress(stats: &MinerStats, last_hashes: Option<(u64, u64)>, window: Duration) -> bool {
+    if stats.paused || last_hashes != Some(hashes_seen(stats)) {
+        return true;
+    }
+    let window = window.as_secs_f64().max(1.0);
+    let loop_active = stats
+        .last_activity
+        .is_some_and(|at| (stats.now().saturating_sub(at) as f64) <= window + 1.0);
+    loop_active && stats.devices.iter().any(|d| d.heartbeat_age_secs.is_some_and(|age| age <= window))
+}
+
+/// Reports `STATUS=` periodically and, when `WatchdogSec=` is set, pings `WATCHDOG=1`
+/// only while the miner is making progress (see `making_progress`),
+/// so a stalled loop or hung device gets the service restarted.
+pub fn start_status_reporter(stats: Arc<RwLock<MinerStats>>) {
+    if std::env::var_os("NOTIFY_SOCKET").is_none() {
+        return;
+    }
+    let watchdog = watchdog_timeout();
+    let interval = watchdog.map(|t| t / 2).unwrap_or(STATUS_INTERVAL).min(STATUS_INTERVAL);
+    if let Some(timeout) = watchdog {
+        info!("🐕 systemd watchdog enabled ({:.1}s)", timeout.as_secs_f64());
+    }
+
+    std::thread::spawn(move || {
+        let mut last_hashes = None;
+        loop {
+            std::thread::sleep(interval);
+            let (status, progress) = {
+                let stats = stats.read();
+                let progress = making_progress(&stats, last_hashes, interval);
+                last_hashes = Some(hashes_seen(&stats));
+                (status_line(&stats), progress)
+            };
+            let mut message = format!("STATUS={}", status);
+            if watchdog.is_some() && progress {
+                message.push_str("\nWATCHDOG=1");
+            }
+            notify(&message);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::DeviceStats;
+
+    fn mining(hashes: u64, heartbeat_age_secs: Option<f64>) -> MinerStats {
+        let mut device = DeviceStats::new("cpu".to_string(), None);
+        device.hashes = hashes;
+        device.heartbeat_age_secs = heartbeat_age_secs;
+        let mut stats = MinerStats::default();
+        stats.devices.push(device);
+        stats.last_activity = Some(0);
+        stats
+    }
+
+    #[test]
+    fn progress_follows_heartbeats_not_just_rounds() {
+        let window = Duration::from_secs(5);
+        // A long round: no round has ended, but the device heartbeat keeps counting hashes
+        let stats = mining(100, Some(0.5));
+        assert!(making_progress(&stats, None, window));
+        assert!(making_progress(&stats, Some((0, 50)), window));
+        // A paced worker holds its heartbeat without hashing
+        assert!(making_progress(&stats, Some(hashes_seen(&stats)), window));
+
+        // A hung device: same hashes, heartbeat older than the window
+        let stalled = mining(100, Some(30.0));
+        assert!(!making_progress(&stalled, Some(hashes_seen(&stalled)), window));
+        // A mining loop that stopped checking leaves a fresh-looking heartbeat behind
+        let mut stuck = mining(100, Some(0.5));
+        stuck.uptime_secs = 60;
+        assert!(!making_progress(&stuck, Some(hashes_seen(&stuck)), window));
+
+        let mut paused = stalled;
+        paused.paused = true;
+        assert!(making_progress(&paused, Some(hashes_seen(&paused)), window));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn reporter_sends_status_and_watchdog_pings() {
+        use std::os::unix::net::UnixDatagram;
+
+        let _env = crate::config::ENV_LOCK.lock();
+        let dir = crate::test_dir::TestDir::new("notify");
+        let path = dir.path().join("notify.sock");
+        let socket = UnixDatagram::bind(&path).unwrap();
+        socket.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        std::env::set_var("NOTIFY_SOCKET", &path);
+        std::env::set_var("WATCHDOG_USEC", "200000");
+        std::env::remove_var("WATCHDOG_PID");
+
+        let stats = mining(0, Some(0.0));
+        let stats = Arc::new(RwLock::new(stats));
+        assert!(notify("READY=1"));
+        start_status_reporter(Arc::clone(&stats));
+        stats.write().devices[0].hashes = 1000;
+
+        let mut received = Vec::new();
+        let mut buf = [0u8; 1024];
+        while !received.iter().any(|m: &String| m.contains("WATCHDOG=1")) {
+            let Ok(len) = socket.recv(&mut buf) else { break };
+            received.push(String::from_utf8_lossy(&buf[..len]).into_owned());
+        }
+        std::env::remove_var("NOTIFY_SOCKET");
+        std::env::remove_var("WATCHDOG_USEC");
+
+        assert_eq!(received.first().map(String::as_str), Some("READY=1"));
+        assert!(received.iter().any(|m| m.starts_with("STATUS=")), "{:?}", received);
+        assert!(received.iter().any(|m| m.contains("\nWATCHDOG=1")), "{:?}", received);
+    }
+}
@@ -0,0 +1,154 @@
+//! Per-generation GA telemetry (`--ga-telemetry`), written as CSV or JSON lines
+//! for offline analysis and charts.
+
+use crate::ga::{GenerationRecord, Telemetry};
+use log::warn;
+use parking_lot::Mutex;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum TelemetryFormat {
+    /// Header row, then one row per generation and population
+    Csv,
+    /// One JSON object per line
+    Jsonl,
+}
+
+const CSV_HEADER: &str = "generation,island,best_fitness,mean_fitness,median_fitness,diversity,fitness_cv,\
+mutation_rate,selection_pressure,best_zero_bits,mean_zero_bits,hashes";
+
+/// Telemetry file; records from concurrent islands are written whole, one per line
+#[derive(Debug)]
+pub struct TelemetrySink {
+    format: TelemetryFormat,
+    out: Mutex<BufWriter<File>>,
+    /// Set after the first write error, which is logged once
+    failed: AtomicBool,
+}
+
+impl TelemetrySink {
+    /// Creates (or truncates) the file at `path`
+    pub fn create(path: &Path, format: TelemetryFormat) -> std::io::Result<Self> {
+        let mut out = BufWriter::new(File::create(path)?);
+        if format == TelemetryFormat::Csv {
+            writeln!(out, "{}", CSV_HEADER)?;
+        }
+        Ok(Self {
+            format,
+            out: Mutex::new(out),
+            failed: AtomicBool::new(false),
+        })
+    }
+
+    fn write(&self, record: &GenerationRecord) -> std::io::Result<()> {
+        let line = match self.format {
+            TelemetryFormat::Csv => format!(
+                "{},{},{},{},{},{:.6},{:.8},{:.6},{:.4},{},{:.3},{}",
+                record.generation,
+                record.island.map(|i| i.to_string()).unwrap_or_default(),
+                record.best_fitness,
+                record.mean_fitness,
+                record.median_fitness,
+                record.diversity,
+                record.fitness_cv,
+                record.mutation_rate,
+                record.selection_pressure,
+                record.best_zero_bits,
+                record.mean_zero_bits,
+                record.hashes
+            ),
+            TelemetryFormat::Jsonl => serde_json::to_string(record)?,
+        };
+        let mut out = self.out.lock();
+        writeln!(out, "{}", line)?;
+        // Flushed per record so the file is usable while the GA runs or after it is killed
+        out.flush()
+    }
+}
+
+impl Telemetry for TelemetrySink {
+    fn record(&self, record: &GenerationRecord) {
+        if let Err(e) = self.write(record) {
+            if !self.failed.swap(true, Ordering::Relaxed) {
+                warn!("⚠️  GA telemetry write failed: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    fn records() -> [GenerationRecord; 2] {
+        let record = |generation, island| GenerationRecord {
+            generation,
+            island,
+            best_fitness: 0.125,
+            mean_fitness: 0.0625,
+            median_fitness: 0.05,
+            diversity: 0.4375,
+            fitness_cv: 0.00012345,
+            mutation_rate: 0.02,
+            selection_pressure: 1.25,
+            best_zero_bits: 17,
+            mean_zero_bits: 1.5,
+            hashes: 4096,
+        };
+        [record(0, None), record(12, Some(3))]
+    }
+
+    fn write(dir: &TestDir, format: TelemetryFormat) -> String {
+        let path = dir.path().join("telemetry");
+        let sink = TelemetrySink::create(&path, format).unwrap();
+        for record in &records() {
+            sink.record(record);
+        }
+        std::fs::read_to_string(&path).unwrap()
+    }
+
+    #[test]
+    fn jsonl_round_trips() {
+        let dir = TestDir::new("telemetry-jsonl");
+        let text = write(&dir, TelemetryFormat::Jsonl);
+        let lines: Vec<serde_json::Value> = text.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        let expected: Vec<serde_json::Value> = records().iter().map(|r| serde_json::to_value(r).unwrap()).collect();
+        assert_eq!(lines, expected);
+        assert_eq!(lines[0]["island"], serde_json::Value::Null);
+        assert_eq!(lines[1]["island"], 3);
+    }
+
+    #[test]
+    fn csv_round_trips() {
+        let dir = TestDir::new("telemetry-csv");
+        let text = write(&dir, TelemetryFormat::Csv);
+        let mut lines = text.lines();
+        let header: Vec<&str> = lines.next().unwrap().split(',').collect();
+        assert_eq!(header.join(","), CSV_HEADER);
+        let rows: Vec<Vec<&str>> = lines.map(|l| l.split(',').collect()).collect();
+        assert_eq!(rows.len(), 2);
+
+        for (row, record) in rows.iter().zip(records()) {
+            assert_eq!(row.len(), header.len());
+            let field = |name: &str| row[header.iter().position(|h| *h == name).unwrap()];
+            let number = |name: &str| field(name).parse::<f64>().unwrap();
+            assert_eq!(field("generation").parse::<usize>().unwrap(), record.generation);
+            assert_eq!(field("island").parse::<usize>().ok(), record.island);
+            assert_eq!(number("best_fitness"), record.best_fitness);
+            assert_eq!(number("mean_fitness"), record.mean_fitness);
+            assert_eq!(number("median_fitness"), record.median_fitness);
+            assert_eq!(number("diversity"), record.diversity);
+            assert_eq!(number("fitness_cv"), record.fitness_cv);
+            assert_eq!(number("mutation_rate"), record.mutation_rate);
+            assert_eq!(number("selection_pressure"), record.selection_pressure);
+            assert_eq!(field("best_zero_bits").parse::<u32>().unwrap(), record.best_zero_bits);
+            assert_eq!(number("mean_zero_bits"), record.mean_zero_bits);
+            assert_eq!(field("hashes").parse::<u64>().unwrap(), record.hashes);
+        }
+        assert_eq!(rows[0][1], "");
+    }
+}
@@ -0,0 +1,450 @@
+//! CPU temperature monitoring of `--mine-loop` (`--thermal-*` options). A background
+//! thread reads the thermal zones and hwmon sensors under the sysfs root, reports them
+//! in the stats and, while the hottest sensor is above `--thermal-limit`, backs off the
+//! CPU backend's threads or duty cycle one step per reading. Below `--thermal-resume`
+//! the back-off is undone the same way; in between it holds, so the miner does not
+//! flap around the limit.
+
+use crate::stats::{MinerStats, Temperature};
+use clap::ValueEnum;
+use log::{info, warn};
+use parking_lot::RwLock;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Share of the capacity kept by each back-off step
+const STEP: f64 = 0.75;
+/// Capacity below which the back-off does not go
+const MIN_CAPACITY: f64 = 0.1;
+/// Degrees below the limit at which a back-off is undone, unless `--thermal-resume` is set
+const DEFAULT_HYSTERESIS: f64 = 5.0;
+/// Sensor names watched when no `--thermal-sensor` is given: Intel and AMD hwmon chips
+/// and the thermal zone types of x86 packages and ARM SoCs
+const CPU_SENSORS: &[&str] = &["coretemp", "k10temp", "zenpower", "x86_pkg_temp", "cpu", "soc"];
+
+/// What a thermal back-off reduces
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ThermalAction {
+    /// Run fewer CPU mining threads from the next round on
+    Threads,
+    /// Lower the duty cycle of the CPU mining threads
+    DutyCycle,
+}
+
+impl fmt::Display for ThermalAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ThermalAction::Threads => "threads",
+            ThermalAction::DutyCycle => "duty cycle",
+        })
+    }
+}
+
+/// Thermal options as given on the command line
+#[derive(Debug, Clone)]
+pub struct ThermalConfig {
+    /// Where sysfs is mounted (`--sysfs-root`)
+    pub sysfs: PathBuf,
+    /// Name filters of the sensors to watch; CPU sensors if empty
+    pub sensors: Vec<String>,
+    /// °C above which to back off (0 = never)
+    pub limit: f64,
+    /// °C below which to resume (default: `DEFAULT_HYSTERESIS` below the limit)
+    pub resume: Option<f64>,
+    pub action: ThermalAction,
+    pub interval: Duration,
+}
+
+impl ThermalConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.0..).contains(&self.limit) {
+            return Err(format!("thermal_limit {} must be 0 (off) or more", self.limit));
+        }
+        if let Some(resume) = self.resume {
+            if self.limit > 0.0 && !(..self.limit).contains(&resume) {
+                return Err(format!("thermal_resume {} must be below thermal_limit {}", resume, self.limit));
+            }
+        }
+        if self.interval.is_zero() {
+            return Err("thermal_interval must be greater than 0".to_string());
+        }
+        Ok(())
+    }
+
+    fn resume(&self) -> f64 {
+        self.resume.unwrap_or(self.limit - DEFAULT_HYSTERESIS)
+    }
+}
+
+/// A temperature input in sysfs, in millidegrees Celsius
+struct Sensor {
+    name: String,
+    path: PathBuf,
+}
+
+impl Sensor {
+    fn read(&self) -> Option<f64> {
+        let millidegrees: f64 = fs::read_to_string(&self.path).ok()?.trim().parse().ok()?;
+        Some(millidegrees / 1000.0)
+    }
+}
+
+fn read_trimmed(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string()).filter(|s| !s.is_empty())
+}
+
+/// Entries of `dir` whose name starts with `prefix`, in numeric order of the rest
+/// (`thermal_zone2` before `thermal_zone10`)
+fn entries(dir: &Path, prefix: &str) -> Vec<(String, PathBuf)> {
+    let mut entries: Vec<(String, PathBuf)> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            name.starts_with(prefix).then(|| (name, entry.path()))
+        })
+        .collect();
+    let number = |name: &str| {
+        let digits: String = name[prefix.len()..].chars().take_while(char::is_ascii_digit).collect();
+        digits.parse::<u64>().unwrap_or(u64::MAX)
+    };
+    entries.sort_by(|(a, _), (b, _)| number(a).cmp(&number(b)).then_with(|| a.cmp(b)));
+    entries
+}
+
+/// Every thermal zone (named by its type) and hwmon temperature input (named
+/// `chip/label`) under the sysfs root
+fn discover(sysfs: &Path) -> Vec<Sensor> {
+    let mut sensors = Vec::new();
+    for (name, zone) in entries(&sysfs.join("class/thermal"), "thermal_zone") {
+        sensors.push(Sensor {
+            name: read_trimmed(&zone.join("type")).unwrap_or(name),
+            path: zone.join("temp"),
+        });
+    }
+    for (name, hwmon) in entries(&sysfs.join("class/hwmon"), "hwmon") {
+        let chip = read_trimmed(&hwmon.join("name")).unwrap_or(name);
+        for (input, path) in entries(&hwmon, "temp") {
+            let Some(channel) = input.strip_suffix("_input") else {
+                continue;
+            };
+            let label = read_trimmed(&hwmon.join(format!("{}_label", channel))).unwrap_or_else(|| channel.to_string());
+            sensors.push(Sensor {
+                name: format!("{}/{}", chip, label),
+                path,
+            });
+        }
+    }
+    sensors
+}
+
+/// Sensors whose name contains one of `filters` (case-insensitive). Without filters, the
+/// CPU sensors, or every thermal zone and hwmon input if none is recognised.
+fn select(sensors: Vec<Sensor>, filters: &[String]) -> Vec<Sensor> {
+    let matches = |sensor: &Sensor, filters: &[&str]| {
+        let name = sensor.name.to_lowercase();
+        filters.iter().any(|filter| name.contains(&filter.to_lowercase()))
+    };
+    if !filters.is_empty() {
+        let filters: Vec<&str> = filters.iter().map(String::as_str).collect();
+        return sensors.into_iter().filter(|sensor| matches(sensor, &filters)).collect();
+    }
+    if sensors.iter().any(|sensor| matches(sensor, CPU_SENSORS)) {
+        sensors.into_iter().filter(|sensor| matches(sensor, CPU_SENSORS)).collect()
+    } else {
+        sensors
+    }
+}
+
+/// Watches the CPU temperature and holds the resulting back-off
+pub struct Monitor {
+    config: ThermalConfig,
+    sensors: Vec<Sensor>,
+    /// Share of the CPU threads or duty cycle left by the back-off (f64 bits)
+    capacity: AtomicU64,
+}
+
+impl Monitor {
+    /// Finds the sensors to watch; None, with a warning, if there are none
+    pub fn new(config: ThermalConfig) -> Option<Arc<Self>> {
+        let sensors = select(discover(&config.sysfs), &config.sensors);
+        if sensors.is_empty() {
+            if config.limit > 0.0 || !config.sensors.is_empty() {
+                warn!(
+                    "⚠️  No temperature sensors found under {}; thermal throttling is off",
+                    config.sysfs.display()
+                );
+            }
+            return None;
+        }
+        Some(Arc::new(Self {
+            config,
+            sensors,
+            capacity: AtomicU64::new(1.0f64.to_bits()),
+        }))
+    }
+
+    /// Polls the sensors every `--thermal-interval` into `stats`, adjusting the back-off
+    pub fn start(self: &Arc<Self>, stats: Arc<RwLock<MinerStats>>) {
+        let monitor = Arc::clone(self);
+        std::thread::spawn(move || loop {
+            let temperatures = monitor.poll();
+            {
+                let mut stats = stats.write();
+                stats.temperatures = temperatures;
+                stats.thermal_capacity = monitor.is_limited().then(|| monitor.capacity());
+            }
+            std::thread::sleep(monitor.config.interval);
+        });
+    }
+
+    fn is_limited(&self) -> bool {
+        self.config.limit > 0.0
+    }
+
+    fn capacity(&self) -> f64 {
+        f64::from_bits(self.capacity.load(Ordering::Relaxed))
+    }
+
+    /// CPU mining threads to run of `configured`, fewer while backing off threads
+    pub fn threads(&self, configured: usize) -> usize {
+        match self.config.action {
+            ThermalAction::Threads => ((configured as f64 * self.capacity()).round() as usize).clamp(1, configured.max(1)),
+            ThermalAction::DutyCycle => configured,
+        }
+    }
+
+    /// Highest duty cycle the CPU mining threads may run at
+    pub fn duty_cycle(&self) -> f64 {
+        match self.config.action {
+            ThermalAction::Threads => 1.0,
+            ThermalAction::DutyCycle => self.capacity(),
+        }
+    }
+
+    /// Reads the sensors and moves the back-off one step towards the hottest reading
+    fn poll(&self) -> Vec<Temperature> {
+        let temperatures: Vec<Temperature> = self
+            .sensors
+            .iter()
+            .filter_map(|sensor| {
+                sensor.read().map(|celsius| Temperature {
+                    sensor: sensor.name.clone(),
+                    celsius,
+                })
+            })
+            .collect();
+        let Some(hottest) = temperatures.iter().map(|t| t.celsius).reduce(f64::max) else {
+            return temperatures;
+        };
+        if !self.is_limited() {
+            return temperatures;
+        }
+
+        let (limit, resume) = (self.config.limit, self.config.resume());
+        let capacity = self.capacity();
+        let next = if hottest > limit {
+            (capacity * STEP).max(MIN_CAPACITY)
+        } else if hottest < resume {
+            (capacity / STEP).min(1.0)
+        } else {
+            capacity
+        };
+        if next != capacity {
+            self.capacity.store(next.to_bits(), Ordering::Relaxed);
+            if next < capacity {
+                warn!(
+                    "🌡️  CPU at {:.1}°C (limit {}°C): backing off to {:.0}% of the {}",
+                    hottest,
+                    limit,
+                    next * 100.0,
+                    self.config.action
+                );
+            } else if next < 1.0 {
+                info!(
+                    "🌡️  CPU at {:.1}°C (resume {}°C): easing back to {:.0}% of the {}",
+                    hottest,
+                    resume,
+                    next * 100.0,
+                    self.config.action
+                );
+            } else {
+                info!("🌡️  CPU at {:.1}°C (resume {}°C): back to full speed", hottest, resume);
+            }
+        }
+        temperatures
+    }
+}
+
+impl fmt::Display for Monitor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<&str> = self.sensors.iter().map(|sensor| sensor.name.as_str()).collect();
+        write!(f, "{}", names.join(", "))?;
+        if self.is_limited() {
+            write!(
+                f,
+                "; limit {}°C, resume below {}°C, backing off {}",
+                self.config.limit,
+                self.config.resume(),
+                self.config.action
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    fn config(sysfs: &Path, limit: f64, action: ThermalAction) -> ThermalConfig {
+        ThermalConfig {
+            sysfs: sysfs.to_path_buf(),
+            sensors: Vec::new(),
+            limit,
+            resume: None,
+            action,
+            interval: Duration::from_secs(1),
+        }
+    }
+
+    fn names(sensors: &[Sensor]) -> Vec<&str> {
+        sensors.iter().map(|sensor| sensor.name.as_str()).collect()
+    }
+
+    /// Two thermal zones and two hwmon chips, one of them the CPU's
+    fn machine() -> TestDir {
+        let dir = TestDir::new("thermal-machine");
+        dir.write("class/thermal/thermal_zone10/type", "x86_pkg_temp\n");
+        dir.write("class/thermal/thermal_zone10/temp", "61000\n");
+        dir.write("class/thermal/thermal_zone2/temp", "40000\n");
+        dir.write("class/hwmon/hwmon0/name", "nvme\n");
+        dir.write("class/hwmon/hwmon0/temp1_input", "38850\n");
+        dir.write("class/hwmon/hwmon1/name", "coretemp\n");
+        dir.write("class/hwmon/hwmon1/temp1_input", "62000\n");
+        dir.write("class/hwmon/hwmon1/temp1_label", "Package id 0\n");
+        dir.write("class/hwmon/hwmon1/temp1_max", "100000\n");
+        dir.write("class/hwmon/hwmon1/temp2_input", "59000\n");
+        dir
+    }
+
+    #[test]
+    fn discovers_zones_and_hwmon_inputs() {
+        let dir = machine();
+        let sensors = discover(dir.path());
+        assert_eq!(
+            names(&sensors),
+            ["thermal_zone2", "x86_pkg_temp", "nvme/temp1", "coretemp/Package id 0", "coretemp/temp2"]
+        );
+        let readings: Vec<Option<f64>> = sensors.iter().map(Sensor::read).collect();
+        assert_eq!(readings, [Some(40.0), Some(61.0), Some(38.85), Some(62.0), Some(59.0)]);
+
+        assert!(discover(&dir.path().join("missing")).is_empty());
+    }
+
+    #[test]
+    fn selects_cpu_sensors_unless_filtered() {
+        let dir = machine();
+        let cpu = select(discover(dir.path()), &[]);
+        assert_eq!(names(&cpu), ["x86_pkg_temp", "coretemp/Package id 0", "coretemp/temp2"]);
+
+        let filtered = select(discover(dir.path()), &["PACKAGE".to_string(), "nvme".to_string()]);
+        assert_eq!(names(&filtered), ["nvme/temp1", "coretemp/Package id 0"]);
+        assert!(select(discover(dir.path()), &["gpu".to_string()]).is_empty());
+
+        // Without a known CPU sensor, every sensor is watched
+        let other = TestDir::new("thermal-other");
+        other.write("class/thermal/thermal_zone0/type", "acpitz\n");
+        other.write("class/thermal/thermal_zone0/temp", "50000\n");
+        other.write("class/hwmon/hwmon0/name", "nvme\n");
+        other.write("class/hwmon/hwmon0/temp1_input", "38000\n");
+        assert_eq!(names(&select(discover(other.path()), &[])), ["acpitz", "nvme/temp1"]);
+    }
+
+    #[test]
+    fn no_sensors_no_monitor() {
+        let dir = TestDir::new("thermal-empty");
+        assert!(Monitor::new(config(dir.path(), 80.0, ThermalAction::Threads)).is_none());
+    }
+
+    #[test]
+    fn backs_off_by_steps_and_resumes_below_the_hysteresis() {
+        let dir = TestDir::new("thermal-steps");
+        dir.write("class/thermal/thermal_zone0/type", "x86_pkg_temp\n");
+        let temp = dir.write("class/thermal/thermal_zone0/temp", "50000\n");
+        dir.write("class/hwmon/hwmon0/name", "k10temp\n");
+        dir.write("class/hwmon/hwmon0/temp1_input", "30000\n");
+        let monitor = Monitor::new(config(dir.path(), 80.0, ThermalAction::Threads)).unwrap();
+        let poll = |millidegrees: &str| {
+            fs::write(&temp, millidegrees).unwrap();
+            let temperatures = monitor.poll();
+            assert_eq!(temperatures.len(), 2);
+            monitor.capacity()
+        };
+
+        assert_eq!(poll("50000"), 1.0);
+        // At the limit holds; the hottest sensor above it steps down
+        assert_eq!(poll("80000"), 1.0);
+        assert_eq!(poll("80500"), 0.75);
+        assert_eq!(poll("90000"), 0.5625);
+        assert_eq!(monitor.threads(8), 5);
+        assert_eq!(monitor.duty_cycle(), 1.0);
+        // Between the resume point (limit - 5) and the limit it holds
+        assert_eq!(poll("79000"), 0.5625);
+        assert_eq!(poll("75000"), 0.5625);
+        assert_eq!(poll("74900"), 0.75);
+        assert_eq!(poll("60000"), 1.0);
+        assert_eq!(poll("60000"), 1.0);
+
+        // Down to the floor, and back up step by step
+        for _ in 0..20 {
+            poll("95000");
+        }
+        assert_eq!(monitor.capacity(), MIN_CAPACITY);
+        assert_eq!(monitor.threads(8), 1);
+        let mut steps = 0;
+        while poll("40000") < 1.0 {
+            steps += 1;
+        }
+        assert_eq!(steps, 8);
+    }
+
+    #[test]
+    fn duty_cycle_action_and_explicit_resume() {
+        let dir = TestDir::new("thermal-duty");
+        dir.write("class/thermal/thermal_zone0/type", "cpu-thermal\n");
+        let temp = dir.write("class/thermal/thermal_zone0/temp", "70000\n");
+        let monitor = Monitor::new(ThermalConfig {
+            resume: Some(60.0),
+            ..config(dir.path(), 65.0, ThermalAction::DutyCycle)
+        })
+        .unwrap();
+        monitor.poll();
+        assert_eq!(monitor.duty_cycle(), 0.75);
+        assert_eq!(monitor.threads(8), 8);
+        fs::write(&temp, "61000").unwrap();
+        monitor.poll();
+        assert_eq!(monitor.duty_cycle(), 0.75);
+        fs::write(&temp, "59000").unwrap();
+        monitor.poll();
+        assert_eq!(monitor.duty_cycle(), 1.0);
+    }
+
+    #[test]
+    fn without_a_limit_only_reports() {
+        let dir = machine();
+        let monitor = Monitor::new(config(dir.path(), 0.0, ThermalAction::Threads)).unwrap();
+        fs::write(dir.path().join("class/thermal/thermal_zone10/temp"), "120000").unwrap();
+        let temperatures = monitor.poll();
+        assert_eq!(temperatures.len(), 3);
+        assert_eq!(temperatures[0].celsius, 120.0);
+        assert_eq!(monitor.capacity(), 1.0);
+        assert_eq!(monitor.to_string(), "x86_pkg_temp, coretemp/Package id 0, coretemp/temp2");
+    }
+}
@@ -0,0 +1,332 @@
+//! Hashrate and duty-cycle limits of `--mine-loop` (`--max-hashrate`,
+//! `--device-max-hashrate`, `--duty-cycle`). Workers report their hashes to their
+//! device's `Pacer`, which sleeps the calling thread while it is ahead of a limit.
+//! Each limit is a token bucket: hashes (or busy time) drain it and it refills at the
+//! limit's rate, so short bursts average out instead of sleeping after every report.
+//! The limits are read from the runtime settings on every report, so changes through
+//! the control API or a config reload apply at once. A CPU device's pacer also applies
+//! the back-off of the thermal monitor.
+
+use crate::control::MinerControl;
+use crate::thermal::Monitor;
+use crate::watchdog::Heartbeat;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Seconds of refill a bucket holds, bounding the burst after an idle period
+const BURST: f64 = 0.05;
+/// Debt below which a worker keeps going, so it sleeps in slices rather than per report
+const MIN_WAIT: Duration = Duration::from_millis(20);
+/// Longest sleep between checks for shutdown, refresh and changed limits
+const WAIT_SLICE: Duration = Duration::from_millis(100);
+
+/// Limits in the runtime settings
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ThrottleSettings {
+    /// Hashes per second of all devices together (0 = unlimited)
+    pub max_hashrate: f64,
+    /// Hashes per second of each device (0 = unlimited)
+    pub device_max_hashrate: f64,
+    /// Fraction of the time each device mines (1 = always)
+    pub duty_cycle: f64,
+}
+
+impl Default for ThrottleSettings {
+    fn default() -> Self {
+        Self {
+            max_hashrate: 0.0,
+            device_max_hashrate: 0.0,
+            duty_cycle: 1.0,
+        }
+    }
+}
+
+impl ThrottleSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.max_hashrate >= 0.0 && self.device_max_hashrate >= 0.0) {
+            return Err("max_hashrate and device_max_hashrate must be 0 (unlimited) or more".to_string());
+        }
+        if !(self.duty_cycle > 0.0 && self.duty_cycle <= 1.0) {
+            return Err(format!("duty_cycle {} is outside 0.0 (exclusive) to 1.0", self.duty_cycle));
+        }
+        Ok(())
+    }
+
+    pub fn is_limited(&self) -> bool {
+        *self != Self::default()
+    }
+}
+
+impl fmt::Display for ThrottleSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rate = |rate: f64| if rate > 0.0 { format!("{:.2} MH/s", rate / 1e6) } else { "unlimited".to_string() };
+        write!(
+            f,
+            "total {}, per device {}, duty cycle {:.0}%",
+            rate(self.max_hashrate),
+            rate(self.device_max_hashrate),
+            self.duty_cycle * 100.0
+        )
+    }
+}
+
+/// Parses a hashrate like `750000`, `500k`, `2.5M` or `1G` (decimal multiples)
+pub fn parse_hashrate(s: &str) -> Result<f64, String> {
+    let s = s.trim();
+    let (digits, multiplier) = match s.char_indices().last() {
+        Some((i, 'k' | 'K')) => (&s[..i], 1e3),
+        Some((i, 'm' | 'M')) => (&s[..i], 1e6),
+        Some((i, 'g' | 'G')) => (&s[..i], 1e9),
+        _ => (s, 1.0),
+    };
+    match digits.parse::<f64>() {
+        Ok(n) if n.is_finite() && n >= 0.0 => Ok(n * multiplier),
+        _ => Err(format!("invalid hashrate: {}", s)),
+    }
+}
+
+/// Tokens refilled at a given rate up to `BURST` seconds' worth, or up to what is being
+/// taken so that a report covering more than `BURST` of work is not cut short. The balance
+/// goes negative when more is taken than refilled; that debt is the time to wait.
+struct Bucket {
+    tokens: f64,
+    last: Instant,
+}
+
+impl Bucket {
+    fn new() -> Self {
+        Self {
+            tokens: 0.0,
+            last: Instant::now(),
+        }
+    }
+
+    /// Takes `amount` and returns how long until the balance is back to zero
+    fn take(&mut self, amount: f64, rate: f64) -> Duration {
+        self.take_at(amount, rate, Instant::now())
+    }
+
+    fn take_at(&mut self, amount: f64, rate: f64, now: Instant) -> Duration {
+        let refill = rate * now.saturating_duration_since(self.last).as_secs_f64();
+        self.tokens = (self.tokens + refill).min((rate * BURST).max(amount));
+        self.last = now;
+        self.tokens -= amount;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / rate)
+        }
+    }
+}
+
+thread_local! {
+    /// Busy-time bucket of the calling worker thread and when it last resumed work.
+    /// Threads of a device mine concurrently, so each keeps its own duty cycle.
+    static DUTY: RefCell<Option<(Bucket, Instant)>> = const { RefCell::new(None) };
+}
+
+/// Limits shared by every device's pacer
+pub struct Throttle {
+    control: Arc<MinerControl>,
+    /// Hashes of all devices against `max_hashrate`
+    global: Mutex<Bucket>,
+}
+
+impl Throttle {
+    pub fn new(control: Arc<MinerControl>) -> Arc<Self> {
+        Arc::new(Self {
+            control,
+            global: Mutex::new(Bucket::new()),
+        })
+    }
+}
+
+/// Paces the workers of one device
+pub struct Pacer {
+    throttle: Arc<Throttle>,
+    /// Hashes of this device against `device_max_hashrate`
+    device: Mutex<Bucket>,
+    thermal: Option<Arc<Monitor>>,
+}
+
+impl Pacer {
+    pub fn new(throttle: &Arc<Throttle>, thermal: Option<Arc<Monitor>>) -> Arc<Self> {
+        Arc::new(Self {
+            throttle: Arc::clone(throttle),
+            device: Mutex::new(Bucket::new()),
+            thermal,
+        })
+    }
+
+    /// Limits of the runtime settings, with the duty cycle lowered by a thermal back-off
+    fn limits(&self) -> ThrottleSettings {
+        let mut limits = self.throttle.control.throttle();
+        if let Some(thermal) = &self.thermal {
+            limits.duty_cycle = limits.duty_cycle.min(thermal.duty_cycle());
+        }
+        limits
+    }
+
+    /// Worker threads to run of `configured`, fewer while the thermal monitor backs off threads
+    pub fn threads(&self, configured: usize) -> usize {
+        self.thermal.as_ref().map_or(configured, |thermal| thermal.threads(configured))
+    }
+
+    /// Records `hashes` computed by the calling thread since its previous report and
+    /// sleeps while any limit is exceeded. A report of 0 hashes marks the start of work,
+    /// so a worker thread makes one before it starts. The sleep holds `heartbeat` and
+    /// ends early on shutdown, a refresh request, changed limits or when `stop` is true.
+    pub fn pace(&self, hashes: u64, heartbeat: Option<&Heartbeat>, stop: &dyn Fn() -> bool) {
+        let limits = self.limits();
+        let now = Instant::now();
+        let busy = DUTY.with_borrow_mut(|duty| {
+            let (_, resumed) = duty.get_or_insert_with(|| (Bucket::new(), now));
+            now - std::mem::replace(resumed, now)
+        });
+        if hashes == 0 || !limits.is_limited() {
+            return;
+        }
+
+        let mut wait = Duration::ZERO;
+        if limits.device_max_hashrate > 0.0 {
+            wait = wait.max(self.device.lock().take(hashes as f64, limits.device_max_hashrate));
+        }
+        if limits.max_hashrate > 0.0 {
+            wait = wait.max(self.throttle.global.lock().take(hashes as f64, limits.max_hashrate));
+        }
+        if limits.duty_cycle < 1.0 {
+            let duty = DUTY.with_borrow_mut(|duty| {
+                duty.as_mut()
+                    .map_or(Duration::ZERO, |(bucket, _)| bucket.take(busy.as_secs_f64(), limits.duty_cycle))
+            });
+            wait = wait.max(duty);
+        }
+        if wait < MIN_WAIT {
+            return;
+        }
+
+        let control = &self.throttle.control;
+        let until = now + wait;
+        loop {
+            if let Some(heartbeat) = heartbeat {
+                heartbeat.hold();
+            }
+            let left = until.saturating_duration_since(Instant::now());
+            if left.is_zero()
+                || stop()
+                || control.is_shutting_down()
+                || control.refresh_requested()
+                || self.limits() != limits
+            {
+                break;
+            }
+            std::thread::sleep(left.min(WAIT_SLICE));
+        }
+        // Time asleep is not busy time
+        DUTY.with_borrow_mut(|duty| {
+            if let Some((_, resumed)) = duty {
+                *resumed = Instant::now();
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    fn close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-6 * expected.abs().max(1.0), "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn bucket_refills_at_its_rate_up_to_the_burst() {
+        let start = Instant::now();
+        let mut bucket = Bucket { tokens: 0.0, last: start };
+        // 10 hashes at 1000/s: 10ms of debt, paid off 10ms later
+        assert_eq!(bucket.take_at(10.0, 1000.0, start), ms(10));
+        assert_eq!(bucket.take_at(0.0, 1000.0, start + ms(10)), Duration::ZERO);
+        close(bucket.tokens, 0.0);
+        // An idle period refills no more than BURST seconds' worth
+        assert_eq!(bucket.take_at(0.0, 1000.0, start + ms(10_010)), Duration::ZERO);
+        close(bucket.tokens, 1000.0 * BURST);
+        assert_eq!(bucket.take_at(60.0, 1000.0, start + ms(10_010)), ms(10));
+    }
+
+    /// Rate a worker ends up at when it computes `chunk` per `work` of busy time and
+    /// sleeps off whatever `take` asks for
+    fn paced_rate(chunk: f64, work: Duration, rate: f64) -> f64 {
+        let start = Instant::now();
+        let mut bucket = Bucket { tokens: 0.0, last: start };
+        let mut now = start;
+        for _ in 0..100 {
+            now += work;
+            now += bucket.take_at(chunk, rate, now);
+        }
+        100.0 * chunk / (now - start).as_secs_f64()
+    }
+
+    #[test]
+    fn hashrate_limit_holds_for_small_and_large_reports() {
+        // 50k hashes/s limited to 10k/s, reported every 1ms (CPU) or every 100ms (GPU launch)
+        close(paced_rate(50.0, ms(1), 10_000.0).round(), 10_000.0);
+        close(paced_rate(5000.0, ms(100), 10_000.0).round(), 10_000.0);
+        // Under the limit nothing sleeps
+        close(paced_rate(5.0, ms(1), 10_000.0), 5000.0);
+    }
+
+    #[test]
+    fn duty_cycle_paces_busy_time() {
+        // The duty bucket takes seconds of busy time at `duty_cycle` seconds per second
+        for (busy, duty) in [(ms(1), 0.5), (ms(100), 0.25), (ms(500), 0.75)] {
+            let fraction = paced_rate(busy.as_secs_f64(), busy, duty);
+            close((fraction * 1000.0).round() / 1000.0, duty);
+        }
+        let start = Instant::now();
+        let mut bucket = Bucket { tokens: 0.0, last: start };
+        // 100ms busy at 25%: 300ms idle
+        assert_eq!(bucket.take_at(0.1, 0.25, start + ms(100)), ms(300));
+    }
+
+    #[test]
+    fn hashrates_parse_with_decimal_suffixes() {
+        for (text, expected) in [
+            ("750000", 750_000.0),
+            ("500k", 500_000.0),
+            ("500K", 500_000.0),
+            ("1.5M", 1_500_000.0),
+            ("2.5m", 2_500_000.0),
+            ("1G", 1e9),
+            (" 0 ", 0.0),
+        ] {
+            assert_eq!(parse_hashrate(text), Ok(expected), "{}", text);
+        }
+        for text in ["", "M", "-1k", "fast", "1T", "inf", "NaN"] {
+            assert!(parse_hashrate(text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn settings_validate_and_display() {
+        let limits = ThrottleSettings {
+            max_hashrate: 2.5e6,
+            device_max_hashrate: 0.0,
+            duty_cycle: 0.5,
+        };
+        assert!(limits.validate().is_ok() && limits.is_limited());
+        assert_eq!(limits.to_string(), "total 2.50 MH/s, per device unlimited, duty cycle 50%");
+        assert!(!ThrottleSettings::default().is_limited());
+        for duty_cycle in [0.0, 1.5, f64::NAN] {
+            assert!(ThrottleSettings { duty_cycle, ..limits }.validate().is_err());
+        }
+        assert!(ThrottleSettings { max_hashrate: -1.0, ..limits }.validate().is_err());
+    }
+}
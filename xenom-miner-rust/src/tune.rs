@@ -0,0 +1,401 @@
+//! Auto-tuning of work sizes (`--auto-tune`). Each backend is measured on a synthetic
+//! header at a few candidate sizes and the fastest is kept; the work of one search round
+//! is then sized to take about the target latency, so the loop checks for stale work
+//! often without leaving the device idle. Profiles are cached per hardware fingerprint,
+//! so later starts on the same machine skip the measurements.
+
+use crate::affinity;
+use crate::cpu_miner::{self, NonceRange};
+use crate::gpu_miner::{GpuMiner, Launch};
+use log::{debug, info};
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Length of the synthetic header: the node's serialized header up to the mutation vector
+const HEADER_LEN: usize = 96;
+/// Time each CPU thread count is measured for
+const CPU_SAMPLE: Duration = Duration::from_millis(300);
+/// Nonces per GPU thread in each measured brute-force launch
+const GPU_ITERATIONS: u64 = 64;
+/// Blocks per multiprocessor tried for the brute-force kernel
+const BLOCKS_PER_SM: [u32; 4] = [4, 8, 16, 32];
+const THREADS_PER_BLOCK: [u32; 5] = [64, 128, 256, 512, 1024];
+/// GA populations tried, smallest first
+const POPULATIONS: [usize; 5] = [256, 1024, 4096, 16384, 65536];
+/// Generations timed per GA population
+const GA_GENERATIONS: usize = 3;
+/// A bigger candidate must be this much faster to be preferred over a smaller one
+const MIN_GAIN: f64 = 1.02;
+
+/// What a profile was tuned for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Workload {
+    CpuBrute,
+    GpuBrute,
+    GpuGa,
+}
+
+/// Hardware a profile applies to
+#[derive(Debug, Clone)]
+pub struct Device {
+    pub description: String,
+    /// Hash of the description and miner version
+    pub fingerprint: String,
+}
+
+impl Device {
+    fn new(description: String) -> Self {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(description.as_bytes());
+        hasher.update(env!("CARGO_PKG_VERSION").as_bytes());
+        let fingerprint = hex::encode(&hasher.finalize().as_bytes()[..8]);
+        Self { description, fingerprint }
+    }
+
+    /// This machine's CPU model and core counts, and the CPUs mining is pinned to
+    pub fn cpu() -> Self {
+        let model = std::fs::read_to_string("/proc/cpuinfo")
+            .ok()
+            .and_then(|info| {
+                info.lines()
+                    .find(|line| line.starts_with("model name"))
+                    .and_then(|line| line.split(':').nth(1))
+                    .map(|model| model.trim().to_string())
+            })
+            .unwrap_or_else(|| "unknown CPU".to_string());
+        let placement = affinity::current();
+        let pinned = if placement.is_pinned() {
+            format!(", CPUs {}", placement.cpus())
+        } else {
+            String::new()
+        };
+        Self::new(format!(
+            "{} ({} logical / {} physical cores, {}{})",
+            model,
+            num_cpus::get(),
+            num_cpus::get_physical(),
+            std::env::consts::ARCH,
+            pinned
+        ))
+    }
+
+    pub fn gpu(miner: &GpuMiner) -> Self {
+        Self::new(miner.hardware())
+    }
+}
+
+/// Work sizes measured on one device
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    pub workload: Workload,
+    pub device: String,
+    pub fingerprint: String,
+    /// Target round duration the profile was tuned for
+    pub latency_ms: u64,
+    pub mv_len: usize,
+    /// CPU worker threads
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threads: Option<usize>,
+    /// Brute-force kernel launch geometry
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub launch: Option<Launch>,
+    /// GPU GA population
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub population: Option<usize>,
+    /// Hashes per search round at the target latency
+    pub round_hashes: u64,
+    /// Measured hashes per second at the chosen sizes
+    pub hashrate: f64,
+    /// Unix time of the measurement
+    pub tuned_at: u64,
+}
+
+impl Profile {
+    fn new(workload: Workload, device: &Device, latency: Duration, mv_len: usize, hashrate: f64) -> Self {
+        Self {
+            workload,
+            device: device.description.clone(),
+            fingerprint: device.fingerprint.clone(),
+            latency_ms: latency.as_millis() as u64,
+            mv_len,
+            threads: None,
+            launch: None,
+            population: None,
+            round_hashes: (hashrate * latency.as_secs_f64()).max(1.0) as u64,
+            hashrate,
+            tuned_at: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()),
+        }
+    }
+
+    /// `--batches` giving a round of about `round_hashes` when a batch is `batch_hashes`
+    pub fn batches(&self, batch_hashes: u64) -> usize {
+        self.round_hashes.div_ceil(batch_hashes.max(1)).max(1) as usize
+    }
+}
+
+/// Tuned profiles, one per workload and fingerprint, kept in a JSON file
+pub struct Cache {
+    path: PathBuf,
+    profiles: Vec<Profile>,
+}
+
+impl Cache {
+    /// Reads the cache, starting empty if the file is missing or unreadable
+    pub fn load(path: &Path) -> Self {
+        let profiles = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+        Self {
+            path: path.to_path_buf(),
+            profiles,
+        }
+    }
+
+    /// Cached profile of `device`, if it was tuned for the same latency and mutation vector length
+    pub fn get(&self, workload: Workload, device: &Device, latency: Duration, mv_len: usize) -> Option<&Profile> {
+        self.profiles.iter().find(|p| {
+            p.workload == workload
+                && p.fingerprint == device.fingerprint
+                && p.latency_ms == latency.as_millis() as u64
+                && p.mv_len == mv_len
+        })
+    }
+
+    /// Adds a profile, replacing any earlier one for the same workload and hardware
+    pub fn insert(&mut self, profile: Profile) {
+        self.profiles
+            .retain(|p| p.workload != profile.workload || p.fingerprint != profile.fingerprint);
+        self.profiles.push(profile);
+    }
+
+    /// Writes the cache through a temporary file, so a crash never leaves half a file
+    pub fn save(&self) -> std::io::Result<()> {
+        if let Some(dir) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string_pretty(&self.profiles)?;
+        let tmp = self.path.with_extension("tmp");
+        std::fs::write(&tmp, json)?;
+        std::fs::rename(&tmp, &self.path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// `$XDG_CACHE_HOME/xenom-miner/tune.json`, else `~/.cache/xenom-miner/tune.json`
+pub fn default_cache_path() -> PathBuf {
+    std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .unwrap_or_default()
+        .join("xenom-miner")
+        .join("tune.json")
+}
+
+fn synthetic_header() -> Vec<u8> {
+    (0..HEADER_LEN).map(|i| i as u8).collect()
+}
+
+/// Keeps the fastest candidate, preferring the earlier (smaller) one unless a later one
+/// beats it by `MIN_GAIN`
+fn keep_best<T>(best: &mut Option<(T, f64)>, candidate: T, rate: f64) {
+    if best.as_ref().is_none_or(|(_, best)| rate > best * MIN_GAIN) {
+        *best = Some((candidate, rate));
+    }
+}
+
+/// Measures the CPU brute-force search at thread counts from half the physical cores
+/// up to every logical core (of the CPUs mining is pinned to)
+pub fn cpu(device: &Device, mv_len: usize, latency: Duration) -> Profile {
+    let (logical, physical) = affinity::current().cores();
+    let mut candidates: Vec<usize> = (0..usize::BITS)
+        .map(|k| 1usize << k)
+        .skip_while(|&n| n < physical / 2)
+        .take_while(|&n| n < logical)
+        .chain([physical, logical])
+        .collect();
+    candidates.sort_unstable();
+    candidates.dedup();
+
+    info!("🎛️  Auto-tuning {}: threads {:?}", device.description, candidates);
+    let header = synthetic_header();
+    let mut best = None;
+    for threads in candidates {
+        let found = AtomicBool::new(false);
+        let start = Instant::now();
+        let deadline = start + CPU_SAMPLE;
+        let range = NonceRange {
+            extranonce: 0,
+            start: 0,
+            count: u64::MAX,
+        };
+        let outcome =
+            cpu_miner::search_range(&header, mv_len, &[0; 32], range, threads, &found, &|_| Instant::now() < deadline);
+        let rate = outcome.hashes as f64 / start.elapsed().as_secs_f64();
+        debug!("   threads={}: {:.2} MH/s", threads, rate / 1e6);
+        keep_best(&mut best, threads, rate);
+    }
+    let (threads, rate) = best.unwrap_or((logical, 0.0));
+    Profile {
+        threads: Some(threads),
+        ..Profile::new(Workload::CpuBrute, device, latency, mv_len, rate)
+    }
+}
+
+/// Measures the brute-force kernel over threads per block and blocks per multiprocessor.
+/// Leaves the fastest launch set on the miner.
+pub fn gpu_bruteforce(miner: &mut GpuMiner, device: &Device, mv_len: usize, latency: Duration) -> Option<Profile> {
+    let (max_threads, multiprocessors) = miner.limits();
+    info!("🎛️  Auto-tuning {}: brute-force launch geometry", device.description);
+    let header = synthetic_header();
+    let target = BigUint::default();
+    let launches = THREADS_PER_BLOCK
+        .into_iter()
+        .filter(|&threads| threads <= max_threads)
+        .flat_map(|threads_per_block| {
+            BLOCKS_PER_SM.map(|per_sm| Launch {
+                threads_per_block,
+                blocks: multiprocessors * per_sm,
+            })
+        });
+
+    // The first launch also pays for module loading and clock ramp-up
+    let _ = miner.measure(|m| {
+        let nonces = m.launch().threads() * GPU_ITERATIONS;
+        m.mine_bruteforce_nonce_gpu(&header, &target, 0, nonces, &AtomicBool::new(false), None);
+    });
+    let mut best = None;
+    for launch in launches {
+        miner.set_launch(launch);
+        let rate = miner.measure(|m| {
+            let nonces = launch.threads() * GPU_ITERATIONS;
+            m.mine_bruteforce_nonce_gpu(&header, &target, 0, nonces, &AtomicBool::new(false), None);
+        });
+        debug!(
+            "   {}x{}: {}",
+            launch.blocks,
+            launch.threads_per_block,
+            rate.map_or("failed".to_string(), |rate| format!("{:.2} MH/s", rate / 1e6))
+        );
+        if let Some(rate) = rate {
+            keep_best(&mut best, launch, rate);
+        }
+    }
+    let (launch, rate) = best?;
+    miner.set_launch(launch);
+    Some(Profile {
+        launch: Some(launch),
+        ..Profile::new(Workload::GpuBrute, device, latency, mv_len, rate)
+    })
+}
+
+/// Measures GA generations at growing populations until a generation exceeds the
+/// latency. Leaves the fastest population set on the miner.
+pub fn gpu_ga(
+    miner: &mut GpuMiner,
+    device: &Device,
+    mv_len: usize,
+    latency: Duration,
+    mutation_rate: f32,
+) -> Option<Profile> {
+    info!("🎛️  Auto-tuning {}: GA population", device.description);
+    let header = synthetic_header();
+    let target = BigUint::default();
+    let mut best = None;
+    for population in POPULATIONS {
+        miner.set_population_size(population);
+        let start = Instant::now();
+        let rate = miner.measure(|m| {
+            m.mine_with_ga(&header, &target, GA_GENERATIONS, mutation_rate, &AtomicBool::new(false), None);
+        });
+        let generation = start.elapsed() / GA_GENERATIONS as u32;
+        debug!(
+            "   population={}: {} ({:?} per generation)",
+            population,
+            rate.map_or("failed".to_string(), |rate| format!("{:.2} MH/s", rate / 1e6)),
+            generation
+        );
+        match rate {
+            Some(rate) if generation <= latency => keep_best(&mut best, population, rate),
+            _ => break,
+        }
+    }
+    let (population, rate) = best?;
+    miner.set_population_size(population);
+    Some(Profile {
+        population: Some(population),
+        ..Profile::new(Workload::GpuGa, device, latency, mv_len, rate)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    const LATENCY: Duration = Duration::from_millis(2000);
+
+    fn cpu_profile(device: &Device, mv_len: usize, threads: usize) -> Profile {
+        Profile {
+            threads: Some(threads),
+            ..Profile::new(Workload::CpuBrute, device, LATENCY, mv_len, 5e6)
+        }
+    }
+
+    #[test]
+    fn cache_round_trips_through_its_file() {
+        let dir = TestDir::new("tune-cache");
+        let path = dir.path().join("nested").join("tune.json");
+        let (cpu, gpu) = (Device::new("test CPU".to_string()), Device::new("test GPU".to_string()));
+
+        let mut cache = Cache::load(&path);
+        assert!(cache.get(Workload::CpuBrute, &cpu, LATENCY, 16).is_none());
+        cache.insert(cpu_profile(&cpu, 16, 4));
+        cache.insert(Profile {
+            launch: Some(Launch::default()),
+            ..Profile::new(Workload::GpuBrute, &gpu, LATENCY, 16, 2e9)
+        });
+        // A retune replaces the earlier profile of the same workload and hardware
+        cache.insert(cpu_profile(&cpu, 16, 8));
+        cache.save().unwrap();
+        assert!(!path.with_extension("tmp").exists());
+
+        let loaded = Cache::load(&path);
+        assert_eq!(loaded.path(), path);
+        let profile = loaded.get(Workload::CpuBrute, &cpu, LATENCY, 16).unwrap();
+        assert_eq!(profile.threads, Some(8));
+        assert_eq!(profile.round_hashes, 10_000_000);
+        assert_eq!(profile.device, "test CPU");
+        let profile = loaded.get(Workload::GpuBrute, &gpu, LATENCY, 16).unwrap();
+        assert_eq!(profile.launch, Some(Launch::default()));
+        assert!(loaded.get(Workload::GpuGa, &gpu, LATENCY, 16).is_none());
+    }
+
+    #[test]
+    fn mismatched_profiles_miss() {
+        let (cpu, other) = (Device::new("test CPU".to_string()), Device::new("other CPU".to_string()));
+        let mut cache = Cache::load(Path::new("/nonexistent/tune.json"));
+        cache.insert(cpu_profile(&cpu, 16, 4));
+
+        assert!(cache.get(Workload::CpuBrute, &cpu, LATENCY, 16).is_some());
+        assert!(cache.get(Workload::CpuBrute, &other, LATENCY, 16).is_none());
+        assert!(cache.get(Workload::CpuBrute, &cpu, LATENCY, 8).is_none());
+        assert!(cache.get(Workload::CpuBrute, &cpu, Duration::from_millis(500), 16).is_none());
+        assert!(cache.get(Workload::GpuBrute, &cpu, LATENCY, 16).is_none());
+    }
+
+    #[test]
+    fn unreadable_cache_starts_empty() {
+        let dir = TestDir::new("tune-corrupt");
+        let path = dir.write("tune.json", "{ not json");
+        let cpu = Device::new("test CPU".to_string());
+        assert!(Cache::load(&path).get(Workload::CpuBrute, &cpu, LATENCY, 16).is_none());
+    }
+}
@@ -0,0 +1,120 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Times a stalled device is restarted before the watchdog drops it
+pub const MAX_RESTARTS: u64 = 3;
+
+/// Liveness of one mining device's worker. The worker stamps it whenever it
+/// computes hashes; `mine_loop` checks it while waiting for the round to end.
+pub struct Heartbeat {
+    epoch: Instant,
+    busy: AtomicBool,
+    /// Milliseconds since `epoch` of the last progress
+    last_progress: AtomicU64,
+    hashes: AtomicU64,
+}
+
+impl Default for Heartbeat {
+    fn default() -> Self {
+        Self {
+            epoch: Instant::now(),
+            busy: AtomicBool::new(false),
+            last_progress: AtomicU64::new(0),
+            hashes: AtomicU64::new(0),
+        }
+    }
+}
+
+impl Heartbeat {
+    fn stamp(&self) {
+        self.last_progress.store(self.epoch.elapsed().as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// Marks the start of a round
+    pub fn start(&self) {
+        self.stamp();
+        self.busy.store(true, Ordering::Relaxed);
+    }
+
+    /// Records `hashes` computed since the previous beat
+    pub fn beat(&self, hashes: u64) {
+        if hashes > 0 {
+            self.hashes.fetch_add(hashes, Ordering::Relaxed);
+            self.stamp();
+        }
+    }
+
+    /// Restarts the stall timer without progress, for waits that are not stalls (pause)
+    pub fn hold(&self) {
+        self.stamp();
+    }
+
+    /// Marks the end of a round
+    pub fn finish(&self) {
+        self.busy.store(false, Ordering::Relaxed);
+    }
+
+    /// Time since the last progress, while a round is running
+    pub fn stalled_for(&self) -> Option<Duration> {
+        if !self.busy.load(Ordering::Relaxed) {
+            return None;
+        }
+        let last = Duration::from_millis(self.last_progress.load(Ordering::Relaxed));
+        Some(self.epoch.elapsed().saturating_sub(last))
+    }
+
+    /// Time since the last progress, once it has reached `timeout` (None: never stalled)
+    pub fn stalled(&self, timeout: Option<Duration>) -> Option<Duration> {
+        self.stalled_for().filter(|stalled| timeout.is_some_and(|timeout| *stalled >= timeout))
+    }
+
+    /// Hashes reported since the worker was created
+    pub fn hashes(&self) -> u64 {
+        self.hashes.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A heartbeat whose worker last made progress `age` ago
+    fn aged(age: Duration) -> Heartbeat {
+        let heartbeat = Heartbeat {
+            epoch: Instant::now() - age,
+            ..Default::default()
+        };
+        heartbeat.busy.store(true, Ordering::Relaxed);
+        heartbeat
+    }
+
+    #[test]
+    fn stall_is_the_time_since_progress_in_a_round() {
+        let timeout = Some(Duration::from_secs(30));
+        let idle = Heartbeat::default();
+        assert_eq!(idle.stalled_for(), None);
+        assert_eq!(idle.stalled(Some(Duration::ZERO)), None, "no round, no stall");
+
+        let hung = aged(Duration::from_secs(45));
+        assert!(hung.stalled_for().unwrap() >= Duration::from_secs(45));
+        assert!(hung.stalled(timeout).unwrap() >= Duration::from_secs(45));
+        assert_eq!(hung.stalled(None), None, "--worker-timeout 0 turns the watchdog off");
+
+        let slow = aged(Duration::from_secs(10));
+        assert_eq!(slow.stalled(timeout), None);
+
+        // Progress or a hold restarts the timer; a beat without hashes does not
+        hung.beat(0);
+        assert!(hung.stalled(timeout).is_some());
+        hung.beat(4096);
+        assert_eq!(hung.stalled(timeout), None);
+        assert_eq!(hung.hashes(), 4096);
+        let paused = aged(Duration::from_secs(45));
+        paused.hold();
+        assert_eq!(paused.stalled(timeout), None);
+        assert_eq!(paused.hashes(), 0);
+
+        paused.finish();
+        assert_eq!(paused.stalled_for(), None);
+    }
+}